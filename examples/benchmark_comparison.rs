@@ -1,8 +1,9 @@
 //! Benchmark demonstrating ContextGraph performance with PetGraph backend
 
 use cim_contextgraph::{ContextGraph, Label};
-use std::time::Instant;
+use petgraph::visit::EdgeRef;
 use rand::Rng;
+use std::time::Instant;
 
 fn main() {
     println!("ContextGraph Performance Benchmark");
@@ -18,23 +19,32 @@ fn main() {
     println!("================");
     println!("ContextGraph Performance:");
     println!("  - Total time: {:.2}s", time.as_secs_f64());
-    println!("  - Nodes: {graph.graph.node_count(}"));
-    println!("  - Edges: {graph.graph.edge_count(}"));
+    println!("  - Nodes: {}", graph.graph.node_count());
+    println!("  - Edges: {}", graph.graph.edge_count());
 
     // Test some algorithms
     println!("\n\nTesting PetGraph Algorithms:");
     let algo_start = Instant::now();
 
-    println!("  - Is cyclic: {graph.is_cyclic(}"));
-    println!("  - Strongly connected components: {graph.strongly_connected_components(}").len());
+    println!("  - Is cyclic: {}", graph.is_cyclic());
+    println!(
+        "  - Strongly connected components: {}",
+        graph.strongly_connected_components().len()
+    );
 
     // Try topological sort (will fail if cyclic)
     match graph.topological_sort() {
-        Ok(sorted) => println!("  - Topological sort succeeded: {sorted.len(} nodes")),
+        Ok(sorted) => println!("  - Topological sort succeeded: {} nodes", sorted.len()),
         Err(_) => println!("  - Topological sort failed (graph has cycles)"),
     }
 
-    println!("  - Algorithm tests took: {:.3}s", algo_start.elapsed().as_secs_f64());
+    println!(
+        "  - Algorithm tests took: {:.3}s",
+        algo_start.elapsed().as_secs_f64()
+    );
+
+    benchmark_serialization(&graph);
+    benchmark_clones(&graph);
 }
 
 fn benchmark_contextgraph() -> (ContextGraph<String, String>, std::time::Duration) {
@@ -59,27 +69,145 @@ fn benchmark_contextgraph() -> (ContextGraph<String, String>, std::time::Duratio
         node_ids.push(node_id);
     }
 
-    println!("  - Added 10,000 nodes in {:.3}s", node_start.elapsed().as_secs_f64());
+    println!(
+        "  - Added 10,000 nodes in {:.3}s",
+        node_start.elapsed().as_secs_f64()
+    );
 
     // Add random edges
     let edge_start = Instant::now();
-    let mut rng = rand::thread_rng();
+    let mut rng = rand::rng();
     let mut edge_count = 0;
 
     while edge_count < 5_000 {
-        let from_idx = rng.gen_range(0..10_000);
-        let to_idx = rng.gen_range(0..10_000);
+        let from_idx = rng.random_range(0..10_000);
+        let to_idx = rng.random_range(0..10_000);
 
         if from_idx != to_idx {
             let relationship = format!("knows_{edge_count}");
-            if graph.add_edge(node_ids[from_idx], node_ids[to_idx], relationship).is_ok() {
+            if graph
+                .add_edge(node_ids[from_idx], node_ids[to_idx], relationship)
+                .is_ok()
+            {
                 edge_count += 1;
             }
         }
     }
 
-    println!("  - Added 5,000 edges in {:.3}s", edge_start.elapsed().as_secs_f64());
+    println!(
+        "  - Added 5,000 edges in {:.3}s",
+        edge_start.elapsed().as_secs_f64()
+    );
 
     let total_time = start.elapsed();
     (graph, total_time)
 }
+
+/// Compare the compact binary format against JSON (JGF) on the same graph
+fn benchmark_serialization(graph: &ContextGraph<String, String>) {
+    println!("\n\nSerialization (binary vs JSON):");
+
+    let start = Instant::now();
+    let binary = graph.to_binary().expect("binary encoding");
+    let binary_write = start.elapsed();
+    let start = Instant::now();
+    let decoded = ContextGraph::<String, String>::from_binary(&binary).expect("binary decoding");
+    let binary_read = start.elapsed();
+    assert_eq!(decoded.node_count(), graph.node_count());
+
+    let start = Instant::now();
+    let json = graph.to_jgf().expect("JSON encoding");
+    let json_write = start.elapsed();
+    let start = Instant::now();
+    let decoded = ContextGraph::<String, String>::from_jgf(&json).expect("JSON decoding");
+    let json_read = start.elapsed();
+    assert_eq!(decoded.node_count(), graph.node_count());
+
+    for (format, size, write, read) in [
+        ("binary", binary.len(), binary_write, binary_read),
+        ("JSON", json.len(), json_write, json_read),
+    ] {
+        println!(
+            "  - {format:<6} {size:>9} bytes, write {:.3}s, read {:.3}s",
+            write.as_secs_f64(),
+            read.as_secs_f64()
+        );
+    }
+    println!(
+        "  - Binary is {:.1}x smaller",
+        json.len() as f64 / binary.len() as f64
+    );
+}
+
+/// Measure copy-on-write clones, as used by what-if simulations
+///
+/// Each write copies only the entries it touches, so a clone plus one
+/// mutation should stay far below a full copy of the storage.
+fn benchmark_clones(graph: &ContextGraph<String, String>) {
+    println!("\n\nClones (copy-on-write):");
+    let ids: Vec<_> = graph.get_all_nodes().map(|(id, _)| id).take(2).collect();
+    let (node_id, other_id) = (ids[0], ids[1]);
+    let edge_id = graph
+        .get_all_edges()
+        .next()
+        .map(|(id, _)| id)
+        .expect("an edge");
+
+    let start = Instant::now();
+    let clones: Vec<_> = (0..1_000).map(|_| graph.clone()).collect();
+    let shared = start.elapsed();
+    assert!(clones.iter().all(|c| c.node_count() == graph.node_count()));
+
+    let per_clone = |name: &str, mutate: &dyn Fn(&mut ContextGraph<String, String>)| {
+        let start = Instant::now();
+        for _ in 0..1_000 {
+            let mut what_if = graph.clone();
+            mutate(&mut what_if);
+        }
+        println!(
+            "  - {name:<24}{:>10.3}µs each",
+            start.elapsed().as_secs_f64() * 1e6 / 1_000.0
+        );
+    };
+
+    println!(
+        "  - {:<24}{:>10.3}µs each",
+        "Clone",
+        shared.as_secs_f64() * 1e6 / 1_000.0
+    );
+    per_clone("Clone + node write", &|g| {
+        g.get_node_mut(node_id).expect("node").value = "Changed".to_string();
+    });
+    per_clone("Clone + add edge", &|g| {
+        g.add_edge(node_id, other_id, "what-if".to_string())
+            .expect("endpoints exist");
+    });
+    per_clone("Clone + remove edge", &|g| {
+        g.remove_edge(edge_id).expect("edge");
+    });
+
+    // A full copy, for comparison: every node and edge into a fresh petgraph
+    let start = Instant::now();
+    for _ in 0..100 {
+        let mut copy =
+            petgraph::Graph::<_, _>::with_capacity(graph.node_count(), graph.edge_count());
+        for node in graph.graph.node_weights() {
+            copy.add_node(node.clone());
+        }
+        for edge in graph.graph.edge_references() {
+            copy.add_edge(edge.source(), edge.target(), edge.weight().clone());
+        }
+        assert_eq!(copy.node_count(), graph.node_count());
+    }
+    println!(
+        "  - {:<24}{:>10.3}µs each",
+        "Full storage copy",
+        start.elapsed().as_secs_f64() * 1e6 / 100.0
+    );
+
+    assert_eq!(
+        graph.get_node_value(node_id).map(String::as_str),
+        Some("Person_0")
+    );
+    assert!(graph.get_edge(edge_id).is_some());
+}
@@ -0,0 +1,87 @@
+//! Sequential vs parallel analytics on a random graph
+//!
+//! Run with `cargo run --release --features parallel --example parallel_benchmark`
+
+use cim_contextgraph::{ContextGraph, Label};
+use rand::Rng;
+use std::time::Instant;
+
+fn main() {
+    let nodes = 20_000;
+    let edges = 100_000;
+    println!("Parallel Analytics Benchmark");
+    println!("============================");
+    println!("Random graph with {nodes} nodes and {edges} edges\n");
+
+    let graph = random_graph(nodes, edges);
+    let small = random_graph(2_000, 10_000);
+
+    compare(
+        "PageRank (50 iterations)",
+        || graph.page_rank(0.85, 50),
+        || graph.par_page_rank(0.85, 50),
+    );
+    compare(
+        "Connected components",
+        || graph.connected_components(),
+        || graph.par_connected_components(),
+    );
+    compare(
+        "Component query",
+        || graph.query_nodes_with_component::<Label>(),
+        || graph.par_query_nodes_with_component::<Label>(),
+    );
+    compare(
+        "Closeness (2,000 nodes)",
+        || small.closeness_centrality(),
+        || small.par_closeness_centrality(),
+    );
+    compare(
+        "Betweenness (2,000 nodes)",
+        || small.betweenness_centrality(),
+        || small.par_betweenness_centrality(),
+    );
+    compare(
+        "All-pairs lengths (2,000 nodes)",
+        || small.all_pairs_shortest_path_lengths(),
+        || small.par_all_pairs_shortest_path_lengths(),
+    );
+}
+
+fn random_graph(nodes: usize, edges: usize) -> ContextGraph<usize, ()> {
+    let mut graph = ContextGraph::new("Random");
+    let ids: Vec<_> = (0..nodes).map(|i| graph.add_node(i)).collect();
+    for (i, &id) in ids.iter().enumerate().step_by(10) {
+        let _ = graph
+            .get_node_mut(id)
+            .map(|node| node.add_component(Label(format!("Tagged_{i}"))));
+    }
+
+    let mut rng = rand::rng();
+    for _ in 0..edges {
+        let source = ids[rng.random_range(0..nodes)];
+        let target = ids[rng.random_range(0..nodes)];
+        graph.add_edge(source, target, ()).expect("endpoints exist");
+    }
+    graph
+}
+
+/// Time the sequential and parallel forms of one algorithm and check that
+/// they agree
+fn compare<T: PartialEq>(name: &str, sequential: impl Fn() -> T, parallel: impl Fn() -> T) {
+    let start = Instant::now();
+    let expected = sequential();
+    let sequential_time = start.elapsed();
+
+    let start = Instant::now();
+    let actual = parallel();
+    let parallel_time = start.elapsed();
+
+    assert!(expected == actual, "{name}: results differ");
+    println!(
+        "  - {name:<32} sequential {:>7.3}s, parallel {:>7.3}s ({:.1}x)",
+        sequential_time.as_secs_f64(),
+        parallel_time.as_secs_f64(),
+        sequential_time.as_secs_f64() / parallel_time.as_secs_f64()
+    );
+}
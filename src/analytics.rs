@@ -0,0 +1,457 @@
+//! Whole-graph analytics: PageRank, centrality, components and all-pairs
+//!
+//! Every algorithm has a sequential form and, with the `parallel` feature, a
+//! `par_` form backed by rayon. Both run the same per-node kernels and add up
+//! floating-point contributions in the same fixed order, so they return
+//! identical results whatever the thread count.
+//!
+//! Parallel edges count once; edge direction is followed except by
+//! [`ContextGraph::connected_components`], which ignores it.
+
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+#[cfg(feature = "parallel")]
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Debug;
+
+/// PageRank stops early once the ranks move less than this in total
+const PAGE_RANK_TOLERANCE: f64 = 1e-12;
+
+/// Betweenness sums per-source contributions in this many fixed partitions
+const BETWEENNESS_PARTITIONS: usize = 32;
+
+const UNREACHED: usize = usize::MAX;
+
+#[derive(Clone, Copy)]
+enum Mode {
+    Sequential,
+    #[cfg(feature = "parallel")]
+    Parallel,
+}
+
+fn map_indices<T, F>(mode: Mode, n: usize, f: F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(usize) -> T + Send + Sync,
+{
+    match mode {
+        Mode::Sequential => (0..n).map(f).collect(),
+        #[cfg(feature = "parallel")]
+        Mode::Parallel => (0..n).into_par_iter().map(f).collect(),
+    }
+}
+
+/// Deduplicated adjacency over dense petgraph node positions
+struct Adjacency {
+    ids: Vec<NodeId>,
+    outgoing: Vec<Vec<usize>>,
+    incoming: Vec<Vec<usize>>,
+}
+
+impl Adjacency {
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Group positions sharing a label, where each label is the smallest
+    /// position in its group
+    fn group_by_label(&self, labels: &[usize]) -> Vec<Vec<NodeId>> {
+        let mut slots = vec![UNREACHED; labels.len()];
+        let mut components: Vec<Vec<NodeId>> = Vec::new();
+        for (position, &label) in labels.iter().enumerate() {
+            if slots[label] == UNREACHED {
+                slots[label] = components.len();
+                components.push(Vec::new());
+            }
+            components[slots[label]].push(self.ids[position]);
+        }
+        components
+    }
+
+    fn by_node_id<T>(&self, values: Vec<T>) -> HashMap<NodeId, T> {
+        self.ids.iter().copied().zip(values).collect()
+    }
+
+    fn undirected(&self) -> Vec<Vec<usize>> {
+        self.outgoing
+            .iter()
+            .zip(&self.incoming)
+            .map(|(out, inc)| {
+                let mut all: Vec<usize> = out.iter().chain(inc).copied().collect();
+                all.sort_unstable();
+                all.dedup();
+                all
+            })
+            .collect()
+    }
+}
+
+/// Hop distances from `source`, `UNREACHED` where there is no path
+fn bfs_distances(adjacency: &[Vec<usize>], source: usize) -> Vec<usize> {
+    let mut distances = vec![UNREACHED; adjacency.len()];
+    let mut queue = VecDeque::from([source]);
+    distances[source] = 0;
+    while let Some(v) = queue.pop_front() {
+        for &w in &adjacency[v] {
+            if distances[w] == UNREACHED {
+                distances[w] = distances[v] + 1;
+                queue.push_back(w);
+            }
+        }
+    }
+    distances
+}
+
+/// Brandes' dependency accumulation for one source
+fn accumulate_betweenness(adjacency: &[Vec<usize>], source: usize, totals: &mut [f64]) {
+    let n = adjacency.len();
+    let mut order = Vec::with_capacity(n);
+    let mut predecessors = vec![Vec::new(); n];
+    let mut paths = vec![0.0_f64; n];
+    let mut distances = vec![UNREACHED; n];
+    let mut queue = VecDeque::from([source]);
+    paths[source] = 1.0;
+    distances[source] = 0;
+
+    while let Some(v) = queue.pop_front() {
+        order.push(v);
+        for &w in &adjacency[v] {
+            if distances[w] == UNREACHED {
+                distances[w] = distances[v] + 1;
+                queue.push_back(w);
+            }
+            if distances[w] == distances[v] + 1 {
+                paths[w] += paths[v];
+                predecessors[w].push(v);
+            }
+        }
+    }
+
+    let mut dependency = vec![0.0_f64; n];
+    while let Some(w) = order.pop() {
+        for &v in &predecessors[w] {
+            dependency[v] += paths[v] / paths[w] * (1.0 + dependency[w]);
+        }
+        if w != source {
+            totals[w] += dependency[w];
+        }
+    }
+}
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug,
+    E: Clone + Debug,
+{
+    /// PageRank with uniform teleportation; dangling nodes spread evenly
+    pub fn page_rank(&self, damping_factor: f64, max_iterations: usize) -> HashMap<NodeId, f64> {
+        self.page_rank_in(Mode::Sequential, damping_factor, max_iterations)
+    }
+
+    /// Closeness centrality along outgoing edges
+    ///
+    /// Uses the Wasserman-Faust form, scaled by the share of nodes reached,
+    /// so nodes in small components do not score artificially high.
+    pub fn closeness_centrality(&self) -> HashMap<NodeId, f64> {
+        self.closeness_in(Mode::Sequential)
+    }
+
+    /// Unnormalized betweenness centrality over directed shortest paths
+    pub fn betweenness_centrality(&self) -> HashMap<NodeId, f64> {
+        self.betweenness_in(Mode::Sequential)
+    }
+
+    /// Weakly connected components, each sorted in storage order and
+    /// ordered by their earliest node
+    pub fn connected_components(&self) -> Vec<Vec<NodeId>> {
+        let adjacency = self.adjacency();
+        let undirected = adjacency.undirected();
+        let mut labels = vec![UNREACHED; undirected.len()];
+        for start in 0..undirected.len() {
+            if labels[start] != UNREACHED {
+                continue;
+            }
+            labels[start] = start;
+            let mut stack = vec![start];
+            while let Some(v) = stack.pop() {
+                for &w in &undirected[v] {
+                    if labels[w] == UNREACHED {
+                        labels[w] = start;
+                        stack.push(w);
+                    }
+                }
+            }
+        }
+        adjacency.group_by_label(&labels)
+    }
+
+    /// Hop counts of the shortest directed path between every reachable pair
+    pub fn all_pairs_shortest_path_lengths(&self) -> HashMap<NodeId, HashMap<NodeId, usize>> {
+        self.all_pairs_in(Mode::Sequential)
+    }
+
+    fn adjacency(&self) -> Adjacency {
+        let ids: Vec<NodeId> = self.graph.node_weights().map(|node| node.id).collect();
+        let n = ids.len();
+        let mut outgoing = vec![Vec::new(); n];
+        let mut incoming = vec![Vec::new(); n];
+        for edge in self.graph.edge_references() {
+            outgoing[edge.source().index()].push(edge.target().index());
+            incoming[edge.target().index()].push(edge.source().index());
+        }
+        for list in outgoing.iter_mut().chain(incoming.iter_mut()) {
+            list.sort_unstable();
+            list.dedup();
+        }
+        Adjacency {
+            ids,
+            outgoing,
+            incoming,
+        }
+    }
+
+    fn page_rank_in(
+        &self,
+        mode: Mode,
+        damping_factor: f64,
+        max_iterations: usize,
+    ) -> HashMap<NodeId, f64> {
+        let adjacency = self.adjacency();
+        let n = adjacency.len();
+        if n == 0 {
+            return HashMap::new();
+        }
+
+        let mut ranks = vec![1.0 / n as f64; n];
+        for _ in 0..max_iterations {
+            let dangling: f64 = (0..n)
+                .filter(|&v| adjacency.outgoing[v].is_empty())
+                .map(|v| ranks[v])
+                .sum();
+            let base = (1.0 - damping_factor + damping_factor * dangling) / n as f64;
+            let next = map_indices(mode, n, |v| {
+                let inflow: f64 = adjacency.incoming[v]
+                    .iter()
+                    .map(|&u| ranks[u] / adjacency.outgoing[u].len() as f64)
+                    .sum();
+                base + damping_factor * inflow
+            });
+            let change: f64 = next.iter().zip(&ranks).map(|(a, b)| (a - b).abs()).sum();
+            ranks = next;
+            if change < PAGE_RANK_TOLERANCE {
+                break;
+            }
+        }
+        adjacency.by_node_id(ranks)
+    }
+
+    fn closeness_in(&self, mode: Mode) -> HashMap<NodeId, f64> {
+        let adjacency = self.adjacency();
+        let n = adjacency.len();
+        let scores = map_indices(mode, n, |v| {
+            let distances = bfs_distances(&adjacency.outgoing, v);
+            let reached = distances.iter().filter(|&&d| d != UNREACHED && d > 0);
+            let (count, total) = reached.fold((0usize, 0usize), |(c, t), &d| (c + 1, t + d));
+            if total == 0 {
+                return 0.0;
+            }
+            let count = count as f64;
+            (count / total as f64) * (count / (n - 1) as f64)
+        });
+        adjacency.by_node_id(scores)
+    }
+
+    fn betweenness_in(&self, mode: Mode) -> HashMap<NodeId, f64> {
+        let adjacency = self.adjacency();
+        let n = adjacency.len();
+        let partition = n.div_ceil(BETWEENNESS_PARTITIONS).max(1);
+        let partials = map_indices(mode, n.div_ceil(partition), |p| {
+            let mut totals = vec![0.0; n];
+            for source in p * partition..((p + 1) * partition).min(n) {
+                accumulate_betweenness(&adjacency.outgoing, source, &mut totals);
+            }
+            totals
+        });
+
+        let mut totals = vec![0.0; n];
+        for partial in partials {
+            for (total, value) in totals.iter_mut().zip(partial) {
+                *total += value;
+            }
+        }
+        adjacency.by_node_id(totals)
+    }
+
+    fn all_pairs_in(&self, mode: Mode) -> HashMap<NodeId, HashMap<NodeId, usize>> {
+        let adjacency = self.adjacency();
+        let rows = map_indices(mode, adjacency.len(), |source| {
+            bfs_distances(&adjacency.outgoing, source)
+                .into_iter()
+                .enumerate()
+                .filter(|&(_, d)| d != UNREACHED)
+                .map(|(target, d)| (adjacency.ids[target], d))
+                .collect::<HashMap<_, _>>()
+        });
+        adjacency.by_node_id(rows)
+    }
+}
+
+#[cfg(feature = "parallel")]
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug + Send + Sync,
+    E: Clone + Debug + Send + Sync,
+{
+    /// Multi-threaded [`page_rank`](Self::page_rank)
+    pub fn par_page_rank(
+        &self,
+        damping_factor: f64,
+        max_iterations: usize,
+    ) -> HashMap<NodeId, f64> {
+        self.page_rank_in(Mode::Parallel, damping_factor, max_iterations)
+    }
+
+    /// Multi-threaded [`closeness_centrality`](Self::closeness_centrality)
+    pub fn par_closeness_centrality(&self) -> HashMap<NodeId, f64> {
+        self.closeness_in(Mode::Parallel)
+    }
+
+    /// Multi-threaded [`betweenness_centrality`](Self::betweenness_centrality)
+    pub fn par_betweenness_centrality(&self) -> HashMap<NodeId, f64> {
+        self.betweenness_in(Mode::Parallel)
+    }
+
+    /// Multi-threaded [`connected_components`](Self::connected_components)
+    ///
+    /// Propagates the smallest position through each component, halving
+    /// label chains every round, then groups exactly as the sequential form.
+    pub fn par_connected_components(&self) -> Vec<Vec<NodeId>> {
+        let adjacency = self.adjacency();
+        let undirected = adjacency.undirected();
+        let mut labels: Vec<usize> = (0..undirected.len()).collect();
+        loop {
+            let hooked: Vec<usize> = undirected
+                .par_iter()
+                .enumerate()
+                .map(|(v, neighbors)| {
+                    neighbors
+                        .iter()
+                        .map(|&w| labels[w])
+                        .fold(labels[v], usize::min)
+                })
+                .collect();
+            let next: Vec<usize> = hooked.par_iter().map(|&label| hooked[label]).collect();
+            if next == labels {
+                break;
+            }
+            labels = next;
+        }
+        adjacency.group_by_label(&labels)
+    }
+
+    /// Multi-threaded [`all_pairs_shortest_path_lengths`](Self::all_pairs_shortest_path_lengths)
+    pub fn par_all_pairs_shortest_path_lengths(&self) -> HashMap<NodeId, HashMap<NodeId, usize>> {
+        self.all_pairs_in(Mode::Parallel)
+    }
+
+    /// Multi-threaded [`query_nodes_with_component`](Self::query_nodes_with_component)
+    pub fn par_query_nodes_with_component<T: Component + 'static>(&self) -> Vec<NodeId> {
+        (0..self.graph.node_count())
+            .into_par_iter()
+            .map(|i| &self.graph[NodeIndex::new(i)])
+            .filter(|node| node.components.has::<T>())
+            .map(|node| node.id)
+            .collect()
+    }
+
+    /// Multi-threaded [`query_edges_with_component`](Self::query_edges_with_component)
+    pub fn par_query_edges_with_component<T: Component + 'static>(&self) -> Vec<EdgeId> {
+        (0..self.graph.edge_count())
+            .into_par_iter()
+            .map(|i| &self.graph[EdgeIndex::new(i)])
+            .filter(|edge| edge.components.has::<T>())
+            .map(|edge| edge.id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> (ContextGraph<&'static str, ()>, Vec<NodeId>) {
+        let mut graph = ContextGraph::new("Analytics");
+        let ids: Vec<NodeId> = ["a", "b", "c", "d", "e", "f"]
+            .into_iter()
+            .map(|name| graph.add_node(name))
+            .collect();
+        for (s, t) in [(0, 1), (1, 2), (2, 0), (2, 3), (4, 5)] {
+            graph.add_edge(ids[s], ids[t], ()).unwrap();
+        }
+        (graph, ids)
+    }
+
+    #[test]
+    fn test_sequential_algorithms() {
+        let (graph, ids) = sample();
+
+        let ranks = graph.page_rank(0.85, 100);
+        assert!((ranks.values().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(ranks[&ids[3]] > ranks[&ids[4]]);
+
+        let betweenness = graph.betweenness_centrality();
+        // c is on the shortest paths a->d, b->d and b->a
+        assert_eq!(betweenness[&ids[2]], 3.0);
+        assert_eq!(betweenness[&ids[3]], 0.0);
+
+        let closeness = graph.closeness_centrality();
+        assert_eq!(closeness[&ids[3]], 0.0);
+        assert!(closeness[&ids[2]] > closeness[&ids[4]]);
+
+        assert_eq!(
+            graph.connected_components(),
+            vec![ids[0..4].to_vec(), ids[4..6].to_vec()]
+        );
+
+        let all_pairs = graph.all_pairs_shortest_path_lengths();
+        assert_eq!(all_pairs[&ids[0]][&ids[3]], 3);
+        assert!(!all_pairs[&ids[3]].contains_key(&ids[0]));
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let (mut graph, ids) = sample();
+        graph
+            .get_node_mut(ids[4])
+            .unwrap()
+            .add_component(Label("tagged".to_string()))
+            .unwrap();
+
+        assert_eq!(graph.par_page_rank(0.85, 100), graph.page_rank(0.85, 100));
+        assert_eq!(
+            graph.par_betweenness_centrality(),
+            graph.betweenness_centrality()
+        );
+        assert_eq!(
+            graph.par_closeness_centrality(),
+            graph.closeness_centrality()
+        );
+        assert_eq!(
+            graph.par_connected_components(),
+            graph.connected_components()
+        );
+        assert_eq!(
+            graph.par_all_pairs_shortest_path_lengths(),
+            graph.all_pairs_shortest_path_lengths()
+        );
+        assert_eq!(
+            graph.par_query_nodes_with_component::<Label>(),
+            graph.query_nodes_with_component::<Label>()
+        );
+    }
+}
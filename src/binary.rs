@@ -0,0 +1,759 @@
+//! Compact binary encoding
+//!
+//! Graphs are written column by column, so a reader can stream them without
+//! seeking. Integers are LEB128 varints (zigzag for signed values) and ids
+//! are raw 16-byte UUIDs. The layout is:
+//!
+//! 1. magic `CGB\x01` and the graph id
+//! 2. graph metadata as length-prefixed JSON
+//! 3. the sorted names of every component type used, written once each
+//! 4. node count, node ids, node values, node components
+//! 5. edge count, edge ids, sources and targets as node positions, edge
+//!    values, edge components
+//!
+//! Each element's components are a count followed by `(name index, payload
+//! length, payload)` records. Payloads are JSON produced by the codecs in a
+//! [`ComponentCodecs`] registry, except for [`Subgraph`], whose payload is
+//! the nested graph in this same format. Components without a codec are not
+//! written, and records with unknown names are skipped when reading.
+
+use crate::conceptual_space::{ConceptPoint, ConvexRegion};
+use crate::context_graph::ContextGraph;
+use crate::layout::Position3D;
+use crate::temporal::ValidityInterval;
+use crate::types::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::any::TypeId;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt::Debug;
+use std::io::{self, Read, Write};
+use uuid::Uuid;
+
+const MAGIC: &[u8; 4] = b"CGB\x01";
+const SUBGRAPH: &str = "Subgraph";
+
+/// Values that can be written to the binary format
+pub trait BinaryValue: Sized {
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+pub(crate) fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    let mut buf = [0u8; 10];
+    let mut len = 0;
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf[len] = byte;
+            len += 1;
+            break;
+        }
+        buf[len] = byte | 0x80;
+        len += 1;
+    }
+    writer.write_all(&buf[..len])
+}
+
+pub(crate) fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0u8];
+        reader.read_exact(&mut byte)?;
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(invalid_data("varint too long"))
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+fn read_len<R: Read>(reader: &mut R) -> io::Result<usize> {
+    usize::try_from(read_varint(reader)?).map_err(|_| invalid_data("length overflows usize"))
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> io::Result<()> {
+    write_varint(writer, bytes.len() as u64)?;
+    writer.write_all(bytes)
+}
+
+/// Length-prefixed bytes, read without trusting the length for allocation
+fn read_bytes<R: Read>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let len = read_len(reader)?;
+    let mut bytes = Vec::new();
+    reader.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(bytes)
+}
+
+fn read_id<R: Read>(reader: &mut R) -> io::Result<[u8; 16]> {
+    let mut bytes = [0u8; 16];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+macro_rules! unsigned_binary_value {
+    ($($t:ty),*) => {$(
+        impl BinaryValue for $t {
+            fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                write_varint(writer, *self as u64)
+            }
+            fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+                <$t>::try_from(read_varint(reader)?)
+                    .map_err(|_| invalid_data(concat!("value out of range for ", stringify!($t))))
+            }
+        }
+    )*};
+}
+
+macro_rules! signed_binary_value {
+    ($($t:ty),*) => {$(
+        impl BinaryValue for $t {
+            fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+                let value = *self as i64;
+                write_varint(writer, ((value << 1) ^ (value >> 63)) as u64)
+            }
+            fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+                let raw = read_varint(reader)?;
+                let value = ((raw >> 1) as i64) ^ -((raw & 1) as i64);
+                <$t>::try_from(value)
+                    .map_err(|_| invalid_data(concat!("value out of range for ", stringify!($t))))
+            }
+        }
+    )*};
+}
+
+unsigned_binary_value!(u8, u16, u32, u64, usize);
+signed_binary_value!(i8, i16, i32, i64, isize);
+
+impl BinaryValue for f32 {
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_le_bytes())
+    }
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut bytes = [0u8; 4];
+        reader.read_exact(&mut bytes)?;
+        Ok(f32::from_le_bytes(bytes))
+    }
+}
+
+impl BinaryValue for f64 {
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.to_le_bytes())
+    }
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut bytes = [0u8; 8];
+        reader.read_exact(&mut bytes)?;
+        Ok(f64::from_le_bytes(bytes))
+    }
+}
+
+impl BinaryValue for bool {
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[*self as u8])
+    }
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        match u8::read_from(reader)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid_data("invalid bool")),
+        }
+    }
+}
+
+impl BinaryValue for () {
+    fn write_to<W: Write>(&self, _writer: &mut W) -> io::Result<()> {
+        Ok(())
+    }
+    fn read_from<R: Read>(_reader: &mut R) -> io::Result<Self> {
+        Ok(())
+    }
+}
+
+impl BinaryValue for String {
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_bytes(writer, self.as_bytes())
+    }
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        String::from_utf8(read_bytes(reader)?).map_err(|_| invalid_data("invalid UTF-8"))
+    }
+}
+
+impl<T: BinaryValue> BinaryValue for Option<T> {
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        match self {
+            Some(value) => {
+                true.write_to(writer)?;
+                value.write_to(writer)
+            }
+            None => false.write_to(writer),
+        }
+    }
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        if bool::read_from(reader)? {
+            T::read_from(reader).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T: BinaryValue> BinaryValue for Vec<T> {
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_varint(writer, self.len() as u64)?;
+        self.iter().try_for_each(|value| value.write_to(writer))
+    }
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let len = read_len(reader)?;
+        (0..len).map(|_| T::read_from(reader)).collect()
+    }
+}
+
+/// Arbitrary JSON, stored as its text
+impl BinaryValue for serde_json::Value {
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_bytes(writer, self.to_string().as_bytes())
+    }
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+        serde_json::from_slice(&read_bytes(reader)?).map_err(|err| invalid_data(&err.to_string()))
+    }
+}
+
+type Encoder = Box<dyn Fn(&dyn Component) -> serde_json::Result<Vec<u8>> + Send + Sync>;
+type Decoder = Box<dyn Fn(&[u8]) -> serde_json::Result<Box<dyn Component>> + Send + Sync>;
+
+/// Serializers for the component types written by the binary format
+///
+/// The default registry covers the crate's serializable components;
+/// register application components before writing or reading graphs that
+/// carry them.
+pub struct ComponentCodecs {
+    encoders: HashMap<TypeId, (&'static str, Encoder)>,
+    decoders: HashMap<&'static str, Decoder>,
+}
+
+impl ComponentCodecs {
+    /// A registry without any codecs
+    pub fn empty() -> Self {
+        Self {
+            encoders: HashMap::new(),
+            decoders: HashMap::new(),
+        }
+    }
+
+    /// Register a component type under a name stored in the encoding
+    pub fn register<T>(&mut self, name: &'static str) -> &mut Self
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        let encode: Encoder = Box::new(|component| {
+            let component = component
+                .as_any()
+                .downcast_ref::<T>()
+                .expect("encoders are looked up by TypeId");
+            serde_json::to_vec(component)
+        });
+        let decode: Decoder = Box::new(|payload| {
+            let component: T = serde_json::from_slice(payload)?;
+            Ok(Box::new(component))
+        });
+        self.encoders.insert(TypeId::of::<T>(), (name, encode));
+        self.decoders.insert(name, decode);
+        self
+    }
+
+    /// Builder form of [`register`](Self::register)
+    pub fn with<T>(mut self, name: &'static str) -> Self
+    where
+        T: Component + Serialize + DeserializeOwned,
+    {
+        self.register::<T>(name);
+        self
+    }
+
+    pub(crate) fn name_of(&self, type_id: &TypeId) -> Option<&'static str> {
+        self.encoders.get(type_id).map(|(name, _)| *name)
+    }
+
+    /// The registered type for a component name
+    pub(crate) fn type_id_of(&self, name: &str) -> Option<TypeId> {
+        self.encoders
+            .iter()
+            .find(|(_, (registered, _))| *registered == name)
+            .map(|(type_id, _)| *type_id)
+    }
+
+    /// Encode a component as JSON under its registered name
+    ///
+    /// Returns `None` when the component's type has no codec.
+    pub(crate) fn encode_json(
+        &self,
+        type_id: &TypeId,
+        component: &dyn Component,
+    ) -> Option<GraphResult<(&'static str, serde_json::Value)>> {
+        let (name, encode) = self.encoders.get(type_id)?;
+        let encoded = encode(component)
+            .and_then(|bytes| serde_json::from_slice(&bytes))
+            .map(|value| (*name, value))
+            .map_err(|err| GraphError::InvalidOperation(format!("cannot encode {name}: {err}")));
+        Some(encoded)
+    }
+
+    /// Decode a component from JSON produced by [`encode_json`](Self::encode_json)
+    pub(crate) fn decode_json(
+        &self,
+        name: &str,
+        data: &serde_json::Value,
+    ) -> GraphResult<Box<dyn Component>> {
+        let decode = self
+            .decoders
+            .get(name)
+            .ok_or_else(|| GraphError::ComponentNotFound(format!("no codec for {name}")))?;
+        serde_json::to_vec(data)
+            .and_then(|bytes| decode(&bytes))
+            .map_err(|err| GraphError::InvalidOperation(format!("cannot decode {name}: {err}")))
+    }
+}
+
+impl Default for ComponentCodecs {
+    fn default() -> Self {
+        Self::empty()
+            .with::<Label>("Label")
+            .with::<Metadata>("Metadata")
+            .with::<GraphReference>("GraphReference")
+            .with::<Provenance>("Provenance")
+            .with::<Position3D>("Position3D")
+            .with::<ConceptPoint>("ConceptPoint")
+            .with::<ConvexRegion>("ConvexRegion")
+            .with::<ValidityInterval>("ValidityInterval")
+    }
+}
+
+impl Debug for ComponentCodecs {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<&str> = self.decoders.keys().copied().collect();
+        names.sort_unstable();
+        f.debug_struct("ComponentCodecs")
+            .field("components", &names)
+            .finish()
+    }
+}
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug + BinaryValue + 'static + Send + Sync,
+    E: Clone + Debug + BinaryValue + 'static + Send + Sync,
+{
+    /// Encode with the default component codecs
+    pub fn to_binary(&self) -> GraphResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write_binary(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Decode with the default component codecs
+    pub fn from_binary(bytes: &[u8]) -> GraphResult<Self> {
+        Self::read_binary(bytes)
+    }
+
+    /// Stream the encoding to a writer, using the default component codecs
+    ///
+    /// Writes are small; pass a buffered writer for files and sockets.
+    pub fn write_binary<W: Write>(&self, writer: W) -> GraphResult<()> {
+        self.write_binary_with(writer, &ComponentCodecs::default())
+    }
+
+    /// Stream the encoding to a writer
+    pub fn write_binary_with<W: Write>(
+        &self,
+        mut writer: W,
+        codecs: &ComponentCodecs,
+    ) -> GraphResult<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(self.id.as_uuid().as_bytes())?;
+        let metadata = serde_json::to_vec(&self.metadata)
+            .map_err(|err| GraphError::InvalidOperation(err.to_string()))?;
+        write_bytes(&mut writer, &metadata)?;
+
+        let subgraph = TypeId::of::<Subgraph<N, E>>();
+        let mut names = BTreeSet::new();
+        let storages = self
+            .get_all_nodes()
+            .map(|(_, node)| &node.components)
+            .chain(self.get_all_edges().map(|(_, edge)| &edge.components));
+        for storage in storages {
+            for (type_id, _) in storage.iter() {
+                if *type_id == subgraph {
+                    names.insert(SUBGRAPH);
+                } else if let Some(name) = codecs.name_of(type_id) {
+                    names.insert(name);
+                }
+            }
+        }
+        let names: Vec<&str> = names.into_iter().collect();
+        write_varint(&mut writer, names.len() as u64)?;
+        for name in &names {
+            write_bytes(&mut writer, name.as_bytes())?;
+        }
+
+        let nodes: Vec<(NodeId, &NodeEntry<N>)> = self.get_all_nodes().collect();
+        write_varint(&mut writer, nodes.len() as u64)?;
+        for (node_id, _) in &nodes {
+            writer.write_all(node_id.as_uuid().as_bytes())?;
+        }
+        for (_, node) in &nodes {
+            node.value.write_to(&mut writer)?;
+        }
+        for (_, node) in &nodes {
+            self.write_components(&mut writer, &node.components, &names, codecs)?;
+        }
+
+        let position: HashMap<NodeId, u64> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| (*id, i as u64))
+            .collect();
+        let edges: Vec<(EdgeId, &EdgeEntry<E>)> = self.get_all_edges().collect();
+        write_varint(&mut writer, edges.len() as u64)?;
+        for (edge_id, _) in &edges {
+            writer.write_all(edge_id.as_uuid().as_bytes())?;
+        }
+        for (_, edge) in &edges {
+            write_varint(&mut writer, position[&edge.source])?;
+        }
+        for (_, edge) in &edges {
+            write_varint(&mut writer, position[&edge.target])?;
+        }
+        for (_, edge) in &edges {
+            edge.value.write_to(&mut writer)?;
+        }
+        for (_, edge) in &edges {
+            self.write_components(&mut writer, &edge.components, &names, codecs)?;
+        }
+        Ok(())
+    }
+
+    fn write_components<W: Write>(
+        &self,
+        writer: &mut W,
+        storage: &ComponentStorage,
+        names: &[&str],
+        codecs: &ComponentCodecs,
+    ) -> GraphResult<()> {
+        let mut records = Vec::new();
+        for (type_id, component) in storage.iter() {
+            let (name, payload) = if *type_id == TypeId::of::<Subgraph<N, E>>() {
+                let subgraph = component
+                    .as_any()
+                    .downcast_ref::<Subgraph<N, E>>()
+                    .expect("matched by TypeId");
+                (SUBGRAPH, subgraph.graph.to_binary_with(codecs)?)
+            } else if let Some((name, encode)) = codecs.encoders.get(type_id) {
+                let payload = encode(component).map_err(|err| {
+                    GraphError::InvalidOperation(format!("cannot encode {name}: {err}"))
+                })?;
+                (*name, payload)
+            } else {
+                continue;
+            };
+            let index = names
+                .binary_search(&name)
+                .expect("name table covers every written component");
+            records.push((index, payload));
+        }
+        records.sort_by_key(|(index, _)| *index);
+
+        write_varint(writer, records.len() as u64)?;
+        for (index, payload) in records {
+            write_varint(writer, index as u64)?;
+            write_bytes(writer, &payload)?;
+        }
+        Ok(())
+    }
+
+    fn to_binary_with(&self, codecs: &ComponentCodecs) -> GraphResult<Vec<u8>> {
+        let mut bytes = Vec::new();
+        self.write_binary_with(&mut bytes, codecs)?;
+        Ok(bytes)
+    }
+
+    /// Stream a graph from a reader, using the default component codecs
+    ///
+    /// Reads are small; pass a buffered reader for files and sockets. Reading
+    /// stops at the end of the graph, so several graphs can share a stream.
+    pub fn read_binary<R: Read>(reader: R) -> GraphResult<Self> {
+        Self::read_binary_with(reader, &ComponentCodecs::default())
+    }
+
+    /// Stream a graph from a reader
+    pub fn read_binary_with<R: Read>(reader: R, codecs: &ComponentCodecs) -> GraphResult<Self> {
+        let mut reader = Counting {
+            inner: reader,
+            read: 0,
+        };
+        Self::decode(&mut reader, codecs).map_err(|err| match err {
+            Failure::Io(err) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                decode_error(reader.read, "unexpected end of input".to_string())
+            }
+            Failure::Io(err) if err.kind() == io::ErrorKind::InvalidData => {
+                decode_error(reader.read, err.to_string())
+            }
+            Failure::Io(err) => err.into(),
+            Failure::Data(message) => decode_error(reader.read, message),
+            Failure::Graph(err) => err,
+        })
+    }
+
+    fn decode<R: Read>(reader: &mut R, codecs: &ComponentCodecs) -> Result<Self, Failure> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(Failure::Data("not a binary ContextGraph".to_string()));
+        }
+        let graph_id = ContextGraphId::from_uuid(Uuid::from_bytes(read_id(reader)?));
+        let metadata: Metadata = serde_json::from_slice(&read_bytes(reader)?)
+            .map_err(|err| Failure::Data(format!("invalid graph metadata: {err}")))?;
+
+        let name_count = read_len(reader)?;
+        let names = (0..name_count)
+            .map(|_| String::read_from(reader))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        let node_count = read_len(reader)?;
+        let mut node_ids = Vec::new();
+        let mut seen = HashSet::new();
+        for _ in 0..node_count {
+            let node_id = NodeId::from_uuid(Uuid::from_bytes(read_id(reader)?));
+            if !seen.insert(node_id) {
+                return Err(Failure::Data(format!("duplicate node id {node_id}")));
+            }
+            node_ids.push(node_id);
+        }
+        let node_values = (0..node_count)
+            .map(|_| N::read_from(reader))
+            .collect::<io::Result<Vec<_>>>()?;
+        let node_components = (0..node_count)
+            .map(|_| Self::read_components(reader, &names, codecs))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let edge_count = read_len(reader)?;
+        let mut edge_ids = Vec::new();
+        let mut seen = HashSet::new();
+        for _ in 0..edge_count {
+            let edge_id = EdgeId::from_uuid(Uuid::from_bytes(read_id(reader)?));
+            if !seen.insert(edge_id) {
+                return Err(Failure::Data(format!("duplicate edge id {edge_id}")));
+            }
+            edge_ids.push(edge_id);
+        }
+        let mut endpoint = || -> Result<NodeId, Failure> {
+            let position = read_len(reader)?;
+            node_ids
+                .get(position)
+                .copied()
+                .ok_or_else(|| Failure::Data(format!("edge endpoint {position} out of range")))
+        };
+        let sources = (0..edge_count)
+            .map(|_| endpoint())
+            .collect::<Result<Vec<_>, _>>()?;
+        let targets = (0..edge_count)
+            .map(|_| endpoint())
+            .collect::<Result<Vec<_>, _>>()?;
+        let edge_values = (0..edge_count)
+            .map(|_| E::read_from(reader))
+            .collect::<io::Result<Vec<_>>>()?;
+        let edge_components = (0..edge_count)
+            .map(|_| Self::read_components(reader, &names, codecs))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut graph = ContextGraph::new("");
+        graph.id = graph_id;
+        graph.metadata = metadata;
+        let nodes = node_ids.iter().zip(node_values).zip(node_components);
+        for ((id, value), components) in nodes {
+            graph.insert_node_entry(NodeEntry {
+                id: *id,
+                value,
+                components,
+            });
+        }
+        let edges = edge_ids
+            .into_iter()
+            .zip(sources.into_iter().zip(targets))
+            .zip(edge_values.into_iter().zip(edge_components));
+        for ((id, (source, target)), (value, components)) in edges {
+            graph
+                .insert_edge_entry(EdgeEntry {
+                    id,
+                    source,
+                    target,
+                    value,
+                    components,
+                })
+                .map_err(Failure::Graph)?;
+        }
+        Ok(graph)
+    }
+
+    fn read_components<R: Read>(
+        reader: &mut R,
+        names: &[String],
+        codecs: &ComponentCodecs,
+    ) -> Result<ComponentStorage, Failure> {
+        let mut storage = ComponentStorage::new();
+        for _ in 0..read_len(reader)? {
+            let index = read_len(reader)?;
+            let name = names
+                .get(index)
+                .ok_or_else(|| Failure::Data(format!("component name {index} out of range")))?;
+            let payload = read_bytes(reader)?;
+
+            let component: Box<dyn Component> = if name == SUBGRAPH {
+                let graph =
+                    Self::read_binary_with(payload.as_slice(), codecs).map_err(Failure::Graph)?;
+                Box::new(Subgraph {
+                    graph: Box::new(graph),
+                })
+            } else if let Some(decode) = codecs.decoders.get(name.as_str()) {
+                decode(&payload).map_err(|err| Failure::Data(format!("invalid {name}: {err}")))?
+            } else {
+                continue;
+            };
+            storage.add_boxed(component).map_err(Failure::Graph)?;
+        }
+        Ok(storage)
+    }
+}
+
+fn decode_error(offset: u64, message: String) -> GraphError {
+    GraphError::Decode {
+        format: "binary".to_string(),
+        offset: Some(offset),
+        message,
+    }
+}
+
+/// Why decoding stopped, before the byte offset is attached
+enum Failure {
+    Io(io::Error),
+    Data(String),
+    Graph(GraphError),
+}
+
+impl From<io::Error> for Failure {
+    fn from(err: io::Error) -> Self {
+        Failure::Io(err)
+    }
+}
+
+/// Reader tracking how many bytes were consumed, for error offsets
+struct Counting<R> {
+    inner: R,
+    read: u64,
+}
+
+impl<R: Read> Read for Counting<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::any::Any;
+
+    #[test]
+    fn test_binary_round_trip() {
+        let mut inner = ContextGraph::<String, i32>::new("Inner");
+        inner.add_node("leaf".to_string());
+
+        let mut graph = ContextGraph::<String, i32>::new("Outer");
+        let a = graph.add_node("a".to_string());
+        let b = graph.add_node("b".to_string());
+        let node = graph.get_node_mut(a).unwrap();
+        node.add_component(Label("Alpha".to_string())).unwrap();
+        node.add_component(Position3D::new(1.0, 2.0, 3.0)).unwrap();
+        graph
+            .get_node_mut(b)
+            .unwrap()
+            .add_component(Subgraph {
+                graph: Box::new(inner),
+            })
+            .unwrap();
+        let edge = graph.add_edge(a, b, -7).unwrap();
+
+        let bytes = graph.to_binary().unwrap();
+        assert_eq!(&bytes[..4], MAGIC);
+        let decoded = ContextGraph::<String, i32>::from_binary(&bytes).unwrap();
+
+        assert_eq!(decoded.id, graph.id);
+        assert_eq!(decoded.metadata, graph.metadata);
+        let node = decoded.get_node(a).unwrap();
+        assert_eq!(node.get_component::<Label>().unwrap().0, "Alpha");
+        assert_eq!(
+            node.get_component::<Position3D>(),
+            Some(&Position3D::new(1.0, 2.0, 3.0))
+        );
+        assert_eq!(decoded.get_edge(edge).unwrap().value, -7);
+        assert_eq!(decoded.total_node_count(), 3);
+        assert_eq!(decoded.to_binary().unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_streaming_and_custom_codecs() {
+        #[derive(Debug, Clone, PartialEq, Serialize, serde::Deserialize)]
+        struct Owner(String);
+        impl Component for Owner {
+            fn as_any(&self) -> &dyn Any {
+                self
+            }
+            fn clone_box(&self) -> Box<dyn Component> {
+                Box::new(self.clone())
+            }
+            fn type_name(&self) -> &'static str {
+                "Owner"
+            }
+        }
+
+        let mut graph = ContextGraph::<u64, ()>::new("Stream");
+        let node = graph.add_node(300);
+        graph
+            .get_node_mut(node)
+            .unwrap()
+            .add_component(Owner("ops".to_string()))
+            .unwrap();
+        let codecs = ComponentCodecs::default().with::<Owner>("Owner");
+
+        let mut stream = Vec::new();
+        graph.write_binary_with(&mut stream, &codecs).unwrap();
+        graph.write_binary(&mut stream).unwrap();
+
+        let mut reader = stream.as_slice();
+        let first = ContextGraph::<u64, ()>::read_binary_with(&mut reader, &codecs).unwrap();
+        let second = ContextGraph::<u64, ()>::read_binary_with(&mut reader, &codecs).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(
+            first.get_node(node).unwrap().get_component::<Owner>(),
+            Some(&Owner("ops".to_string()))
+        );
+        assert!(!second.get_node(node).unwrap().has_component::<Owner>());
+        assert_eq!(second.get_node(node).unwrap().value, 300);
+
+        let truncated = &stream[..stream.len() / 2 - 3];
+        let err = ContextGraph::<u64, ()>::from_binary(truncated).unwrap_err();
+        assert!(
+            matches!(err, GraphError::Decode { offset: Some(offset), .. } if offset == truncated.len() as u64),
+            "{err}"
+        );
+    }
+}
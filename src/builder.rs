@@ -0,0 +1,363 @@
+//! Bulk construction of graphs with caller-provided IDs
+//!
+//! [`GraphBuilder`] collects nodes and edges without indexing or checking
+//! anything, then builds the storage and the ID indexes in one pass and runs
+//! each invariant once. Instead of stopping at the first problem, `build`
+//! reports every duplicate ID, dangling edge and invariant violation.
+//!
+//! IDs are used as given. Loaders that need new ones can draw them from the
+//! builder's [`IdGenerator`], which the built graph then keeps using.
+
+use crate::context_graph::{ContextGraph, GraphInvariant};
+use crate::ids::{IdGenerator, RandomIds};
+use crate::persistent::PersistentGraph;
+use crate::types::*;
+use petgraph::graph::NodeIndex;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Debug};
+
+/// One problem found while building a graph
+#[derive(Debug, Clone)]
+pub enum BuildViolation {
+    /// A later node reused this ID; the first node was kept
+    DuplicateNode(NodeId),
+    /// A later edge reused this ID; the first edge was kept
+    DuplicateEdge(EdgeId),
+    /// The edge was dropped because an endpoint does not exist
+    MissingEndpoint { edge: EdgeId, node: NodeId },
+    /// An invariant rejected the assembled graph
+    Invariant { name: String, error: GraphError },
+}
+
+impl fmt::Display for BuildViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildViolation::DuplicateNode(id) => write!(f, "duplicate node {id}"),
+            BuildViolation::DuplicateEdge(id) => write!(f, "duplicate edge {id}"),
+            BuildViolation::MissingEndpoint { edge, node } => {
+                write!(f, "edge {edge} refers to missing node {node}")
+            }
+            BuildViolation::Invariant { name, error } => write!(f, "{name}: {error}"),
+        }
+    }
+}
+
+/// Every violation found by [`GraphBuilder::build`]
+#[derive(Debug, Clone)]
+pub struct BuildReport {
+    pub violations: Vec<BuildViolation>,
+}
+
+impl fmt::Display for BuildReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let messages: Vec<String> = self.violations.iter().map(|v| v.to_string()).collect();
+        write!(
+            f,
+            "{} build violation(s): {}",
+            messages.len(),
+            messages.join("; ")
+        )
+    }
+}
+
+impl std::error::Error for BuildReport {}
+
+impl From<BuildReport> for GraphError {
+    fn from(report: BuildReport) -> Self {
+        GraphError::InvariantViolation(report.to_string())
+    }
+}
+
+/// Accumulates nodes and edges for a single bulk build
+pub struct GraphBuilder<N, E> {
+    id: ContextGraphId,
+    metadata: Metadata,
+    nodes: Vec<NodeEntry<N>>,
+    edges: Vec<EdgeEntry<E>>,
+    invariants: Vec<Box<dyn GraphInvariant<N, E>>>,
+    id_generator: Box<dyn IdGenerator>,
+}
+
+impl<N, E> GraphBuilder<N, E>
+where
+    N: Clone + Debug,
+    E: Clone + Debug,
+{
+    pub fn new(name: impl Into<String>) -> Self {
+        let mut metadata = Metadata::default();
+        metadata
+            .properties
+            .insert("name".to_string(), serde_json::json!(name.into()));
+
+        Self {
+            id: ContextGraphId::new(),
+            metadata,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            invariants: Vec::new(),
+            id_generator: Box::new(RandomIds),
+        }
+    }
+
+    pub fn with_id(mut self, id: ContextGraphId) -> Self {
+        self.id = id;
+        self
+    }
+
+    pub fn with_metadata(mut self, metadata: Metadata) -> Self {
+        self.metadata = metadata;
+        self
+    }
+
+    /// Reserve room for this many more nodes and edges
+    pub fn with_capacity(mut self, nodes: usize, edges: usize) -> Self {
+        self.nodes.reserve(nodes);
+        self.edges.reserve(edges);
+        self
+    }
+
+    /// Draw new IDs from `generator`, here and in the built graph
+    pub fn with_id_generator(mut self, generator: impl IdGenerator + 'static) -> Self {
+        self.id_generator = Box::new(generator);
+        self
+    }
+
+    /// A new node ID from the builder's generator
+    pub fn next_node_id(&mut self) -> NodeId {
+        self.id_generator.node_id()
+    }
+
+    /// A new edge ID from the builder's generator
+    pub fn next_edge_id(&mut self) -> EdgeId {
+        self.id_generator.edge_id()
+    }
+
+    /// Check this invariant once the graph is assembled
+    pub fn with_invariant(mut self, invariant: impl GraphInvariant<N, E> + 'static) -> Self {
+        self.invariants.push(Box::new(invariant));
+        self
+    }
+
+    pub fn with_nodes(mut self, nodes: impl IntoIterator<Item = (NodeId, N)>) -> Self {
+        self.extend_nodes(nodes);
+        self
+    }
+
+    pub fn with_edges(
+        mut self,
+        edges: impl IntoIterator<Item = (EdgeId, NodeId, NodeId, E)>,
+    ) -> Self {
+        self.extend_edges(edges);
+        self
+    }
+
+    pub fn add_node(&mut self, id: NodeId, value: N) -> &mut Self {
+        self.nodes.push(NodeEntry::with_id(id, value));
+        self
+    }
+
+    /// Add a prepared node, keeping its ID and components
+    pub fn add_node_entry(&mut self, entry: NodeEntry<N>) -> &mut Self {
+        self.nodes.push(entry);
+        self
+    }
+
+    pub fn add_edge(&mut self, id: EdgeId, source: NodeId, target: NodeId, value: E) -> &mut Self {
+        self.edges.push(EdgeEntry {
+            id,
+            source,
+            target,
+            value,
+            components: ComponentStorage::new(),
+        });
+        self
+    }
+
+    /// Add a prepared edge, keeping its ID and components
+    pub fn add_edge_entry(&mut self, entry: EdgeEntry<E>) -> &mut Self {
+        self.edges.push(entry);
+        self
+    }
+
+    pub fn extend_nodes(&mut self, nodes: impl IntoIterator<Item = (NodeId, N)>) -> &mut Self {
+        let nodes = nodes.into_iter();
+        self.nodes.reserve(nodes.size_hint().0);
+        self.nodes
+            .extend(nodes.map(|(id, value)| NodeEntry::with_id(id, value)));
+        self
+    }
+
+    pub fn extend_edges(
+        &mut self,
+        edges: impl IntoIterator<Item = (EdgeId, NodeId, NodeId, E)>,
+    ) -> &mut Self {
+        let edges = edges.into_iter();
+        self.edges.reserve(edges.size_hint().0);
+        for (id, source, target, value) in edges {
+            self.add_edge(id, source, target, value);
+        }
+        self
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Assemble the graph, indexing it once and checking each invariant once
+    ///
+    /// Fails with every violation found; duplicates and dangling edges do
+    /// not stop the invariants from being checked on the rest.
+    pub fn build(self) -> Result<ContextGraph<N, E>, BuildReport> {
+        let mut violations = Vec::new();
+        let mut graph = PersistentGraph::new();
+        let mut node_indices: HashMap<NodeId, NodeIndex> = HashMap::with_capacity(self.nodes.len());
+
+        for entry in self.nodes {
+            if node_indices.contains_key(&entry.id) {
+                violations.push(BuildViolation::DuplicateNode(entry.id));
+                continue;
+            }
+            let id = entry.id;
+            node_indices.insert(id, graph.add_node(entry));
+        }
+
+        let mut edge_ids = HashSet::with_capacity(self.edges.len());
+        for entry in self.edges {
+            if !edge_ids.insert(entry.id) {
+                violations.push(BuildViolation::DuplicateEdge(entry.id));
+                continue;
+            }
+            let endpoints = [entry.source, entry.target].map(|node| {
+                node_indices
+                    .get(&node)
+                    .copied()
+                    .ok_or(BuildViolation::MissingEndpoint {
+                        edge: entry.id,
+                        node,
+                    })
+            });
+            match endpoints {
+                [Ok(source), Ok(target)] => {
+                    graph.add_edge(source, target, entry);
+                }
+                [source, target] => {
+                    violations.extend(source.err());
+                    violations.extend(target.err());
+                }
+            }
+        }
+
+        let mut graph = ContextGraph::from_parts(self.id, self.metadata, graph, self.invariants);
+        graph.id_generator = self.id_generator;
+        for invariant in &graph.invariants {
+            if let Err(error) = invariant.check(&graph) {
+                violations.push(BuildViolation::Invariant {
+                    name: invariant.name().to_string(),
+                    error,
+                });
+            }
+        }
+
+        if violations.is_empty() {
+            Ok(graph)
+        } else {
+            Err(BuildReport { violations })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone)]
+    struct NoSelfLoops;
+
+    impl<N, E> GraphInvariant<N, E> for NoSelfLoops {
+        fn check(&self, graph: &ContextGraph<N, E>) -> GraphResult<()> {
+            match graph
+                .graph
+                .edge_weights()
+                .find(|edge| edge.source == edge.target)
+            {
+                Some(edge) => Err(GraphError::InvariantViolation(format!(
+                    "self-loop {}",
+                    edge.id
+                ))),
+                None => Ok(()),
+            }
+        }
+
+        fn name(&self) -> &str {
+            "NoSelfLoops"
+        }
+
+        fn clone_box(&self) -> Box<dyn GraphInvariant<N, E>> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_build_keeps_caller_ids() {
+        let ids: Vec<NodeId> = (0..100).map(|_| NodeId::new()).collect();
+        let edges: Vec<_> = ids
+            .windows(2)
+            .map(|pair| (EdgeId::new(), pair[0], pair[1], 1.0))
+            .collect();
+        let graph_id = ContextGraphId::new();
+
+        let graph = GraphBuilder::<usize, f64>::new("Bulk")
+            .with_id(graph_id)
+            .with_capacity(ids.len(), edges.len())
+            .with_invariant(NoSelfLoops)
+            .with_nodes(ids.iter().copied().zip(0..))
+            .with_edges(edges.clone())
+            .build()
+            .unwrap();
+
+        assert_eq!(graph.id, graph_id);
+        assert_eq!(graph.node_count(), 100);
+        assert_eq!(graph.edge_count(), 99);
+        assert_eq!(graph.get_node_value(ids[42]), Some(&42));
+        assert_eq!(graph.successors(ids[42]), vec![ids[43]]);
+        assert_eq!(graph.get_edge(edges[7].0).unwrap().source, ids[7]);
+        assert_eq!(graph.invariants.len(), 1);
+    }
+
+    #[test]
+    fn test_build_reports_every_violation() {
+        let (a, b, missing) = (NodeId::new(), NodeId::new(), NodeId::new());
+        let (loop_edge, dangling) = (EdgeId::new(), EdgeId::new());
+
+        let mut builder = GraphBuilder::<&str, ()>::new("Broken").with_invariant(NoSelfLoops);
+        builder
+            .add_node(a, "a")
+            .add_node(b, "b")
+            .add_node(a, "again")
+            .add_edge(loop_edge, a, a, ())
+            .add_edge(loop_edge, a, b, ())
+            .add_edge(dangling, b, missing, ());
+
+        let report = builder.build().unwrap_err();
+        assert_eq!(report.violations.len(), 4);
+        assert!(matches!(report.violations[0], BuildViolation::DuplicateNode(id) if id == a));
+        assert!(
+            matches!(report.violations[1], BuildViolation::DuplicateEdge(id) if id == loop_edge)
+        );
+        assert!(matches!(
+            report.violations[2],
+            BuildViolation::MissingEndpoint { edge, node } if edge == dangling && node == missing
+        ));
+        assert!(matches!(
+            &report.violations[3],
+            BuildViolation::Invariant { name, .. } if name == "NoSelfLoops"
+        ));
+
+        let error: GraphError = report.into();
+        assert!(error.to_string().contains("4 build violation(s)"));
+    }
+}
@@ -0,0 +1,494 @@
+//! Conceptual space geometry for concept graphs
+//!
+//! Following Gärdenfors' conceptual spaces, a [`ConceptualSpace`] is built
+//! from weighted [`QualityDimension`]s. Nodes are placed in the space with a
+//! [`ConceptPoint`] component and concepts are described by [`ConvexRegion`]
+//! components. Distances and similarities between concepts, region membership
+//! and nearest-neighbour queries are computed with `nalgebra`.
+
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use nalgebra::{DMatrix, DVector};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::fmt::Debug;
+
+/// How differences along a single quality dimension are measured
+///
+/// Deserializing rejects circular scales whose period is not finite and
+/// positive.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+#[serde(try_from = "ScaleFields")]
+pub enum DimensionScale {
+    /// Plain difference between values
+    #[default]
+    Linear,
+    /// Values wrap around with the given period (e.g. hue in degrees)
+    Circular { period: f64 },
+}
+
+/// Unchecked serialized form of [`DimensionScale`]
+#[derive(Deserialize)]
+enum ScaleFields {
+    Linear,
+    Circular { period: f64 },
+}
+
+impl TryFrom<ScaleFields> for DimensionScale {
+    type Error = GraphError;
+
+    fn try_from(fields: ScaleFields) -> GraphResult<Self> {
+        match fields {
+            ScaleFields::Linear => Ok(Self::Linear),
+            ScaleFields::Circular { period } if valid_period(period) => {
+                Ok(Self::Circular { period })
+            }
+            ScaleFields::Circular { period } => Err(GraphError::InvalidOperation(format!(
+                "Circular scale needs a positive period, got {period}"
+            ))),
+        }
+    }
+}
+
+fn valid_period(period: f64) -> bool {
+    period.is_finite() && period > 0.0
+}
+
+/// A single quality dimension of a conceptual space
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QualityDimension {
+    pub name: String,
+    /// Expected value range, used to normalise differences
+    pub range: (f64, f64),
+    /// Salience of the dimension when combining distances
+    pub weight: f64,
+    /// Set through [`circular`](Self::circular) so the period is checked
+    scale: DimensionScale,
+}
+
+impl QualityDimension {
+    pub fn new(name: impl Into<String>, min: f64, max: f64) -> Self {
+        Self {
+            name: name.into(),
+            range: (min, max),
+            weight: 1.0,
+            scale: DimensionScale::Linear,
+        }
+    }
+
+    /// Builder method to set the weight
+    pub fn with_weight(mut self, weight: f64) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Builder method to make the dimension circular
+    ///
+    /// Fails unless `period` is finite and positive.
+    pub fn circular(mut self, period: f64) -> GraphResult<Self> {
+        if !valid_period(period) {
+            return Err(GraphError::InvalidOperation(format!(
+                "Circular dimension {} needs a positive period, got {period}",
+                self.name
+            )));
+        }
+        self.scale = DimensionScale::Circular { period };
+        Ok(self)
+    }
+
+    pub fn scale(&self) -> DimensionScale {
+        self.scale
+    }
+
+    /// Normalised difference between two values on this dimension
+    fn difference(&self, a: f64, b: f64) -> f64 {
+        let raw = match self.scale {
+            DimensionScale::Linear => (a - b).abs(),
+            DimensionScale::Circular { period } => {
+                let d = (a - b).abs() % period;
+                d.min(period - d)
+            }
+        };
+        let span = self.range.1 - self.range.0;
+        if span > 0.0 {
+            raw / span
+        } else {
+            raw
+        }
+    }
+}
+
+/// How per-dimension differences combine into a distance
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum DistanceMetric {
+    /// Weighted Euclidean distance (integral dimensions)
+    #[default]
+    Euclidean,
+    /// Weighted city-block distance (separable dimensions)
+    Manhattan,
+    /// Largest weighted difference
+    Chebyshev,
+}
+
+/// Position of a node in a conceptual space
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConceptPoint(pub DVector<f64>);
+
+impl ConceptPoint {
+    pub fn new(coordinates: impl IntoIterator<Item = f64>) -> Self {
+        Self(DVector::from_vec(coordinates.into_iter().collect()))
+    }
+
+    /// Number of coordinates
+    pub fn dimensions(&self) -> usize {
+        self.0.len()
+    }
+}
+
+impl Component for ConceptPoint {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+    fn type_name(&self) -> &'static str {
+        "ConceptPoint"
+    }
+}
+
+/// A convex region of a conceptual space, as an intersection of half-spaces
+///
+/// A point `x` lies in the region when `normals * x <= offsets` holds row by
+/// row. The optional prototype is the most typical member of the concept.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConvexRegion {
+    pub normals: DMatrix<f64>,
+    pub offsets: DVector<f64>,
+    pub prototype: Option<DVector<f64>>,
+}
+
+impl ConvexRegion {
+    /// Region bounded by the half-spaces `normals * x <= offsets`
+    pub fn from_half_spaces(normals: DMatrix<f64>, offsets: DVector<f64>) -> GraphResult<Self> {
+        if normals.nrows() != offsets.len() {
+            return Err(GraphError::InvalidOperation(format!(
+                "Region has {} normals but {} offsets",
+                normals.nrows(),
+                offsets.len()
+            )));
+        }
+        Ok(Self {
+            normals,
+            offsets,
+            prototype: None,
+        })
+    }
+
+    /// Axis-aligned box between `min` and `max`, with its centre as prototype
+    pub fn bounding_box(min: &[f64], max: &[f64]) -> GraphResult<Self> {
+        if min.len() != max.len() {
+            return Err(GraphError::InvalidOperation(format!(
+                "Box corners have {} and {} dimensions",
+                min.len(),
+                max.len()
+            )));
+        }
+
+        // One upper (x_i <= max_i) and one lower (-x_i <= -min_i) bound per axis
+        let n = min.len();
+        let identity = DMatrix::<f64>::identity(n, n);
+        let mut normals = DMatrix::<f64>::zeros(2 * n, n);
+        normals.rows_mut(0, n).copy_from(&identity);
+        normals.rows_mut(n, n).copy_from(&(-identity));
+        let offsets =
+            DVector::from_iterator(2 * n, max.iter().copied().chain(min.iter().map(|v| -v)));
+
+        let prototype =
+            DVector::from_iterator(n, min.iter().zip(max).map(|(lo, hi)| (lo + hi) / 2.0));
+        Ok(Self::from_half_spaces(normals, offsets)?.with_prototype(prototype))
+    }
+
+    /// Builder method to set the prototype
+    pub fn with_prototype(mut self, prototype: DVector<f64>) -> Self {
+        self.prototype = Some(prototype);
+        self
+    }
+
+    /// Number of dimensions of the space the region lives in
+    pub fn dimensions(&self) -> usize {
+        self.normals.ncols()
+    }
+
+    /// Check whether a point lies inside the region (boundary included)
+    pub fn contains(&self, point: &ConceptPoint) -> bool {
+        if point.dimensions() != self.dimensions() {
+            return false;
+        }
+        let projected = &self.normals * &point.0;
+        projected
+            .iter()
+            .zip(self.offsets.iter())
+            .all(|(value, bound)| *value <= *bound + f64::EPSILON * bound.abs().max(1.0))
+    }
+}
+
+impl Component for ConvexRegion {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+    fn type_name(&self) -> &'static str {
+        "ConvexRegion"
+    }
+}
+
+/// A conceptual space made of weighted quality dimensions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConceptualSpace {
+    pub dimensions: Vec<QualityDimension>,
+    pub metric: DistanceMetric,
+    /// Decay rate turning distance into similarity: `exp(-sensitivity * d)`
+    pub sensitivity: f64,
+}
+
+impl ConceptualSpace {
+    pub fn new(dimensions: Vec<QualityDimension>) -> Self {
+        Self {
+            dimensions,
+            metric: DistanceMetric::default(),
+            sensitivity: 1.0,
+        }
+    }
+
+    /// Builder method to set the distance metric
+    pub fn with_metric(mut self, metric: DistanceMetric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    /// Builder method to set the similarity sensitivity
+    pub fn with_sensitivity(mut self, sensitivity: f64) -> Self {
+        self.sensitivity = sensitivity;
+        self
+    }
+
+    /// Find a dimension's position by name
+    pub fn dimension_index(&self, name: &str) -> Option<usize> {
+        self.dimensions.iter().position(|d| d.name == name)
+    }
+
+    fn check_point(&self, point: &ConceptPoint) -> GraphResult<()> {
+        if point.dimensions() == self.dimensions.len() {
+            Ok(())
+        } else {
+            Err(GraphError::InvalidOperation(format!(
+                "Point has {} coordinates but the space has {} dimensions",
+                point.dimensions(),
+                self.dimensions.len()
+            )))
+        }
+    }
+
+    /// Weighted distance between two points
+    pub fn distance(&self, a: &ConceptPoint, b: &ConceptPoint) -> GraphResult<f64> {
+        self.check_point(a)?;
+        self.check_point(b)?;
+
+        let differences = DVector::from_iterator(
+            self.dimensions.len(),
+            self.dimensions
+                .iter()
+                .enumerate()
+                .map(|(i, dim)| dim.weight * dim.difference(a.0[i], b.0[i])),
+        );
+
+        Ok(match self.metric {
+            DistanceMetric::Euclidean => differences.norm(),
+            DistanceMetric::Manhattan => differences.lp_norm(1),
+            DistanceMetric::Chebyshev => differences.amax(),
+        })
+    }
+
+    /// Similarity in `(0, 1]`, decaying exponentially with distance
+    pub fn similarity(&self, a: &ConceptPoint, b: &ConceptPoint) -> GraphResult<f64> {
+        Ok((-self.sensitivity * self.distance(a, b)?).exp())
+    }
+
+    /// Distance between the concept points of two nodes
+    pub fn node_distance<N, E>(
+        &self,
+        graph: &ContextGraph<N, E>,
+        a: NodeId,
+        b: NodeId,
+    ) -> GraphResult<f64>
+    where
+        N: Clone + Debug,
+        E: Clone + Debug,
+    {
+        self.distance(concept_point(graph, a)?, concept_point(graph, b)?)
+    }
+
+    /// Similarity between the concept points of two nodes
+    pub fn node_similarity<N, E>(
+        &self,
+        graph: &ContextGraph<N, E>,
+        a: NodeId,
+        b: NodeId,
+    ) -> GraphResult<f64>
+    where
+        N: Clone + Debug,
+        E: Clone + Debug,
+    {
+        self.similarity(concept_point(graph, a)?, concept_point(graph, b)?)
+    }
+
+    /// Nodes whose concept point lies inside the region
+    pub fn nodes_in_region<N, E>(
+        &self,
+        graph: &ContextGraph<N, E>,
+        region: &ConvexRegion,
+    ) -> Vec<NodeId>
+    where
+        N: Clone + Debug,
+        E: Clone + Debug,
+    {
+        graph
+            .query_nodes()
+            .where_component::<ConceptPoint>(|point| region.contains(point))
+            .ids()
+            .collect()
+    }
+
+    /// The `k` nodes whose concept points are closest to `point`
+    ///
+    /// Results are ordered by increasing distance; nodes whose points do not
+    /// match the space's dimensionality are ignored.
+    pub fn nearest_nodes<N, E>(
+        &self,
+        graph: &ContextGraph<N, E>,
+        point: &ConceptPoint,
+        k: usize,
+    ) -> GraphResult<Vec<(NodeId, f64)>>
+    where
+        N: Clone + Debug,
+        E: Clone + Debug,
+    {
+        self.check_point(point)?;
+
+        let mut distances: Vec<(NodeId, f64)> = graph
+            .query_nodes()
+            .with::<ConceptPoint>()
+            .entries()
+            .filter_map(|(id, node)| {
+                let other = node.get_component::<ConceptPoint>()?;
+                self.distance(point, other).ok().map(|d| (id, d))
+            })
+            .collect();
+        distances.sort_by(|a, b| a.1.total_cmp(&b.1));
+        distances.truncate(k);
+        Ok(distances)
+    }
+}
+
+fn concept_point<N, E>(graph: &ContextGraph<N, E>, node_id: NodeId) -> GraphResult<&ConceptPoint>
+where
+    N: Clone + Debug,
+    E: Clone + Debug,
+{
+    graph
+        .get_node(node_id)
+        .ok_or(GraphError::NodeNotFound(node_id))?
+        .get_component::<ConceptPoint>()
+        .ok_or_else(|| GraphError::ComponentNotFound("ConceptPoint".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn color_space() -> ConceptualSpace {
+        ConceptualSpace::new(vec![
+            QualityDimension::new("hue", 0.0, 360.0)
+                .circular(360.0)
+                .unwrap(),
+            QualityDimension::new("saturation", 0.0, 1.0),
+            QualityDimension::new("brightness", 0.0, 1.0).with_weight(0.5),
+        ])
+    }
+
+    #[test]
+    fn test_distance_and_similarity() {
+        let space = color_space();
+        let red = ConceptPoint::new([350.0, 1.0, 1.0]);
+        let also_red = ConceptPoint::new([10.0, 1.0, 1.0]);
+        let grey = ConceptPoint::new([10.0, 0.0, 0.5]);
+
+        let near = space.distance(&red, &also_red).unwrap();
+        let far = space.distance(&red, &grey).unwrap();
+        assert!((near - 20.0 / 360.0).abs() < 1e-9);
+        assert!(far > near);
+        assert!(
+            space.similarity(&red, &also_red).unwrap() > space.similarity(&red, &grey).unwrap()
+        );
+        assert_eq!(space.similarity(&red, &red).unwrap(), 1.0);
+
+        let manhattan = space.clone().with_metric(DistanceMetric::Manhattan);
+        assert!(manhattan.distance(&red, &grey).unwrap() >= far);
+
+        assert!(space.distance(&red, &ConceptPoint::new([1.0])).is_err());
+
+        for period in [0.0, -360.0, f64::NAN, f64::INFINITY] {
+            assert!(matches!(
+                QualityDimension::new("hue", 0.0, 360.0).circular(period),
+                Err(GraphError::InvalidOperation(_))
+            ));
+        }
+        assert!(serde_json::from_str::<DimensionScale>(r#"{"Circular":{"period":0.0}}"#).is_err());
+        let hue = serde_json::to_string(&space.dimensions[0]).unwrap();
+        let hue: QualityDimension = serde_json::from_str(&hue).unwrap();
+        assert_eq!(hue.scale(), DimensionScale::Circular { period: 360.0 });
+        assert!(serde_json::from_str::<QualityDimension>(
+            r#"{"name":"hue","range":[0.0,1.0],"weight":1.0,"scale":{"Circular":{"period":-1.0}}}"#
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_region_and_nearest_queries() {
+        let space = ConceptualSpace::new(vec![
+            QualityDimension::new("x", 0.0, 10.0),
+            QualityDimension::new("y", 0.0, 10.0),
+        ]);
+        let mut graph = ContextGraph::<&str, ()>::new("Concepts");
+
+        let mut place = |name, x, y| {
+            let id = graph.add_node(name);
+            graph
+                .get_node_mut(id)
+                .unwrap()
+                .add_component(ConceptPoint::new([x, y]))
+                .unwrap();
+            id
+        };
+        let a = place("a", 1.0, 1.0);
+        let b = place("b", 2.0, 2.0);
+        let c = place("c", 8.0, 8.0);
+        graph.add_node("unplaced");
+
+        let region = ConvexRegion::bounding_box(&[0.0, 0.0], &[5.0, 5.0]).unwrap();
+        assert_eq!(space.nodes_in_region(&graph, &region), vec![a, b]);
+        assert_eq!(region.prototype, Some(DVector::from_vec(vec![2.5, 2.5])));
+
+        let nearest = space
+            .nearest_nodes(&graph, &ConceptPoint::new([9.0, 9.0]), 2)
+            .unwrap();
+        assert_eq!(
+            nearest.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            vec![c, b]
+        );
+        assert!(space.node_distance(&graph, a, b).unwrap() > 0.0);
+    }
+}
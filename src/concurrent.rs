@@ -0,0 +1,264 @@
+//! Concurrent access with snapshot-isolated readers
+//!
+//! A [`GraphWriter`] owns the right to change a graph and publishes each new
+//! version atomically. Any number of [`GraphReader`] handles, on any thread,
+//! load the latest published version without locking: a reader keeps the
+//! [`GraphSnapshot`] it loaded for as long as it likes and never observes a
+//! half-applied update.
+//!
+//! Updates work on a copy-on-write clone of the current version, so readers
+//! holding older snapshots are unaffected and a failed update publishes
+//! nothing. The clone itself is O(1); each write then copies only the entry
+//! it touches, so a version shares everything it did not change with the one
+//! before it. Nested graphs in [`Subgraph`] components are cloned the same
+//! way, so replacing one costs no more than editing the outer graph.
+
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use arc_swap::ArcSwap;
+use std::fmt::Debug;
+use std::ops::Deref;
+use std::sync::Arc;
+
+/// One published version of a concurrently shared graph
+#[derive(Debug)]
+pub struct GraphSnapshot<N, E> {
+    version: u64,
+    graph: ContextGraph<N, E>,
+}
+
+impl<N, E> GraphSnapshot<N, E> {
+    /// Number of updates published before this one
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn graph(&self) -> &ContextGraph<N, E> {
+        &self.graph
+    }
+}
+
+impl<N, E> Deref for GraphSnapshot<N, E> {
+    type Target = ContextGraph<N, E>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.graph
+    }
+}
+
+/// Cheap, cloneable read handle; never blocks
+pub struct GraphReader<N, E> {
+    current: Arc<ArcSwap<GraphSnapshot<N, E>>>,
+}
+
+impl<N, E> Clone for GraphReader<N, E> {
+    fn clone(&self) -> Self {
+        Self {
+            current: self.current.clone(),
+        }
+    }
+}
+
+impl<N, E> GraphReader<N, E> {
+    /// The latest published version, kept alive for as long as it is held
+    pub fn snapshot(&self) -> Arc<GraphSnapshot<N, E>> {
+        self.current.load_full()
+    }
+
+    /// Run a short query against the latest version
+    pub fn read<R>(&self, f: impl FnOnce(&ContextGraph<N, E>) -> R) -> R {
+        f(&self.current.load().graph)
+    }
+
+    /// Version number of the latest published version
+    pub fn version(&self) -> u64 {
+        self.current.load().version
+    }
+}
+
+/// The single handle allowed to publish new versions
+///
+/// Not `Clone`; share it behind a `Mutex` if several tasks must write.
+pub struct GraphWriter<N, E> {
+    current: Arc<ArcSwap<GraphSnapshot<N, E>>>,
+}
+
+impl<N, E> GraphWriter<N, E>
+where
+    N: Clone + Debug,
+    E: Clone + Debug,
+{
+    /// Publish `graph` as version 0
+    pub fn new(graph: ContextGraph<N, E>) -> Self {
+        Self {
+            current: Arc::new(ArcSwap::from_pointee(GraphSnapshot { version: 0, graph })),
+        }
+    }
+
+    /// A new read handle for this graph
+    pub fn reader(&self) -> GraphReader<N, E> {
+        GraphReader {
+            current: self.current.clone(),
+        }
+    }
+
+    /// The latest published version
+    pub fn snapshot(&self) -> Arc<GraphSnapshot<N, E>> {
+        self.current.load_full()
+    }
+
+    pub fn version(&self) -> u64 {
+        self.current.load().version
+    }
+
+    /// Apply `f` to a copy of the current graph and publish the result
+    ///
+    /// If `f` fails, or the result breaks an invariant, nothing is published.
+    /// Besides the writes `f` makes, each call checks every invariant and
+    /// publishes a version; use [`update_all`](Self::update_all) to pay that
+    /// once for many small updates.
+    pub fn update<R>(
+        &mut self,
+        f: impl FnOnce(&mut ContextGraph<N, E>) -> GraphResult<R>,
+    ) -> GraphResult<R> {
+        let mut results = self.update_all([f])?;
+        Ok(results.remove(0))
+    }
+
+    /// Apply `updates` in order to one copy of the current graph and publish
+    /// the result as a single version
+    ///
+    /// Invariants are checked once, after the last update. If any update
+    /// fails, or the result breaks an invariant, nothing is published.
+    pub fn update_all<R, F>(&mut self, updates: impl IntoIterator<Item = F>) -> GraphResult<Vec<R>>
+    where
+        F: FnOnce(&mut ContextGraph<N, E>) -> GraphResult<R>,
+    {
+        let current = self.current.load_full();
+        let mut graph = current.graph.clone();
+        let results = updates
+            .into_iter()
+            .map(|f| f(&mut graph))
+            .collect::<GraphResult<Vec<R>>>()?;
+        graph.check_invariants()?;
+
+        let version = current.version + 1;
+        self.current
+            .store(Arc::new(GraphSnapshot { version, graph }));
+        Ok(results)
+    }
+
+    /// Publish `graph` as the next version, replacing the current one
+    pub fn replace(&mut self, graph: ContextGraph<N, E>) -> GraphResult<u64> {
+        graph.check_invariants()?;
+        let version = self.version() + 1;
+        self.current
+            .store(Arc::new(GraphSnapshot { version, graph }));
+        Ok(version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_handles_are_send_and_sync() {
+        assert_send_sync::<GraphReader<String, u32>>();
+        assert_send_sync::<GraphWriter<String, u32>>();
+        assert_send_sync::<Arc<GraphSnapshot<String, u32>>>();
+    }
+
+    #[test]
+    fn test_readers_keep_their_snapshot() {
+        let mut writer = GraphWriter::new(ContextGraph::<String, u32>::new("Live"));
+        let reader = writer.reader();
+        let before = reader.snapshot();
+
+        let a = writer
+            .update(|graph| Ok(graph.add_node("a".to_string())))
+            .unwrap();
+        assert_eq!(before.version(), 0);
+        assert_eq!(before.node_count(), 0);
+        assert_eq!(reader.version(), 1);
+        assert_eq!(
+            reader.read(|graph| graph.get_node_value(a).cloned()),
+            Some("a".to_string())
+        );
+
+        let failed = writer.update(|graph| {
+            graph.add_node("b".to_string());
+            graph.add_edge(a, NodeId::new(), 1)
+        });
+        assert!(failed.is_err());
+        assert_eq!(reader.snapshot().node_count(), 1);
+        assert_eq!(reader.version(), 1);
+    }
+
+    #[test]
+    fn test_batched_updates_publish_one_version() {
+        let mut writer = GraphWriter::new(ContextGraph::<u32, ()>::new("Batch"));
+        let ids = writer
+            .update_all(
+                (0..3).map(|i| move |graph: &mut ContextGraph<u32, ()>| Ok(graph.add_node(i))),
+            )
+            .unwrap();
+        assert_eq!(writer.version(), 1);
+        assert_eq!(writer.snapshot().node_count(), 3);
+
+        let before = writer.snapshot();
+        let edges = [(ids[0], ids[1]), (ids[0], NodeId::new())];
+        let failed = writer.update_all(
+            edges.map(|(a, b)| move |graph: &mut ContextGraph<u32, ()>| graph.add_edge(a, b, ())),
+        );
+        assert!(failed.is_err());
+        assert_eq!(writer.version(), 1);
+        assert_eq!(writer.snapshot().edge_count(), 0);
+
+        // Versions share the entries an update did not touch
+        writer
+            .update(|graph| {
+                graph.get_node_mut(ids[2]).unwrap().value = 20;
+                Ok(())
+            })
+            .unwrap();
+        let after = writer.snapshot();
+        let entry = |s: &GraphSnapshot<u32, ()>, id| s.get_node(id).unwrap() as *const _;
+        assert_eq!(entry(&before, ids[0]), entry(&after, ids[0]));
+        assert_ne!(entry(&before, ids[2]), entry(&after, ids[2]));
+    }
+
+    #[test]
+    fn test_concurrent_readers_see_whole_updates() {
+        let mut writer = GraphWriter::new(ContextGraph::<u32, ()>::new("Ingest"));
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let reader = writer.reader();
+                thread::spawn(move || {
+                    while reader.version() < 50 {
+                        let snapshot = reader.snapshot();
+                        // Every update adds a pair of nodes joined by one edge
+                        assert_eq!(snapshot.node_count(), 2 * snapshot.version() as usize);
+                        assert_eq!(snapshot.edge_count(), snapshot.version() as usize);
+                    }
+                })
+            })
+            .collect();
+
+        for i in 0..50 {
+            writer
+                .update(|graph| {
+                    let a = graph.add_node(i);
+                    let b = graph.add_node(i);
+                    graph.add_edge(a, b, ())
+                })
+                .unwrap();
+        }
+        for reader in readers {
+            reader.join().unwrap();
+        }
+    }
+}
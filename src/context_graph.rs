@@ -5,10 +5,18 @@
 //! - Component system for extensibility
 //! - Domain-specific features
 //! - Recursive graph support
+//! - Cheap copy-on-write clones: a write copies only the entry it touches
 
+use crate::hierarchy::WalkControl;
+use crate::ids::{IdGenerator, RandomIds};
+use crate::persistent::PersistentGraph;
+use crate::query::{EdgeQuery, NodeQuery};
 use crate::types::*;
-use petgraph::graph::{EdgeIndex, Graph, NodeIndex};
-use std::collections::HashMap;
+use imbl::HashMap;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::EdgeRef;
+use petgraph::Direction;
+use std::collections::HashSet;
 use std::fmt::Debug;
 
 /// Trait for graph invariants that must be maintained
@@ -22,20 +30,20 @@ pub trait GraphInvariant<N, E>: Send + Sync {
 pub struct ContextGraph<N, E> {
     pub id: ContextGraphId,
 
-    // The actual PetGraph - we get all its algorithms!
-    pub graph: Graph<NodeEntry<N>, EdgeEntry<E>>,
+    // PetGraph-compatible storage - we get all its algorithms!
+    // Shared between clones; a write copies only the entry it touches
+    pub graph: PersistentGraph<NodeEntry<N>, EdgeEntry<E>>,
 
-    // Additional mappings for our ID system
+    // Additional mappings for our ID system; each entry's ID is the reverse
     node_id_map: HashMap<NodeId, NodeIndex>,
     edge_id_map: HashMap<EdgeId, EdgeIndex>,
 
-    // Reverse mappings
-    node_index_map: HashMap<NodeIndex, NodeId>,
-    edge_index_map: HashMap<EdgeIndex, EdgeId>,
-
     pub metadata: Metadata,
 
     pub invariants: Vec<Box<dyn GraphInvariant<N, E>>>,
+
+    // Supplies IDs for nodes and edges added without one
+    pub(crate) id_generator: Box<dyn IdGenerator>,
 }
 
 impl<N, E> Debug for ContextGraph<N, E>
@@ -53,6 +61,8 @@ where
     }
 }
 
+/// Clones share the node and edge storage, ID mappings and components; a
+/// write copies only the entries it touches
 impl<N, E> Clone for ContextGraph<N, E>
 where
     N: Clone,
@@ -64,10 +74,9 @@ where
             graph: self.graph.clone(),
             node_id_map: self.node_id_map.clone(),
             edge_id_map: self.edge_id_map.clone(),
-            node_index_map: self.node_index_map.clone(),
-            edge_index_map: self.edge_index_map.clone(),
             metadata: self.metadata.clone(),
             invariants: self.invariants.iter().map(|inv| inv.clone_box()).collect(),
+            id_generator: self.id_generator.clone_box(),
         }
     }
 }
@@ -85,57 +94,157 @@ where
 
         Self {
             id: ContextGraphId::new(),
-            graph: Graph::new(),
+            graph: PersistentGraph::new(),
             node_id_map: HashMap::new(),
             edge_id_map: HashMap::new(),
-            node_index_map: HashMap::new(),
-            edge_index_map: HashMap::new(),
             metadata,
             invariants: Vec::new(),
+            id_generator: Box::new(RandomIds),
+        }
+    }
+
+    /// Use `generator` for the IDs of nodes and edges added without one
+    pub fn with_id_generator(mut self, generator: impl IdGenerator + 'static) -> Self {
+        self.set_id_generator(generator);
+        self
+    }
+
+    pub fn set_id_generator(&mut self, generator: impl IdGenerator + 'static) {
+        self.id_generator = Box::new(generator);
+    }
+
+    /// Wrap prepared storage, indexing its IDs once
+    ///
+    /// Node and edge IDs must be unique. Invariants are not checked.
+    pub(crate) fn from_parts(
+        id: ContextGraphId,
+        metadata: Metadata,
+        graph: PersistentGraph<NodeEntry<N>, EdgeEntry<E>>,
+        invariants: Vec<Box<dyn GraphInvariant<N, E>>>,
+    ) -> Self {
+        let node_id_map = graph
+            .node_indices()
+            .map(|idx| (graph[idx].id, idx))
+            .collect();
+        let edge_id_map = graph
+            .edge_indices()
+            .map(|idx| (graph[idx].id, idx))
+            .collect();
+        Self {
+            id,
+            graph,
+            node_id_map,
+            edge_id_map,
+            metadata,
+            invariants,
+            id_generator: Box::new(RandomIds),
         }
     }
 
+    /// An empty graph with this graph's ID, metadata, invariants and ID
+    /// generator
+    pub(crate) fn empty_like(&self) -> Self {
+        let mut graph = Self::new("");
+        graph.id = self.id;
+        graph.metadata = self.metadata.clone();
+        graph.invariants = self.invariants.iter().map(|inv| inv.clone_box()).collect();
+        graph.id_generator = self.id_generator.clone_box();
+        graph
+    }
+
     /// Add a node - wraps PetGraph's add_node
     pub fn add_node(&mut self, value: N) -> NodeId {
-        let node_entry = NodeEntry::new(value);
-        let node_id = node_entry.id;
+        let node_id = self.fresh_node_id();
+        self.insert_node_entry(NodeEntry::with_id(node_id, value));
+        node_id
+    }
 
-        // Add to PetGraph
-        let node_index = self.graph.add_node(node_entry);
+    /// The next generated node ID not already in this graph
+    pub(crate) fn fresh_node_id(&mut self) -> NodeId {
+        // Skip generated IDs already taken by caller-provided ones
+        let mut node_id = self.id_generator.node_id();
+        while self.node_id_map.contains_key(&node_id) {
+            node_id = self.id_generator.node_id();
+        }
+        node_id
+    }
 
-        // Maintain our mappings
-        self.node_id_map.insert(node_id, node_index);
-        self.node_index_map.insert(node_index, node_id);
+    /// The next generated edge ID not already in this graph
+    pub(crate) fn fresh_edge_id(&mut self) -> EdgeId {
+        let mut edge_id = self.id_generator.edge_id();
+        while self.edge_id_map.contains_key(&edge_id) {
+            edge_id = self.id_generator.edge_id();
+        }
+        edge_id
+    }
 
-        node_id
+    /// Add a node with a caller-provided ID
+    pub fn add_node_with_id(&mut self, node_id: NodeId, value: N) -> GraphResult<NodeId> {
+        if self.node_id_map.contains_key(&node_id) {
+            return Err(GraphError::DuplicateNode(node_id));
+        }
+        self.insert_node_entry(NodeEntry::with_id(node_id, value));
+        Ok(node_id)
     }
 
     /// Add an edge - wraps PetGraph's add_edge
     pub fn add_edge(&mut self, source: NodeId, target: NodeId, value: E) -> GraphResult<EdgeId> {
-        // Get PetGraph indices
-        let source_idx = self
-            .node_id_map
-            .get(&source)
-            .ok_or(GraphError::NodeNotFound(source))?;
-        let target_idx = self
-            .node_id_map
-            .get(&target)
-            .ok_or(GraphError::NodeNotFound(target))?;
+        let edge_id = self.fresh_edge_id();
+        self.add_edge_with_id(edge_id, source, target, value)
+    }
+
+    /// Add an edge with a caller-provided ID
+    pub fn add_edge_with_id(
+        &mut self,
+        edge_id: EdgeId,
+        source: NodeId,
+        target: NodeId,
+        value: E,
+    ) -> GraphResult<EdgeId> {
+        if self.edge_id_map.contains_key(&edge_id) {
+            return Err(GraphError::DuplicateEdge(edge_id));
+        }
+        self.insert_edge_entry(EdgeEntry {
+            id: edge_id,
+            source,
+            target,
+            value,
+            components: ComponentStorage::new(),
+        })?;
+
+        // Check invariants, taking the edge back out if one rejects it
+        if let Err(error) = self.check_invariants() {
+            self.remove_edge(edge_id);
+            return Err(error);
+        }
 
-        let edge_entry = EdgeEntry::new(source, target, value);
-        let edge_id = edge_entry.id;
+        Ok(edge_id)
+    }
 
-        // Add to PetGraph
-        let edge_index = self.graph.add_edge(*source_idx, *target_idx, edge_entry);
+    /// Insert a prepared node entry, keeping its ID and components
+    pub(crate) fn insert_node_entry(&mut self, entry: NodeEntry<N>) -> NodeIndex {
+        let node_id = entry.id;
+        let node_index = self.graph.add_node(entry);
+        self.node_id_map.insert(node_id, node_index);
+        node_index
+    }
 
-        // Maintain mappings
+    /// Insert a prepared edge entry, keeping its ID and components
+    ///
+    /// Both endpoints must already be present. Invariants are not checked.
+    pub(crate) fn insert_edge_entry(&mut self, entry: EdgeEntry<E>) -> GraphResult<EdgeIndex> {
+        let source_idx = *self
+            .node_id_map
+            .get(&entry.source)
+            .ok_or(GraphError::NodeNotFound(entry.source))?;
+        let target_idx = *self
+            .node_id_map
+            .get(&entry.target)
+            .ok_or(GraphError::NodeNotFound(entry.target))?;
+        let edge_id = entry.id;
+        let edge_index = self.graph.add_edge(source_idx, target_idx, entry);
         self.edge_id_map.insert(edge_id, edge_index);
-        self.edge_index_map.insert(edge_index, edge_id);
-
-        // Check invariants
-        self.check_invariants()?;
-
-        Ok(edge_id)
+        Ok(edge_index)
     }
 
     /// Get node by our ID
@@ -188,7 +297,7 @@ where
             .map(|component| {
                 component
                     .into_iter()
-                    .filter_map(|idx| self.node_index_map.get(&idx).copied())
+                    .map(|idx| self.graph[idx].id)
                     .collect()
             })
             .collect()
@@ -199,10 +308,7 @@ where
         use petgraph::algo::toposort;
 
         match toposort(&self.graph, None) {
-            Ok(sorted) => Ok(sorted
-                .into_iter()
-                .filter_map(|idx| self.node_index_map.get(&idx).copied())
-                .collect()),
+            Ok(sorted) => Ok(sorted.into_iter().map(|idx| self.graph[idx].id).collect()),
             Err(_) => Err(GraphError::CycleDetected),
         }
     }
@@ -213,17 +319,32 @@ where
     pub fn query_nodes_with_component<T: Component + 'static>(&self) -> Vec<NodeId> {
         self.graph
             .node_indices()
-            .filter_map(|idx| {
-                let node = &self.graph[idx];
-                if node.components.has::<T>() {
-                    self.node_index_map.get(&idx).copied()
-                } else {
-                    None
-                }
-            })
+            .map(|idx| &self.graph[idx])
+            .filter(|node| node.components.has::<T>())
+            .map(|node| node.id)
+            .collect()
+    }
+
+    /// Query edges by component type
+    pub fn query_edges_with_component<T: Component + 'static>(&self) -> Vec<EdgeId> {
+        self.graph
+            .edge_indices()
+            .map(|idx| &self.graph[idx])
+            .filter(|edge| edge.components.has::<T>())
+            .map(|edge| edge.id)
             .collect()
     }
 
+    /// Start a fluent query over nodes
+    pub fn query_nodes(&self) -> NodeQuery<'_, Self> {
+        NodeQuery::new(self)
+    }
+
+    /// Start a fluent query over edges
+    pub fn query_edges(&self) -> EdgeQuery<'_, Self> {
+        EdgeQuery::new(self)
+    }
+
     /// Get all subgraph nodes (for recursion)
     pub fn get_subgraph_nodes(&self) -> Vec<NodeId>
     where
@@ -233,51 +354,20 @@ where
         self.query_nodes_with_component::<Subgraph<N, E>>()
     }
 
-    /// Recursive visitor using PetGraph's DFS
+    /// Recursive visitor over this graph and all nested subgraphs
+    ///
+    /// The visitor receives each graph with its nesting depth. See
+    /// [`ContextGraph::walk_hierarchy`] for path context and early exit.
     pub fn visit_recursive<F>(&self, mut visitor: F)
     where
         F: FnMut(&ContextGraph<N, E>, usize),
         N: 'static + Send + Sync,
         E: 'static + Send + Sync,
     {
-        use petgraph::visit::Dfs;
-
-        // Visit this graph
-        visitor(self, 0);
-
-        // Use DFS to find all nodes with subgraphs
-        let mut dfs = Dfs::new(
-            &self.graph,
-            self.graph
-                .node_indices()
-                .next()
-                .unwrap_or(NodeIndex::new(0)),
-        );
-
-        while let Some(nx) = dfs.next(&self.graph) {
-            if let Some(node) = self.graph.node_weight(nx) {
-                if let Some(subgraph) = node.get_component::<Subgraph<N, E>>() {
-                    subgraph.graph.visit_recursive_impl(&mut visitor, 1);
-                }
-            }
-        }
-    }
-
-    fn visit_recursive_impl<F>(&self, visitor: &mut F, depth: usize)
-    where
-        F: FnMut(&ContextGraph<N, E>, usize),
-        N: 'static + Send + Sync,
-        E: 'static + Send + Sync,
-    {
-        visitor(self, depth);
-
-        for node_id in self.get_subgraph_nodes() {
-            if let Some(node) = self.get_node(node_id) {
-                if let Some(subgraph) = node.get_component::<Subgraph<N, E>>() {
-                    subgraph.graph.visit_recursive_impl(visitor, depth + 1);
-                }
-            }
-        }
+        self.walk_hierarchy(|graph, ctx| {
+            visitor(graph, ctx.depth());
+            WalkControl::Continue
+        });
     }
 
     // Invariant checking
@@ -288,48 +378,60 @@ where
         Ok(())
     }
 
-    /// Remove a node and clean up mappings (for testing)
+    /// Remove a node and its edges
     #[doc(hidden)]
     pub fn remove_node(&mut self, node_id: NodeId) -> Option<NodeEntry<N>> {
-        if let Some(node_idx) = self.node_id_map.remove(&node_id) {
-            // First, get the node data before removal
-            let node_data = self.graph.node_weight(node_idx).cloned();
-
-            // Remove the node from PetGraph
-            self.graph.remove_node(node_idx);
-
-            // Remove from reverse mapping
-            self.node_index_map.remove(&node_idx);
-
-            // PetGraph shifts indices when removing nodes, so we need to rebuild our mappings
-            // Clear and rebuild all mappings
-            self.node_id_map.clear();
-            self.node_index_map.clear();
-            self.edge_id_map.clear();
-            self.edge_index_map.clear();
-
-            // Rebuild node mappings
-            for node_idx in self.graph.node_indices() {
-                if let Some(node) = self.graph.node_weight(node_idx) {
-                    self.node_id_map.insert(node.id, node_idx);
-                    self.node_index_map.insert(node_idx, node.id);
-                }
+        let node_idx = *self.node_id_map.get(&node_id)?;
+        for direction in [Direction::Outgoing, Direction::Incoming] {
+            while let Some(edge_idx) = self
+                .graph
+                .edges_directed(node_idx, direction)
+                .next()
+                .map(|edge| edge.id())
+            {
+                self.remove_edge_at(edge_idx);
             }
+        }
 
-            // Rebuild edge mappings
-            for edge_idx in self.graph.edge_indices() {
-                if let Some(edge) = self.graph.edge_weight(edge_idx) {
-                    self.edge_id_map.insert(edge.id, edge_idx);
-                    self.edge_index_map.insert(edge_idx, edge.id);
-                }
-            }
+        let node = self.graph.remove_node(node_idx)?;
+        self.node_id_map.remove(&node_id);
+        // The last node moved into the freed index
+        if let Some(moved) = self.graph.node_weight(node_idx) {
+            self.node_id_map.insert(moved.id, node_idx);
+        }
+        Some(node)
+    }
 
-            node_data
-        } else {
-            None
+    /// Remove a set of nodes and their edges
+    pub(crate) fn remove_nodes(&mut self, node_ids: &HashSet<NodeId>) {
+        for &node_id in node_ids {
+            self.remove_node(node_id);
+        }
+    }
+
+    /// Remove an edge, leaving its endpoints in place
+    pub fn remove_edge(&mut self, edge_id: EdgeId) -> Option<EdgeEntry<E>> {
+        let edge_idx = *self.edge_id_map.get(&edge_id)?;
+        self.remove_edge_at(edge_idx)
+    }
+
+    /// Remove a set of edges, leaving their endpoints in place
+    pub(crate) fn remove_edges(&mut self, edge_ids: &HashSet<EdgeId>) {
+        for &edge_id in edge_ids {
+            self.remove_edge(edge_id);
         }
     }
 
+    fn remove_edge_at(&mut self, edge_idx: EdgeIndex) -> Option<EdgeEntry<E>> {
+        let edge = self.graph.remove_edge(edge_idx)?;
+        self.edge_id_map.remove(&edge.id);
+        // The last edge moved into the freed index
+        if let Some(moved) = self.graph.edge_weight(edge_idx) {
+            self.edge_id_map.insert(moved.id, edge_idx);
+        }
+        Some(edge)
+    }
+
     // Convenience methods for common operations
 
     /// Get the number of nodes in the graph
@@ -371,8 +473,6 @@ where
 
     /// Get the degree of a node (in + out edges)
     pub fn degree(&self, node_id: NodeId) -> usize {
-        use petgraph::Direction;
-
         if let Some(node_idx) = self.node_id_map.get(&node_id) {
             self.graph
                 .edges_directed(*node_idx, Direction::Incoming)
@@ -388,20 +488,161 @@ where
 
     /// Get all nodes as an iterator
     pub fn get_all_nodes(&self) -> impl Iterator<Item = (NodeId, &NodeEntry<N>)> {
-        self.graph.node_indices().filter_map(move |idx| {
-            self.node_index_map
-                .get(&idx)
-                .and_then(|id| self.graph.node_weight(idx).map(|node| (*id, node)))
-        })
+        self.graph.node_weights().map(|node| (node.id, node))
     }
 
     /// Get all edges as an iterator
     pub fn get_all_edges(&self) -> impl Iterator<Item = (EdgeId, &EdgeEntry<E>)> {
-        self.graph.edge_indices().filter_map(move |idx| {
-            self.edge_index_map
-                .get(&idx)
-                .and_then(|id| self.graph.edge_weight(idx).map(|edge| (*id, edge)))
-        })
+        self.graph.edge_weights().map(|edge| (edge.id, edge))
+    }
+
+    // Neighborhood and adjacency, keyed by our IDs
+
+    /// Get the nodes reachable over one outgoing edge
+    pub fn successors(&self, node_id: NodeId) -> Vec<NodeId> {
+        self.adjacent(node_id, Some(Direction::Outgoing))
+    }
+
+    /// Get the nodes with an edge pointing at this node
+    pub fn predecessors(&self, node_id: NodeId) -> Vec<NodeId> {
+        self.adjacent(node_id, Some(Direction::Incoming))
+    }
+
+    /// Get the nodes connected to this node in either direction
+    pub fn neighbors(&self, node_id: NodeId) -> Vec<NodeId> {
+        self.adjacent(node_id, None)
+    }
+
+    fn adjacent(&self, node_id: NodeId, direction: Option<Direction>) -> Vec<NodeId> {
+        let Some(&idx) = self.node_id_map.get(&node_id) else {
+            return Vec::new();
+        };
+        let indices: Vec<NodeIndex> = match direction {
+            Some(direction) => self.graph.neighbors_directed(idx, direction).collect(),
+            None => self.graph.neighbors_undirected(idx).collect(),
+        };
+
+        // PetGraph yields one neighbor per edge (most recent first), so parallel
+        // edges produce duplicates - keep the first occurrence in insertion order
+        let mut seen = HashSet::new();
+        indices
+            .into_iter()
+            .rev()
+            .map(|n| self.graph[n].id)
+            .filter(|id| seen.insert(*id))
+            .collect()
+    }
+
+    /// Get the edges ending at this node
+    pub fn incoming_edges(&self, node_id: NodeId) -> impl Iterator<Item = (EdgeId, &EdgeEntry<E>)> {
+        self.directed_edges(node_id, Direction::Incoming)
+    }
+
+    /// Get the edges starting at this node
+    pub fn outgoing_edges(&self, node_id: NodeId) -> impl Iterator<Item = (EdgeId, &EdgeEntry<E>)> {
+        self.directed_edges(node_id, Direction::Outgoing)
+    }
+
+    fn directed_edges(
+        &self,
+        node_id: NodeId,
+        direction: Direction,
+    ) -> impl Iterator<Item = (EdgeId, &EdgeEntry<E>)> {
+        let mut edges: Vec<_> = self
+            .node_id_map
+            .get(&node_id)
+            .into_iter()
+            .flat_map(|idx| self.graph.edges_directed(*idx, direction))
+            .map(|edge| (edge.weight().id, edge.weight()))
+            .collect();
+        edges.reverse();
+        edges.into_iter()
+    }
+
+    /// Get the edges from `source` to `target` (direction matters)
+    pub fn edges_between(
+        &self,
+        source: NodeId,
+        target: NodeId,
+    ) -> impl Iterator<Item = (EdgeId, &EdgeEntry<E>)> {
+        self.outgoing_edges(source)
+            .filter(move |(_, edge)| edge.target == target)
+    }
+
+    /// Get all nodes within `k` hops of `center`, ignoring edge direction
+    ///
+    /// The center itself is included at distance 0. Nodes are returned in
+    /// breadth-first order.
+    pub fn neighborhood(&self, center: NodeId, k: usize) -> Vec<NodeId> {
+        if !self.node_id_map.contains_key(&center) {
+            return Vec::new();
+        }
+
+        let mut visited = HashSet::from([center]);
+        let mut order = vec![center];
+        let mut frontier = vec![center];
+
+        for _ in 0..k {
+            let mut next = Vec::new();
+            for node_id in frontier {
+                for neighbor in self.neighbors(node_id) {
+                    if visited.insert(neighbor) {
+                        order.push(neighbor);
+                        next.push(neighbor);
+                    }
+                }
+            }
+            if next.is_empty() {
+                break;
+            }
+            frontier = next;
+        }
+
+        order
+    }
+
+    /// Extract the `k`-hop neighborhood of `center` as a new graph
+    ///
+    /// Node and edge IDs, values and components are preserved.
+    pub fn neighborhood_graph(&self, center: NodeId, k: usize) -> GraphResult<ContextGraph<N, E>> {
+        if !self.node_id_map.contains_key(&center) {
+            return Err(GraphError::NodeNotFound(center));
+        }
+        Ok(self.induced_subgraph(self.neighborhood(center, k)))
+    }
+
+    /// Build a new graph containing the given nodes and every edge between them
+    ///
+    /// Unknown node IDs are ignored. Node and edge IDs, values and components
+    /// are preserved; invariants are not carried over.
+    pub fn induced_subgraph(&self, nodes: impl IntoIterator<Item = NodeId>) -> ContextGraph<N, E> {
+        let name = self
+            .metadata
+            .properties
+            .get("name")
+            .and_then(|v| v.as_str())
+            .unwrap_or("Graph");
+        let mut subgraph = ContextGraph::new(format!("{name} (subgraph)"));
+
+        for node_id in nodes {
+            if subgraph.node_id_map.contains_key(&node_id) {
+                continue;
+            }
+            if let Some(node) = self.get_node(node_id) {
+                subgraph.insert_node_entry(node.clone());
+            }
+        }
+
+        for (_, edge) in self.get_all_edges() {
+            if subgraph.node_id_map.contains_key(&edge.source)
+                && subgraph.node_id_map.contains_key(&edge.target)
+            {
+                // Both endpoints are present, so this cannot fail
+                let _ = subgraph.insert_edge_entry(edge.clone());
+            }
+        }
+
+        subgraph
     }
 
     /// Find all paths between two nodes (simple wrapper for find_paths)
@@ -415,16 +656,25 @@ where
         N: 'static + Send + Sync,
         E: 'static + Send + Sync,
     {
-        let mut count = self.node_count();
-
-        for node_id in self.get_subgraph_nodes() {
-            if let Some(node) = self.get_node(node_id) {
-                if let Some(subgraph) = node.get_component::<Subgraph<N, E>>() {
-                    count += subgraph.graph.total_node_count();
-                }
-            }
-        }
+        let mut count = 0;
+        self.walk_hierarchy(|graph, _| {
+            count += graph.node_count();
+            WalkControl::Continue
+        });
+        count
+    }
 
+    /// Count total edges including subgraphs
+    pub fn total_edge_count(&self) -> usize
+    where
+        N: 'static + Send + Sync,
+        E: 'static + Send + Sync,
+    {
+        let mut count = 0;
+        self.walk_hierarchy(|graph, _| {
+            count += graph.edge_count();
+            WalkControl::Continue
+        });
         count
     }
 }
@@ -461,11 +711,7 @@ where
         // Convert to our IDs
         paths
             .into_iter()
-            .map(|path| {
-                path.into_iter()
-                    .filter_map(|idx| self.node_index_map.get(&idx).copied())
-                    .collect()
-            })
+            .map(|path| path.into_iter().map(|idx| self.graph[idx].id).collect())
             .collect()
     }
 
@@ -479,23 +725,6 @@ where
         Self::new("MST")
     }
 
-    /// Page rank algorithm
-    pub fn page_rank(&self, _damping_factor: f64, _max_iterations: usize) -> HashMap<NodeId, f64> {
-        // We can implement PageRank using PetGraph's structure
-        let mut ranks = HashMap::new();
-
-        // Initialize ranks
-        let n = self.graph.node_count() as f64;
-        for node_id in self.node_index_map.values() {
-            ranks.insert(*node_id, 1.0 / n);
-        }
-
-        // Iterate...
-        // (simplified implementation)
-
-        ranks
-    }
-
     /// Get the PetGraph node index for a NodeId (for testing)
     #[doc(hidden)]
     pub fn get_node_index(&self, node_id: NodeId) -> Option<NodeIndex> {
@@ -566,4 +795,81 @@ mod tests {
         assert!(labeled.contains(&n1));
         assert!(labeled.contains(&n3));
     }
+
+    #[test]
+    fn test_adjacency_by_node_id() {
+        let mut graph = ContextGraph::<&str, &str>::new("Adjacency");
+
+        let a = graph.add_node("A");
+        let b = graph.add_node("B");
+        let c = graph.add_node("C");
+        let d = graph.add_node("D");
+
+        let ab = graph.add_edge(a, b, "ab").unwrap();
+        let ab2 = graph.add_edge(a, b, "ab2").unwrap();
+        graph.add_edge(a, c, "ac").unwrap();
+        graph.add_edge(c, d, "cd").unwrap();
+
+        assert_eq!(graph.successors(a), vec![b, c]);
+        assert_eq!(graph.predecessors(b), vec![a]);
+        assert_eq!(graph.neighbors(c), vec![a, d]);
+        assert_eq!(graph.outgoing_edges(a).count(), 3);
+        assert_eq!(graph.incoming_edges(d).count(), 1);
+
+        let between: Vec<EdgeId> = graph.edges_between(a, b).map(|(id, _)| id).collect();
+        assert_eq!(between, vec![ab, ab2]);
+        assert_eq!(graph.edges_between(b, a).count(), 0);
+
+        assert_eq!(graph.neighborhood(b, 1), vec![b, a]);
+        assert_eq!(graph.neighborhood(b, 2), vec![b, a, c]);
+
+        let ego = graph.neighborhood_graph(b, 2).unwrap();
+        assert_eq!(ego.node_count(), 3);
+        assert_eq!(ego.edge_count(), 3);
+        assert!(ego.get_edge(ab).is_some());
+        assert!(ego.get_node(d).is_none());
+    }
+
+    #[test]
+    fn test_clones_share_until_written() {
+        let mut graph = ContextGraph::<String, ()>::new("Original");
+        let a = graph.add_node("a".to_string());
+        let b = graph.add_node("b".to_string());
+        graph.add_edge(a, b, ()).unwrap();
+        graph
+            .get_node_mut(a)
+            .unwrap()
+            .add_component(Label("Alpha".to_string()))
+            .unwrap();
+
+        let entry = |g: &ContextGraph<String, ()>, id| g.get_node(id).unwrap() as *const _;
+        let mut what_if = graph.clone();
+        assert_eq!(entry(&graph, b), entry(&what_if, b));
+
+        what_if.get_node_mut(b).unwrap().value = "changed".to_string();
+        what_if.add_node("c".to_string());
+        assert_ne!(entry(&graph, b), entry(&what_if, b));
+        assert_eq!(entry(&graph, a), entry(&what_if, a));
+        assert_eq!(graph.get_node_value(b).unwrap(), "b");
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(what_if.node_count(), 3);
+
+        let label = |g: &ContextGraph<String, ()>| {
+            g.get_node(a).unwrap().get_component::<Label>().unwrap() as *const Label
+        };
+        assert_eq!(label(&graph), label(&what_if));
+
+        what_if.remove_node(a);
+        assert!(graph.get_node(a).is_some());
+        assert_eq!(graph.successors(a), vec![b]);
+
+        // No clone shares the label any more, so removing it reuses the allocation
+        let stored = label(&graph);
+        let removed = graph.get_node_mut(a).unwrap().components.remove::<Label>();
+        let removed = removed.unwrap();
+        assert_eq!(
+            removed.as_any().downcast_ref::<Label>().unwrap() as *const Label,
+            stored
+        );
+    }
 }
@@ -0,0 +1,943 @@
+//! Structural diffs and patches between graph versions
+//!
+//! [`diff`] compares two versions of a graph by [`NodeId`] and [`EdgeId`] and
+//! records every added, removed and modified element, value and component,
+//! plus graph metadata changes. A [`GraphDiff`] is serializable, so it can be
+//! reviewed and stored, and [`ContextGraph::apply_patch`] replays it on
+//! another graph after checking that every change still applies.
+//!
+//! Components are compared through their JSON encoding from a
+//! [`ComponentCodecs`] registry. Components without a codec, such as
+//! [`Subgraph`], are not tracked.
+
+use crate::binary::ComponentCodecs;
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::{self, Debug};
+
+/// A value before and after a change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Change<T> {
+    pub old: T,
+    pub new: T,
+}
+
+impl<T> Change<T> {
+    /// The change that undoes this one
+    pub fn reversed(self) -> Self {
+        Change {
+            old: self.new,
+            new: self.old,
+        }
+    }
+}
+
+/// A component in its JSON encoding, under its registered name
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComponentValue {
+    pub name: String,
+    pub data: Value,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum ComponentChange {
+    Added(ComponentValue),
+    Removed(ComponentValue),
+    Modified {
+        name: String,
+        old: Value,
+        new: Value,
+    },
+}
+
+/// Removed elements keep their old contents so patches can be checked and
+/// inverted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum NodeChange<N> {
+    Added {
+        id: NodeId,
+        value: N,
+        components: Vec<ComponentValue>,
+    },
+    Removed {
+        id: NodeId,
+        value: N,
+        components: Vec<ComponentValue>,
+    },
+    Modified {
+        id: NodeId,
+        value: Option<Change<N>>,
+        components: Vec<ComponentChange>,
+    },
+}
+
+/// Edges whose endpoints changed are recorded as a removal and an addition
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum EdgeChange<E> {
+    Added {
+        id: EdgeId,
+        source: NodeId,
+        target: NodeId,
+        value: E,
+        components: Vec<ComponentValue>,
+    },
+    Removed {
+        id: EdgeId,
+        source: NodeId,
+        target: NodeId,
+        value: E,
+        components: Vec<ComponentValue>,
+    },
+    Modified {
+        id: EdgeId,
+        value: Option<Change<E>>,
+        components: Vec<ComponentChange>,
+    },
+}
+
+/// Everything that changed between two versions of a graph
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphDiff<N, E> {
+    pub metadata: Option<Change<Metadata>>,
+    pub nodes: Vec<NodeChange<N>>,
+    pub edges: Vec<EdgeChange<E>>,
+}
+
+/// What a conflict refers to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ChangeTarget {
+    Metadata,
+    Node(NodeId),
+    Edge(EdgeId),
+}
+
+impl fmt::Display for ChangeTarget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChangeTarget::Metadata => write!(f, "graph metadata"),
+            ChangeTarget::Node(id) => write!(f, "node {id}"),
+            ChangeTarget::Edge(id) => write!(f, "edge {id}"),
+        }
+    }
+}
+
+/// A change that no longer applies to the graph being patched
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Conflict {
+    pub target: ChangeTarget,
+    pub message: String,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.target, self.message)
+    }
+}
+
+impl<N: Clone, E: Clone> GraphDiff<N, E> {
+    pub fn is_empty(&self) -> bool {
+        self.metadata.is_none() && self.nodes.is_empty() && self.edges.is_empty()
+    }
+
+    /// The diff that undoes this one
+    pub fn inverse(&self) -> Self {
+        Self {
+            metadata: self.metadata.clone().map(Change::reversed),
+            nodes: self
+                .nodes
+                .iter()
+                .map(|change| match change.clone() {
+                    NodeChange::Added {
+                        id,
+                        value,
+                        components,
+                    } => NodeChange::Removed {
+                        id,
+                        value,
+                        components,
+                    },
+                    NodeChange::Removed {
+                        id,
+                        value,
+                        components,
+                    } => NodeChange::Added {
+                        id,
+                        value,
+                        components,
+                    },
+                    NodeChange::Modified {
+                        id,
+                        value,
+                        components,
+                    } => NodeChange::Modified {
+                        id,
+                        value: value.map(Change::reversed),
+                        components: invert_components(components),
+                    },
+                })
+                .collect(),
+            edges: self
+                .edges
+                .iter()
+                .map(|change| match change.clone() {
+                    EdgeChange::Added {
+                        id,
+                        source,
+                        target,
+                        value,
+                        components,
+                    } => EdgeChange::Removed {
+                        id,
+                        source,
+                        target,
+                        value,
+                        components,
+                    },
+                    EdgeChange::Removed {
+                        id,
+                        source,
+                        target,
+                        value,
+                        components,
+                    } => EdgeChange::Added {
+                        id,
+                        source,
+                        target,
+                        value,
+                        components,
+                    },
+                    EdgeChange::Modified {
+                        id,
+                        value,
+                        components,
+                    } => EdgeChange::Modified {
+                        id,
+                        value: value.map(Change::reversed),
+                        components: invert_components(components),
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+fn invert_components(changes: Vec<ComponentChange>) -> Vec<ComponentChange> {
+    changes
+        .into_iter()
+        .map(|change| match change {
+            ComponentChange::Added(value) => ComponentChange::Removed(value),
+            ComponentChange::Removed(value) => ComponentChange::Added(value),
+            ComponentChange::Modified { name, old, new } => ComponentChange::Modified {
+                name,
+                old: new,
+                new: old,
+            },
+        })
+        .collect()
+}
+
+/// Diff two versions of a graph, tracking the default component types
+pub fn diff<N, E>(
+    old: &ContextGraph<N, E>,
+    new: &ContextGraph<N, E>,
+) -> GraphResult<GraphDiff<N, E>>
+where
+    N: Clone + Debug + PartialEq + 'static + Send + Sync,
+    E: Clone + Debug + PartialEq + 'static + Send + Sync,
+{
+    diff_with(old, new, &ComponentCodecs::default())
+}
+
+/// Diff two versions of a graph, tracking the component types in `codecs`
+pub fn diff_with<N, E>(
+    old: &ContextGraph<N, E>,
+    new: &ContextGraph<N, E>,
+    codecs: &ComponentCodecs,
+) -> GraphResult<GraphDiff<N, E>>
+where
+    N: Clone + Debug + PartialEq + 'static + Send + Sync,
+    E: Clone + Debug + PartialEq + 'static + Send + Sync,
+{
+    let mut nodes = Vec::new();
+    for (id, before) in old.get_all_nodes() {
+        let before_components = encode(&before.components, codecs)?;
+        match new.get_node(id) {
+            None => nodes.push(NodeChange::Removed {
+                id,
+                value: before.value.clone(),
+                components: listed(before_components),
+            }),
+            Some(after) => {
+                let components =
+                    component_changes(&before_components, &encode(&after.components, codecs)?);
+                let value = changed(&before.value, &after.value);
+                if value.is_some() || !components.is_empty() {
+                    nodes.push(NodeChange::Modified {
+                        id,
+                        value,
+                        components,
+                    });
+                }
+            }
+        }
+    }
+    for (id, after) in new.get_all_nodes() {
+        if old.get_node(id).is_none() {
+            nodes.push(NodeChange::Added {
+                id,
+                value: after.value.clone(),
+                components: listed(encode(&after.components, codecs)?),
+            });
+        }
+    }
+
+    let mut edges = Vec::new();
+    let mut replaced = HashSet::new();
+    for (id, before) in old.get_all_edges() {
+        let before_components = encode(&before.components, codecs)?;
+        match new.get_edge(id) {
+            Some(after) if (after.source, after.target) == (before.source, before.target) => {
+                let components =
+                    component_changes(&before_components, &encode(&after.components, codecs)?);
+                let value = changed(&before.value, &after.value);
+                if value.is_some() || !components.is_empty() {
+                    edges.push(EdgeChange::Modified {
+                        id,
+                        value,
+                        components,
+                    });
+                }
+            }
+            moved => {
+                if moved.is_some() {
+                    replaced.insert(id);
+                }
+                edges.push(EdgeChange::Removed {
+                    id,
+                    source: before.source,
+                    target: before.target,
+                    value: before.value.clone(),
+                    components: listed(before_components),
+                });
+            }
+        }
+    }
+    for (id, after) in new.get_all_edges() {
+        if old.get_edge(id).is_none() || replaced.contains(&id) {
+            edges.push(EdgeChange::Added {
+                id,
+                source: after.source,
+                target: after.target,
+                value: after.value.clone(),
+                components: listed(encode(&after.components, codecs)?),
+            });
+        }
+    }
+
+    Ok(GraphDiff {
+        metadata: changed(&old.metadata, &new.metadata),
+        nodes,
+        edges,
+    })
+}
+
+fn changed<T: Clone + PartialEq>(old: &T, new: &T) -> Option<Change<T>> {
+    (old != new).then(|| Change {
+        old: old.clone(),
+        new: new.clone(),
+    })
+}
+
+pub(crate) type Encoded = BTreeMap<&'static str, Value>;
+
+/// Components with a codec, keyed by registered name
+pub(crate) fn encode(storage: &ComponentStorage, codecs: &ComponentCodecs) -> GraphResult<Encoded> {
+    let mut encoded = BTreeMap::new();
+    for (type_id, component) in storage.iter() {
+        if let Some(result) = codecs.encode_json(type_id, component) {
+            let (name, data) = result?;
+            encoded.insert(name, data);
+        }
+    }
+    Ok(encoded)
+}
+
+fn listed(encoded: Encoded) -> Vec<ComponentValue> {
+    encoded
+        .into_iter()
+        .map(|(name, data)| ComponentValue {
+            name: name.to_string(),
+            data,
+        })
+        .collect()
+}
+
+fn component_changes(old: &Encoded, new: &Encoded) -> Vec<ComponentChange> {
+    let names: std::collections::BTreeSet<&str> = old.keys().chain(new.keys()).copied().collect();
+    names
+        .into_iter()
+        .filter_map(|name| match (old.get(name), new.get(name)) {
+            (Some(before), Some(after)) if before != after => Some(ComponentChange::Modified {
+                name: name.to_string(),
+                old: before.clone(),
+                new: after.clone(),
+            }),
+            (Some(before), None) => Some(ComponentChange::Removed(ComponentValue {
+                name: name.to_string(),
+                data: before.clone(),
+            })),
+            (None, Some(after)) => Some(ComponentChange::Added(ComponentValue {
+                name: name.to_string(),
+                data: after.clone(),
+            })),
+            _ => None,
+        })
+        .collect()
+}
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug + PartialEq + 'static + Send + Sync,
+    E: Clone + Debug + PartialEq + 'static + Send + Sync,
+{
+    /// Changes in `diff` that do not apply to this graph, using the default
+    /// component codecs
+    pub fn check_patch(&self, diff: &GraphDiff<N, E>) -> GraphResult<Vec<Conflict>> {
+        self.check_patch_with(diff, &ComponentCodecs::default())
+    }
+
+    /// Changes in `diff` that do not apply to this graph
+    ///
+    /// A change conflicts when the element it expects is missing or already
+    /// present, or when the old value or component it records differs from
+    /// the graph's current one.
+    pub fn check_patch_with(
+        &self,
+        diff: &GraphDiff<N, E>,
+        codecs: &ComponentCodecs,
+    ) -> GraphResult<Vec<Conflict>> {
+        let mut conflicts = Vec::new();
+        let mut conflict = |target, message: &str| {
+            conflicts.push(Conflict {
+                target,
+                message: message.to_string(),
+            })
+        };
+
+        if let Some(change) = &diff.metadata {
+            if change.old != self.metadata {
+                conflict(ChangeTarget::Metadata, "metadata changed since the diff");
+            }
+        }
+
+        let mut removed_edges = HashSet::new();
+        for change in &diff.edges {
+            if let EdgeChange::Removed {
+                id,
+                source,
+                target,
+                value,
+                components,
+            } = change
+            {
+                let target_id = ChangeTarget::Edge(*id);
+                removed_edges.insert(*id);
+                match self.get_edge(*id) {
+                    None => conflict(target_id, "edge to remove is missing"),
+                    Some(edge) if (edge.source, edge.target) != (*source, *target) => {
+                        conflict(target_id, "edge endpoints changed")
+                    }
+                    Some(edge) if edge.value != *value => conflict(target_id, "edge value changed"),
+                    Some(edge) if encode(&edge.components, codecs)? != keyed(components) => {
+                        conflict(target_id, "edge components changed")
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        let mut removed_nodes = HashSet::new();
+        let mut added_nodes = HashSet::new();
+        for change in &diff.nodes {
+            match change {
+                NodeChange::Removed {
+                    id,
+                    value,
+                    components,
+                } => {
+                    let target = ChangeTarget::Node(*id);
+                    removed_nodes.insert(*id);
+                    match self.get_node(*id) {
+                        None => conflict(target, "node to remove is missing"),
+                        Some(node) if node.value != *value => {
+                            conflict(target, "node value changed")
+                        }
+                        Some(node) if encode(&node.components, codecs)? != keyed(components) => {
+                            conflict(target, "node components changed")
+                        }
+                        Some(_) => {}
+                    }
+                    let kept_edges = self
+                        .incoming_edges(*id)
+                        .chain(self.outgoing_edges(*id))
+                        .any(|(edge_id, _)| !removed_edges.contains(&edge_id));
+                    if kept_edges {
+                        conflict(target, "node has edges the patch does not remove");
+                    }
+                }
+                NodeChange::Added { id, components, .. } => {
+                    added_nodes.insert(*id);
+                    if self.get_node(*id).is_some() {
+                        conflict(ChangeTarget::Node(*id), "node already exists");
+                    }
+                    for component in components {
+                        if codecs.type_id_of(&component.name).is_none() {
+                            conflict(ChangeTarget::Node(*id), "component has no codec");
+                        }
+                    }
+                }
+                NodeChange::Modified {
+                    id,
+                    value,
+                    components,
+                } => {
+                    let target = ChangeTarget::Node(*id);
+                    match self.get_node(*id) {
+                        Some(node) if !removed_nodes.contains(id) => {
+                            if value
+                                .as_ref()
+                                .is_some_and(|change| change.old != node.value)
+                            {
+                                conflict(target, "node value changed");
+                            }
+                            let current = encode(&node.components, codecs)?;
+                            if let Some(message) = component_conflict(&current, components, codecs)
+                            {
+                                conflict(target, message);
+                            }
+                        }
+                        _ => conflict(target, "node to modify is missing"),
+                    }
+                }
+            }
+        }
+
+        let exists = |node_id: &NodeId| {
+            added_nodes.contains(node_id)
+                || (self.get_node(*node_id).is_some() && !removed_nodes.contains(node_id))
+        };
+        for change in &diff.edges {
+            match change {
+                EdgeChange::Added {
+                    id,
+                    source,
+                    target,
+                    components,
+                    ..
+                } => {
+                    let target_id = ChangeTarget::Edge(*id);
+                    if self.get_edge(*id).is_some() && !removed_edges.contains(id) {
+                        conflict(target_id, "edge already exists");
+                    }
+                    if !exists(source) || !exists(target) {
+                        conflict(target_id, "edge endpoint is missing");
+                    }
+                    for component in components {
+                        if codecs.type_id_of(&component.name).is_none() {
+                            conflict(target_id, "component has no codec");
+                        }
+                    }
+                }
+                EdgeChange::Modified {
+                    id,
+                    value,
+                    components,
+                } => {
+                    let target = ChangeTarget::Edge(*id);
+                    match self.get_edge(*id) {
+                        Some(edge) if !removed_edges.contains(id) => {
+                            if value
+                                .as_ref()
+                                .is_some_and(|change| change.old != edge.value)
+                            {
+                                conflict(target, "edge value changed");
+                            }
+                            let current = encode(&edge.components, codecs)?;
+                            if let Some(message) = component_conflict(&current, components, codecs)
+                            {
+                                conflict(target, message);
+                            }
+                        }
+                        _ => conflict(target, "edge to modify is missing"),
+                    }
+                }
+                EdgeChange::Removed { .. } => {}
+            }
+        }
+
+        Ok(conflicts)
+    }
+
+    /// Apply a diff using the default component codecs
+    pub fn apply_patch(&mut self, diff: &GraphDiff<N, E>) -> GraphResult<()> {
+        self.apply_patch_with(diff, &ComponentCodecs::default())
+    }
+
+    /// Apply a diff, or change nothing if any part of it conflicts, fails to
+    /// decode or breaks an invariant
+    ///
+    /// Conflicts are reported as [`GraphError::Conflict`]; use
+    /// [`check_patch_with`](Self::check_patch_with) to inspect them.
+    pub fn apply_patch_with(
+        &mut self,
+        diff: &GraphDiff<N, E>,
+        codecs: &ComponentCodecs,
+    ) -> GraphResult<()> {
+        let conflicts = self.check_patch_with(diff, codecs)?;
+        if !conflicts.is_empty() {
+            let messages: Vec<String> = conflicts.iter().map(Conflict::to_string).collect();
+            return Err(GraphError::Conflict(messages.join("; ")));
+        }
+
+        // Patch a copy-on-write clone and keep it only if everything succeeds
+        let mut patched = self.clone();
+        patched.apply_unchecked(diff, codecs)?;
+        patched.check_invariants()?;
+        *self = patched;
+        Ok(())
+    }
+
+    fn apply_unchecked(
+        &mut self,
+        diff: &GraphDiff<N, E>,
+        codecs: &ComponentCodecs,
+    ) -> GraphResult<()> {
+        if let Some(change) = &diff.metadata {
+            self.metadata = change.new.clone();
+        }
+
+        let removed_edges: HashSet<EdgeId> = diff
+            .edges
+            .iter()
+            .filter_map(|change| match change {
+                EdgeChange::Removed { id, .. } => Some(*id),
+                _ => None,
+            })
+            .collect();
+        if !removed_edges.is_empty() {
+            self.remove_edges(&removed_edges);
+        }
+        let removed_nodes: HashSet<NodeId> = diff
+            .nodes
+            .iter()
+            .filter_map(|change| match change {
+                NodeChange::Removed { id, .. } => Some(*id),
+                _ => None,
+            })
+            .collect();
+        if !removed_nodes.is_empty() {
+            self.remove_nodes(&removed_nodes);
+        }
+
+        for change in &diff.nodes {
+            match change {
+                NodeChange::Added {
+                    id,
+                    value,
+                    components,
+                } => {
+                    self.insert_node_entry(NodeEntry {
+                        id: *id,
+                        value: value.clone(),
+                        components: decode(components, codecs)?,
+                    });
+                }
+                NodeChange::Modified {
+                    id,
+                    value,
+                    components,
+                } => {
+                    let node = self
+                        .get_node_mut(*id)
+                        .ok_or(GraphError::NodeNotFound(*id))?;
+                    if let Some(change) = value {
+                        node.value = change.new.clone();
+                    }
+                    apply_components(&mut node.components, components, codecs)?;
+                }
+                NodeChange::Removed { .. } => {}
+            }
+        }
+
+        for change in &diff.edges {
+            match change {
+                EdgeChange::Added {
+                    id,
+                    source,
+                    target,
+                    value,
+                    components,
+                } => {
+                    self.insert_edge_entry(EdgeEntry {
+                        id: *id,
+                        source: *source,
+                        target: *target,
+                        value: value.clone(),
+                        components: decode(components, codecs)?,
+                    })?;
+                }
+                EdgeChange::Modified {
+                    id,
+                    value,
+                    components,
+                } => {
+                    let edge = self
+                        .get_edge_mut(*id)
+                        .ok_or(GraphError::EdgeNotFound(*id))?;
+                    if let Some(change) = value {
+                        edge.value = change.new.clone();
+                    }
+                    apply_components(&mut edge.components, components, codecs)?;
+                }
+                EdgeChange::Removed { .. } => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn keyed(components: &[ComponentValue]) -> BTreeMap<&str, Value> {
+    components
+        .iter()
+        .map(|component| (component.name.as_str(), component.data.clone()))
+        .collect()
+}
+
+/// Why component changes do not apply to the current components, if they don't
+fn component_conflict(
+    current: &Encoded,
+    changes: &[ComponentChange],
+    codecs: &ComponentCodecs,
+) -> Option<&'static str> {
+    changes.iter().find_map(|change| {
+        let (name, expected) = match change {
+            ComponentChange::Added(added) => (&added.name, None),
+            ComponentChange::Removed(removed) => (&removed.name, Some(&removed.data)),
+            ComponentChange::Modified { name, old, .. } => (name, Some(old)),
+        };
+        if codecs.type_id_of(name).is_none() {
+            Some("component has no codec")
+        } else if current.get(name.as_str()) != expected {
+            Some("components changed since the diff")
+        } else {
+            None
+        }
+    })
+}
+
+fn decode(
+    components: &[ComponentValue],
+    codecs: &ComponentCodecs,
+) -> GraphResult<ComponentStorage> {
+    let mut storage = ComponentStorage::new();
+    for component in components {
+        storage.add_boxed(codecs.decode_json(&component.name, &component.data)?)?;
+    }
+    Ok(storage)
+}
+
+fn apply_components(
+    storage: &mut ComponentStorage,
+    changes: &[ComponentChange],
+    codecs: &ComponentCodecs,
+) -> GraphResult<()> {
+    for change in changes {
+        let (name, data) = match change {
+            ComponentChange::Added(added) => (&added.name, Some(&added.data)),
+            ComponentChange::Removed(removed) => (&removed.name, None),
+            ComponentChange::Modified { name, new, .. } => (name, Some(new)),
+        };
+        let type_id = codecs
+            .type_id_of(name)
+            .ok_or_else(|| GraphError::ComponentNotFound(format!("no codec for {name}")))?;
+        storage.remove_type(&type_id);
+        if let Some(data) = data {
+            storage.add_boxed(codecs.decode_json(name, data)?)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context_graph::GraphInvariant;
+
+    fn versions() -> (ContextGraph<String, u32>, ContextGraph<String, u32>) {
+        let mut old = ContextGraph::<String, u32>::new("Versions");
+        let a = old.add_node("a".to_string());
+        let b = old.add_node("b".to_string());
+        let c = old.add_node("c".to_string());
+        old.add_edge(a, b, 1).unwrap();
+        old.add_edge(b, c, 2).unwrap();
+        old.get_node_mut(a)
+            .unwrap()
+            .add_component(Label("Alpha".to_string()))
+            .unwrap();
+
+        let mut new = old.clone();
+        new.remove_node(c);
+        new.get_node_mut(b).unwrap().value = "beta".to_string();
+        let node = new.get_node_mut(a).unwrap();
+        node.components.take::<Label>();
+        node.add_component(Label("Alpha v2".to_string())).unwrap();
+        let d = new.add_node("d".to_string());
+        new.add_edge(a, d, 3).unwrap();
+        new.metadata
+            .properties
+            .insert("reviewed".to_string(), serde_json::json!(true));
+        (old, new)
+    }
+
+    #[test]
+    fn test_diff_records_changes() {
+        let (old, new) = versions();
+        let changes = diff(&old, &new).unwrap();
+
+        assert!(changes.metadata.is_some());
+        let kinds: Vec<&str> = changes
+            .nodes
+            .iter()
+            .map(|change| match change {
+                NodeChange::Added { .. } => "added",
+                NodeChange::Removed { .. } => "removed",
+                NodeChange::Modified { .. } => "modified",
+            })
+            .collect();
+        assert_eq!(kinds, vec!["modified", "modified", "removed", "added"]);
+        let NodeChange::Modified { components, .. } = &changes.nodes[0] else {
+            panic!("expected a modification");
+        };
+        assert_eq!(
+            components,
+            &vec![ComponentChange::Modified {
+                name: "Label".to_string(),
+                old: serde_json::json!("Alpha"),
+                new: serde_json::json!("Alpha v2"),
+            }]
+        );
+        assert_eq!(changes.edges.len(), 2);
+        assert!(diff(&old, &old).unwrap().is_empty());
+
+        let json = serde_json::to_string(&changes).unwrap();
+        let restored: GraphDiff<String, u32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored, changes);
+    }
+
+    #[test]
+    fn test_patch_round_trip_and_inverse() {
+        let (old, new) = versions();
+        let changes = diff(&old, &new).unwrap();
+
+        let mut patched = old.clone();
+        patched.apply_patch(&changes).unwrap();
+        assert!(diff(&patched, &new).unwrap().is_empty());
+
+        patched.apply_patch(&changes.inverse()).unwrap();
+        assert!(diff(&patched, &old).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_patch_conflicts_leave_graph_unchanged() {
+        let (old, new) = versions();
+        let changes = diff(&old, &new).unwrap();
+
+        let mut diverged = old.clone();
+        let (b, _) = diverged
+            .query_nodes()
+            .where_value(|v| v == "b")
+            .first()
+            .unwrap();
+        diverged.get_node_mut(b).unwrap().value = "bee".to_string();
+        let (c, _) = diverged
+            .query_nodes()
+            .where_value(|v| v == "c")
+            .first()
+            .unwrap();
+        let e = diverged.add_node("e".to_string());
+        diverged.add_edge(e, c, 9).unwrap();
+
+        let conflicts = diverged.check_patch(&changes).unwrap();
+        let targets: Vec<ChangeTarget> = conflicts.iter().map(|c| c.target).collect();
+        assert_eq!(targets, vec![ChangeTarget::Node(b), ChangeTarget::Node(c)]);
+
+        let before = diverged.clone();
+        assert!(matches!(
+            diverged.apply_patch(&changes),
+            Err(GraphError::Conflict(_))
+        ));
+        assert!(diff(&before, &diverged).unwrap().is_empty());
+    }
+
+    #[derive(Clone)]
+    struct Acyclic;
+
+    impl<N: Clone + Debug, E: Clone + Debug> GraphInvariant<N, E> for Acyclic {
+        fn check(&self, graph: &ContextGraph<N, E>) -> GraphResult<()> {
+            if graph.is_cyclic() {
+                Err(GraphError::CycleDetected)
+            } else {
+                Ok(())
+            }
+        }
+
+        fn name(&self) -> &str {
+            "Acyclic"
+        }
+
+        fn clone_box(&self) -> Box<dyn GraphInvariant<N, E>> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_failed_patches_leave_graph_unchanged() {
+        let (old, new) = versions();
+        let mut changes = diff(&old, &new).unwrap();
+        let NodeChange::Modified { components, .. } = &mut changes.nodes[0] else {
+            panic!("expected a modification");
+        };
+        components[0] = ComponentChange::Modified {
+            name: "Label".to_string(),
+            old: serde_json::json!("Alpha"),
+            new: serde_json::json!(42),
+        };
+
+        // Removals come first, but the bad component must still undo them
+        let mut patched = old.clone();
+        assert!(patched.check_patch(&changes).unwrap().is_empty());
+        assert!(patched.apply_patch(&changes).is_err());
+        assert!(diff(&old, &patched).unwrap().is_empty());
+
+        let mut cyclic = old.clone();
+        let ids: Vec<NodeId> = cyclic.get_all_nodes().map(|(id, _)| id).collect();
+        cyclic.add_edge(ids[2], ids[0], 3).unwrap();
+        let closes_cycle = diff(&old, &cyclic).unwrap();
+
+        let mut guarded = old.clone();
+        guarded.invariants.push(Box::new(Acyclic));
+        assert!(matches!(
+            guarded.apply_patch(&closes_cycle),
+            Err(GraphError::CycleDetected)
+        ));
+        assert_eq!(guarded.edge_count(), 2);
+        assert!(guarded.add_edge(ids[2], ids[0], 3).is_err());
+        assert_eq!(guarded.edge_count(), 2);
+    }
+}
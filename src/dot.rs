@@ -0,0 +1,284 @@
+//! Graphviz DOT export
+//!
+//! Unlike petgraph's `Dot`, the output is driven by components: node names
+//! are the stable [`NodeId`]s, captions come from [`Label`], attributes from
+//! [`Metadata`] properties, and [`Subgraph`] components become clusters. A
+//! [`DotStyle`] adds or overrides attributes per node and edge.
+
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use std::fmt::{Debug, Display, Write};
+
+/// Attribute name/value pairs, written in order
+pub type DotAttributes = Vec<(String, String)>;
+
+/// Styling hook for DOT export
+///
+/// Attributes returned here are applied after the defaults from `Label` and
+/// `Metadata`, replacing any with the same name.
+pub trait DotStyle<N, E> {
+    /// Caption for a node without a `Label` component
+    fn node_label(&self, _node: &NodeEntry<N>) -> Option<String> {
+        None
+    }
+
+    /// Caption for an edge without a `Label` component
+    fn edge_label(&self, _edge: &EdgeEntry<E>) -> Option<String> {
+        None
+    }
+
+    fn node_attributes(&self, _node: &NodeEntry<N>) -> DotAttributes {
+        Vec::new()
+    }
+
+    fn edge_attributes(&self, _edge: &EdgeEntry<E>) -> DotAttributes {
+        Vec::new()
+    }
+}
+
+/// Style without extra attributes or fallback captions
+impl<N, E> DotStyle<N, E> for () {}
+
+/// Style captioning unlabelled entries with their `Display` output
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DisplayLabels;
+
+impl<N: Display, E: Display> DotStyle<N, E> for DisplayLabels {
+    fn node_label(&self, node: &NodeEntry<N>) -> Option<String> {
+        Some(node.value.to_string())
+    }
+
+    fn edge_label(&self, edge: &EdgeEntry<E>) -> Option<String> {
+        Some(edge.value.to_string()).filter(|label| !label.is_empty())
+    }
+}
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    /// Render as a DOT digraph, captioning unlabelled entries by value
+    pub fn to_dot(&self) -> String
+    where
+        N: Display,
+        E: Display,
+    {
+        self.to_dot_with(&DisplayLabels)
+    }
+
+    /// Render as a DOT digraph using a custom style
+    pub fn to_dot_with(&self, style: &impl DotStyle<N, E>) -> String {
+        let mut out = format!("digraph {} {{\n", quote(&self.id.to_string()));
+        out.push_str("    compound=true;\n");
+        if let Some(name) = self
+            .metadata
+            .properties
+            .get("name")
+            .and_then(|v| v.as_str())
+        {
+            let _ = writeln!(out, "    label={};", quote(name));
+        }
+        write_graph(&mut out, self, style, 1);
+        out.push_str("}\n");
+        out
+    }
+}
+
+fn write_graph<N, E>(
+    out: &mut String,
+    graph: &ContextGraph<N, E>,
+    style: &impl DotStyle<N, E>,
+    depth: usize,
+) where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    let pad = "    ".repeat(depth);
+
+    for (node_id, node) in graph.get_all_nodes() {
+        let label = node
+            .get_component::<Label>()
+            .map(|label| label.0.clone())
+            .or_else(|| style.node_label(node));
+        let mut attributes = defaults(label, node.get_component::<Metadata>());
+        merge(&mut attributes, style.node_attributes(node));
+
+        if let Some(subgraph) = node.get_component::<Subgraph<N, E>>() {
+            // The container is drawn inside its cluster so edges can reach it
+            let _ = writeln!(out, "{pad}subgraph {} {{", quote(&cluster(node_id)));
+            if let Some((_, label)) = attributes.iter().find(|(name, _)| name == "label") {
+                let _ = writeln!(out, "{pad}    label={};", quote(label));
+            }
+            let _ = writeln!(
+                out,
+                "{pad}    {}{};",
+                quote(&node_id.to_string()),
+                render(&attributes)
+            );
+            write_graph(out, &subgraph.graph, style, depth + 1);
+            let _ = writeln!(out, "{pad}}}");
+        } else {
+            let _ = writeln!(
+                out,
+                "{pad}{}{};",
+                quote(&node_id.to_string()),
+                render(&attributes)
+            );
+        }
+    }
+
+    for (edge_id, edge) in graph.get_all_edges() {
+        let label = edge
+            .get_component::<Label>()
+            .map(|label| label.0.clone())
+            .or_else(|| style.edge_label(edge));
+        let mut attributes = vec![("id".to_string(), edge_id.to_string())];
+        merge(
+            &mut attributes,
+            defaults(label, edge.get_component::<Metadata>()),
+        );
+        for (endpoint, attribute) in [(edge.source, "ltail"), (edge.target, "lhead")] {
+            if graph
+                .get_node(endpoint)
+                .is_some_and(|node| node.has_component::<Subgraph<N, E>>())
+            {
+                attributes.push((attribute.to_string(), cluster(endpoint)));
+            }
+        }
+        merge(&mut attributes, style.edge_attributes(edge));
+
+        let _ = writeln!(
+            out,
+            "{pad}{} -> {}{};",
+            quote(&edge.source.to_string()),
+            quote(&edge.target.to_string()),
+            render(&attributes)
+        );
+    }
+}
+
+/// Attributes from a caption and `Metadata` properties
+fn defaults(label: Option<String>, metadata: Option<&Metadata>) -> DotAttributes {
+    let mut attributes: DotAttributes = label
+        .map(|label| ("label".to_string(), label))
+        .into_iter()
+        .collect();
+    if let Some(metadata) = metadata {
+        let properties = metadata.properties.iter().map(|(name, value)| {
+            let value = match value {
+                serde_json::Value::String(text) => text.clone(),
+                other => other.to_string(),
+            };
+            (name.clone(), value)
+        });
+        merge(&mut attributes, properties.collect());
+    }
+    attributes
+}
+
+/// Apply `overrides`, replacing attributes with the same name in place
+fn merge(attributes: &mut DotAttributes, overrides: DotAttributes) {
+    for (name, value) in overrides {
+        match attributes
+            .iter_mut()
+            .find(|(existing, _)| *existing == name)
+        {
+            Some(slot) => slot.1 = value,
+            None => attributes.push((name, value)),
+        }
+    }
+}
+
+fn render(attributes: &DotAttributes) -> String {
+    if attributes.is_empty() {
+        return String::new();
+    }
+    let list: Vec<String> = attributes
+        .iter()
+        .map(|(name, value)| format!("{}={}", quote(name), quote(value)))
+        .collect();
+    format!(" [{}]", list.join(", "))
+}
+
+fn cluster(node_id: NodeId) -> String {
+    format!("cluster_{node_id}")
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dot_uses_components() {
+        let mut graph = ContextGraph::<String, String>::new("Org");
+        let ceo = graph.add_node("ceo".to_string());
+        let cto = graph.add_node("cto".to_string());
+        let node = graph.get_node_mut(ceo).unwrap();
+        node.add_component(Label("Chief \"Exec\"".to_string()))
+            .unwrap();
+        let mut metadata = Metadata::default();
+        metadata
+            .properties
+            .insert("shape".to_string(), serde_json::json!("box"));
+        metadata
+            .properties
+            .insert("penwidth".to_string(), serde_json::json!(2));
+        node.add_component(metadata).unwrap();
+        let edge = graph.add_edge(ceo, cto, "manages".to_string()).unwrap();
+
+        let dot = graph.to_dot();
+        assert!(dot.contains("label=\"Org\";"));
+        assert!(dot.contains(&format!(
+            "\"{ceo}\" [\"label\"=\"Chief \\\"Exec\\\"\", \"penwidth\"=\"2\", \"shape\"=\"box\"];"
+        )));
+        assert!(dot.contains(&format!("\"{cto}\" [\"label\"=\"cto\"];")));
+        assert!(dot.contains(&format!(
+            "\"{ceo}\" -> \"{cto}\" [\"id\"=\"{edge}\", \"label\"=\"manages\"];"
+        )));
+        assert_eq!(dot, graph.to_dot());
+    }
+
+    #[test]
+    fn test_dot_clusters_and_styling_hook() {
+        struct Approvals;
+        impl DotStyle<&'static str, bool> for Approvals {
+            fn node_label(&self, node: &NodeEntry<&'static str>) -> Option<String> {
+                Some(node.value.to_uppercase())
+            }
+            fn edge_attributes(&self, edge: &EdgeEntry<bool>) -> DotAttributes {
+                let color = if edge.value { "green" } else { "red" };
+                vec![("color".to_string(), color.to_string())]
+            }
+        }
+
+        let mut inner = ContextGraph::<&str, bool>::new("Review");
+        inner.add_node("legal");
+        let mut graph = ContextGraph::new("Approval");
+        let submit = graph.add_node("submit");
+        let review = graph.add_node("review");
+        graph
+            .get_node_mut(review)
+            .unwrap()
+            .add_component(Subgraph {
+                graph: Box::new(inner),
+            })
+            .unwrap();
+        graph.add_edge(submit, review, false).unwrap();
+
+        let dot = graph.to_dot_with(&Approvals);
+        assert!(dot.contains(&format!("subgraph \"cluster_{review}\" {{")));
+        assert!(dot.contains("label=\"REVIEW\";"));
+        assert!(dot.contains("[\"label\"=\"LEGAL\"]"));
+        assert!(dot.contains(&format!(
+            "\"lhead\"=\"cluster_{review}\", \"color\"=\"red\""
+        )));
+
+        let plain = graph.to_dot_with(&());
+        assert!(!plain.contains("SUBMIT"));
+    }
+}
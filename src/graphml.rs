@@ -0,0 +1,546 @@
+//! GraphML import and export
+//!
+//! Node and edge ids are the [`NodeId`]/[`EdgeId`] strings, so a round trip
+//! keeps them. The `label` attribute carries the [`Label`] component, `value`
+//! the JSON-encoded node or edge value, `description` and `tags` the
+//! matching [`Metadata`] fields, and every other attribute one
+//! `Metadata.properties` entry. [`Subgraph`] components become nested
+//! `<graph>` elements.
+
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{Debug, Write};
+
+const RESERVED: [&str; 4] = ["label", "value", "description", "tags"];
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    /// Export as a GraphML document
+    pub fn to_graphml(&self) -> GraphResult<String>
+    where
+        N: Serialize,
+        E: Serialize,
+    {
+        let mut properties = BTreeMap::new();
+        collect_property_types(self, &mut properties);
+
+        let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        out.push_str("<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n");
+        for name in RESERVED {
+            let _ = writeln!(
+                out,
+                "  <key id=\"{name}\" for=\"all\" attr.name=\"{name}\" attr.type=\"string\"/>"
+            );
+        }
+        let mut keys = HashMap::new();
+        for (index, (name, kind)) in properties.iter().enumerate() {
+            let id = format!("p{index}");
+            let _ = writeln!(
+                out,
+                "  <key id=\"{id}\" for=\"all\" attr.name=\"{}\" attr.type=\"{kind}\"/>",
+                escape(name)
+            );
+            keys.insert(name.clone(), id);
+        }
+        write_graph(&mut out, self, &keys, 1)?;
+        out.push_str("</graphml>\n");
+        Ok(out)
+    }
+
+    /// Import a GraphML document
+    ///
+    /// Ids that are not `NodeId`/`EdgeId` strings get fresh ids. Values are
+    /// read from the `value` attribute as JSON, or as a plain string when
+    /// that fails; without one, nodes fall back to `null` and then their
+    /// GraphML id, edges to `null` and then the empty string. Only the
+    /// first top-level `<graph>` is read.
+    pub fn from_graphml(text: &str) -> GraphResult<Self>
+    where
+        N: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        let document = roxmltree::Document::parse(text).map_err(|err| {
+            let pos = err.pos();
+            parse_error(pos.row as usize, pos.col as usize, err.to_string())
+        })?;
+        let reader = Reader::new(&document)?;
+        let root = document.root_element();
+        let graph = root
+            .children()
+            .find(|child| child.has_tag_name("graph"))
+            .ok_or_else(|| reader.error(root, "no <graph> element".to_string()))?;
+        reader.graph(graph)
+    }
+}
+
+fn parse_error(line: usize, column: usize, message: String) -> GraphError {
+    GraphError::Parse {
+        format: "GraphML".to_string(),
+        line,
+        column,
+        message,
+    }
+}
+
+/// GraphML type per property name; conflicting types fall back to string
+fn collect_property_types<N, E>(
+    graph: &ContextGraph<N, E>,
+    types: &mut BTreeMap<String, &'static str>,
+) where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    let metadata = graph
+        .get_all_nodes()
+        .filter_map(|(_, node)| node.get_component::<Metadata>())
+        .chain(
+            graph
+                .get_all_edges()
+                .filter_map(|(_, edge)| edge.get_component::<Metadata>()),
+        );
+    for metadata in metadata {
+        for (name, value) in &metadata.properties {
+            if RESERVED.contains(&name.as_str()) {
+                continue;
+            }
+            let kind = match value {
+                Value::Bool(_) => "boolean",
+                Value::Number(number) if number.is_f64() => "double",
+                Value::Number(_) => "long",
+                _ => "string",
+            };
+            types
+                .entry(name.clone())
+                .and_modify(|existing| {
+                    if *existing != kind {
+                        *existing = "string";
+                    }
+                })
+                .or_insert(kind);
+        }
+    }
+    for (_, node) in graph.get_all_nodes() {
+        if let Some(subgraph) = node.get_component::<Subgraph<N, E>>() {
+            collect_property_types(&subgraph.graph, types);
+        }
+    }
+}
+
+fn write_graph<N, E>(
+    out: &mut String,
+    graph: &ContextGraph<N, E>,
+    keys: &HashMap<String, String>,
+    depth: usize,
+) -> GraphResult<()>
+where
+    N: Clone + Debug + Serialize + 'static + Send + Sync,
+    E: Clone + Debug + Serialize + 'static + Send + Sync,
+{
+    let pad = "  ".repeat(depth);
+    let _ = writeln!(
+        out,
+        "{pad}<graph id=\"{}\" edgedefault=\"directed\">",
+        graph.id
+    );
+    if let Some(name) = graph
+        .metadata
+        .properties
+        .get("name")
+        .and_then(|v| v.as_str())
+    {
+        let _ = writeln!(out, "{pad}  <data key=\"label\">{}</data>", escape(name));
+    }
+
+    for (node_id, node) in graph.get_all_nodes() {
+        let _ = writeln!(out, "{pad}  <node id=\"{node_id}\">");
+        write_data(out, &pad, &node.value, &node.components, keys)?;
+        if let Some(subgraph) = node.get_component::<Subgraph<N, E>>() {
+            write_graph(out, &subgraph.graph, keys, depth + 2)?;
+        }
+        let _ = writeln!(out, "{pad}  </node>");
+    }
+
+    for (edge_id, edge) in graph.get_all_edges() {
+        let _ = writeln!(
+            out,
+            "{pad}  <edge id=\"{edge_id}\" source=\"{}\" target=\"{}\">",
+            edge.source, edge.target
+        );
+        write_data(out, &pad, &edge.value, &edge.components, keys)?;
+        let _ = writeln!(out, "{pad}  </edge>");
+    }
+
+    let _ = writeln!(out, "{pad}</graph>");
+    Ok(())
+}
+
+fn write_data<T: Serialize>(
+    out: &mut String,
+    pad: &str,
+    value: &T,
+    components: &ComponentStorage,
+    keys: &HashMap<String, String>,
+) -> GraphResult<()> {
+    let mut data = |key: &str, text: &str| {
+        let _ = writeln!(out, "{pad}    <data key=\"{key}\">{}</data>", escape(text));
+    };
+
+    if let Some(label) = components.get::<Label>() {
+        data("label", &label.0);
+    }
+    let value = serde_json::to_string(value)
+        .map_err(|err| GraphError::InvalidOperation(format!("value not serializable: {err}")))?;
+    data("value", &value);
+
+    if let Some(metadata) = components.get::<Metadata>() {
+        if let Some(description) = &metadata.description {
+            data("description", description);
+        }
+        if !metadata.tags.is_empty() {
+            data("tags", &Value::from(metadata.tags.clone()).to_string());
+        }
+        for (name, value) in &metadata.properties {
+            if let Some(key) = keys.get(name) {
+                match value {
+                    Value::String(text) => data(key, text),
+                    other => data(key, &other.to_string()),
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+struct Key {
+    name: String,
+    kind: String,
+    default: Option<String>,
+    scope: String,
+}
+
+struct Reader<'a, 'input> {
+    document: &'a roxmltree::Document<'input>,
+    keys: HashMap<String, Key>,
+}
+
+impl<'a, 'input> Reader<'a, 'input> {
+    fn new(document: &'a roxmltree::Document<'input>) -> GraphResult<Self> {
+        let mut reader = Self {
+            document,
+            keys: HashMap::new(),
+        };
+        let root = document.root_element();
+        if !root.has_tag_name("graphml") {
+            return Err(reader.error(root, "root element is not <graphml>".to_string()));
+        }
+        for key in root.children().filter(|child| child.has_tag_name("key")) {
+            let id = reader.attribute(key, "id")?;
+            let default = key
+                .children()
+                .find(|child| child.has_tag_name("default"))
+                .map(|default| default.text().unwrap_or("").to_string());
+            reader.keys.insert(
+                id.to_string(),
+                Key {
+                    name: key.attribute("attr.name").unwrap_or(id).to_string(),
+                    kind: key.attribute("attr.type").unwrap_or("string").to_string(),
+                    default,
+                    scope: key.attribute("for").unwrap_or("all").to_string(),
+                },
+            );
+        }
+        Ok(reader)
+    }
+
+    fn error(&self, node: roxmltree::Node, message: String) -> GraphError {
+        let pos = self.document.text_pos_at(node.range().start);
+        parse_error(pos.row as usize, pos.col as usize, message)
+    }
+
+    fn attribute<'n>(&self, node: roxmltree::Node<'n, 'input>, name: &str) -> GraphResult<&'n str> {
+        node.attribute(name).ok_or_else(|| {
+            let element = node.tag_name().name();
+            self.error(node, format!("<{element}> is missing `{name}`"))
+        })
+    }
+
+    /// Attribute values of an element by name, including key defaults
+    fn data(&self, element: roxmltree::Node) -> GraphResult<BTreeMap<String, Value>> {
+        let scope = element.tag_name().name();
+        let mut values = BTreeMap::new();
+        for key in self.keys.values() {
+            if let Some(default) = &key.default {
+                if key.scope == scope || key.scope == "all" {
+                    values.insert(key.name.clone(), self.typed(element, key, default)?);
+                }
+            }
+        }
+        for data in element
+            .children()
+            .filter(|child| child.has_tag_name("data"))
+        {
+            let id = self.attribute(data, "key")?;
+            let key = self
+                .keys
+                .get(id)
+                .ok_or_else(|| self.error(data, format!("undeclared key `{id}`")))?;
+            let text = data.text().unwrap_or("");
+            values.insert(key.name.clone(), self.typed(data, key, text)?);
+        }
+        Ok(values)
+    }
+
+    fn typed(&self, node: roxmltree::Node, key: &Key, text: &str) -> GraphResult<Value> {
+        let text = text.trim();
+        let value = match key.kind.as_str() {
+            "boolean" => text.parse::<bool>().ok().map(Value::Bool),
+            "int" | "long" => text.parse::<i64>().ok().map(Value::from),
+            "float" | "double" => text
+                .parse::<f64>()
+                .ok()
+                .and_then(serde_json::Number::from_f64)
+                .map(Value::Number),
+            _ => Some(Value::String(text.to_string())),
+        };
+        value.ok_or_else(|| {
+            self.error(
+                node,
+                format!("`{text}` is not a valid {} for `{}`", key.kind, key.name),
+            )
+        })
+    }
+
+    fn graph<N, E>(&self, element: roxmltree::Node) -> GraphResult<ContextGraph<N, E>>
+    where
+        N: Clone + Debug + DeserializeOwned + 'static + Send + Sync,
+        E: Clone + Debug + DeserializeOwned + 'static + Send + Sync,
+    {
+        let data = self.data(element)?;
+        let name = match data.get("label") {
+            Some(Value::String(label)) => label.clone(),
+            _ => element.attribute("id").unwrap_or("GraphML").to_string(),
+        };
+        let mut graph = ContextGraph::new(name);
+        if let Some(id) = element.attribute("id").and_then(|id| id.parse().ok()) {
+            graph.id = id;
+        }
+
+        let mut ids = HashMap::new();
+        for node in element
+            .children()
+            .filter(|child| child.has_tag_name("node"))
+        {
+            let name = self.attribute(node, "id")?;
+            let node_id = name.parse::<NodeId>().unwrap_or_default();
+            if ids.insert(name, node_id).is_some() || graph.get_node(node_id).is_some() {
+                return Err(self.error(node, format!("duplicate node id `{name}`")));
+            }
+
+            let mut data = self.data(node)?;
+            let value = self.value(node, data.remove("value"), name)?;
+            let mut entry = NodeEntry {
+                id: node_id,
+                value,
+                components: self.components(data),
+            };
+            if let Some(inner) = node.children().find(|child| child.has_tag_name("graph")) {
+                entry.add_component(Subgraph {
+                    graph: Box::new(self.graph::<N, E>(inner)?),
+                })?;
+            }
+            graph.insert_node_entry(entry);
+        }
+
+        for edge in element
+            .children()
+            .filter(|child| child.has_tag_name("edge"))
+        {
+            let endpoint = |attribute| -> GraphResult<NodeId> {
+                let name = self.attribute(edge, attribute)?;
+                ids.get(name).copied().ok_or_else(|| {
+                    self.error(
+                        edge,
+                        format!("{attribute} `{name}` is not a node of this graph"),
+                    )
+                })
+            };
+            let (source, target) = (endpoint("source")?, endpoint("target")?);
+            let edge_id = edge
+                .attribute("id")
+                .and_then(|id| id.parse().ok())
+                .unwrap_or_default();
+            if graph.get_edge(edge_id).is_some() {
+                return Err(self.error(edge, format!("duplicate edge id `{edge_id}`")));
+            }
+
+            let mut data = self.data(edge)?;
+            let value = self.value(edge, data.remove("value"), "")?;
+            graph.insert_edge_entry(EdgeEntry {
+                id: edge_id,
+                source,
+                target,
+                value,
+                components: self.components(data),
+            })?;
+        }
+        Ok(graph)
+    }
+
+    fn value<T: DeserializeOwned>(
+        &self,
+        element: roxmltree::Node,
+        value: Option<Value>,
+        fallback: &str,
+    ) -> GraphResult<T> {
+        let first = match value {
+            Some(Value::String(text)) => {
+                serde_json::from_str(&text).or_else(|_| serde_json::from_value(Value::String(text)))
+            }
+            Some(other) => serde_json::from_value(other),
+            None => serde_json::from_value(Value::Null)
+                .or_else(|_| serde_json::from_value(Value::String(fallback.to_string()))),
+        };
+        first.map_err(|err| self.error(element, format!("invalid value: {err}")))
+    }
+
+    /// `Label` and `Metadata` components from the remaining attributes
+    fn components(&self, mut data: BTreeMap<String, Value>) -> ComponentStorage {
+        let mut components = ComponentStorage::new();
+        if let Some(Value::String(label)) = data.remove("label") {
+            let _ = components.add(Label(label));
+        }
+
+        let mut metadata = Metadata::default();
+        if let Some(Value::String(description)) = data.remove("description") {
+            metadata.description = Some(description);
+        }
+        if let Some(Value::String(tags)) = data.remove("tags") {
+            metadata.tags = serde_json::from_str(&tags).unwrap_or_else(|_| {
+                tags.split(',')
+                    .map(|tag| tag.trim().to_string())
+                    .filter(|tag| !tag.is_empty())
+                    .collect()
+            });
+        }
+        metadata.properties.extend(data);
+        if metadata != Metadata::default() {
+            let _ = components.add(metadata);
+        }
+        components
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graphml_round_trip_preserves_ids_and_components() {
+        let mut inner = ContextGraph::<String, u32>::new("Team");
+        inner.add_node("dev".to_string());
+
+        let mut graph = ContextGraph::<String, u32>::new("Org <chart>");
+        let lead = graph.add_node("lead".to_string());
+        let team = graph.add_node("team".to_string());
+        let node = graph.get_node_mut(lead).unwrap();
+        node.add_component(Label("Lead & \"boss\"".to_string()))
+            .unwrap();
+        let mut metadata = Metadata {
+            description: Some("Runs things".to_string()),
+            tags: vec!["exec".to_string()],
+            ..Default::default()
+        };
+        metadata
+            .properties
+            .insert("headcount".to_string(), serde_json::json!(12));
+        metadata
+            .properties
+            .insert("remote".to_string(), serde_json::json!(true));
+        node.add_component(metadata.clone()).unwrap();
+        graph
+            .get_node_mut(team)
+            .unwrap()
+            .add_component(Subgraph {
+                graph: Box::new(inner),
+            })
+            .unwrap();
+        let edge = graph.add_edge(lead, team, 3).unwrap();
+
+        let xml = graph.to_graphml().unwrap();
+        assert!(xml.contains("attr.name=\"headcount\" attr.type=\"long\""));
+        let parsed = ContextGraph::<String, u32>::from_graphml(&xml).unwrap();
+
+        assert_eq!(parsed.id, graph.id);
+        assert_eq!(parsed.total_node_count(), 3);
+        let lead_node = parsed.get_node(lead).unwrap();
+        assert_eq!(lead_node.value, "lead");
+        assert_eq!(
+            lead_node.get_component::<Label>().unwrap().0,
+            "Lead & \"boss\""
+        );
+        assert_eq!(lead_node.get_component::<Metadata>(), Some(&metadata));
+        assert_eq!(parsed.get_edge(edge).unwrap().value, 3);
+        assert!(parsed
+            .get_node(team)
+            .unwrap()
+            .has_component::<Subgraph<String, u32>>());
+    }
+
+    #[test]
+    fn test_graphml_partner_file_and_errors() {
+        let xml = r#"<?xml version="1.0"?>
+<graphml xmlns="http://graphml.graphdrawing.org/xmlns">
+  <key id="d0" for="node" attr.name="weight" attr.type="double">
+    <default>1.5</default>
+  </key>
+  <graph edgedefault="directed">
+    <node id="a"/>
+    <node id="b"><data key="d0">2.5</data></node>
+    <edge source="a" target="b"/>
+  </graph>
+</graphml>"#;
+        let graph = ContextGraph::<String, String>::from_graphml(xml).unwrap();
+        let values: Vec<&str> = graph
+            .get_all_nodes()
+            .map(|(_, n)| n.value.as_str())
+            .collect();
+        assert_eq!(values, ["a", "b"]);
+        let (_, b) = graph.get_all_nodes().nth(1).unwrap();
+        assert_eq!(
+            b.get_component::<Metadata>().unwrap().properties["weight"],
+            2.5
+        );
+        assert_eq!(graph.get_all_edges().next().unwrap().1.value, "");
+
+        let broken = xml.replace("target=\"b\"", "target=\"c\"");
+        let err = ContextGraph::<String, String>::from_graphml(&broken).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                GraphError::Parse {
+                    line: 9,
+                    column: 5,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+
+        let err = ContextGraph::<String, String>::from_graphml("<graphml><graph>").unwrap_err();
+        assert!(matches!(err, GraphError::Parse { line: 1, .. }), "{err}");
+    }
+}
@@ -0,0 +1,704 @@
+//! Hierarchical walking over nested Subgraph components
+//!
+//! A [`ContextGraph`] can contain whole graphs inside [`Subgraph`] components
+//! on its nodes. The walkers here visit the root graph and every nested graph
+//! in pre-order, telling the visitor which container nodes lead to it.
+//!
+//! Hierarchies can also be reshaped: [`ContextGraph::flatten`] inlines every
+//! nested graph into its parent and [`ContextGraph::collapse`] does the
+//! reverse for a chosen set of nodes.
+
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use petgraph::Direction;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Debug;
+
+/// One step down the hierarchy: the container node in its parent graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HierarchyStep {
+    /// The graph holding the container node
+    pub graph_id: ContextGraphId,
+    /// The node carrying the Subgraph component
+    pub node_id: NodeId,
+}
+
+/// Position of a visited graph within the hierarchy
+#[derive(Debug, Clone, Copy)]
+pub struct HierarchyContext<'p> {
+    /// Container nodes from the root down to the visited graph
+    pub path: &'p [HierarchyStep],
+}
+
+impl HierarchyContext<'_> {
+    /// Nesting depth; the root graph is at depth 0
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// The container node directly holding the visited graph
+    pub fn parent(&self) -> Option<&HierarchyStep> {
+        self.path.last()
+    }
+}
+
+/// What a hierarchy walk should do after visiting a graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WalkControl {
+    /// Descend into the graph's subgraphs
+    #[default]
+    Continue,
+    /// Do not descend into this graph's subgraphs
+    SkipChildren,
+    /// End the walk immediately
+    Stop,
+}
+
+/// Limits applied to a hierarchy walk
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WalkOptions {
+    /// Do not descend below this depth (the root is depth 0)
+    pub max_depth: Option<usize>,
+}
+
+impl WalkOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+}
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    /// Walk this graph and every nested subgraph in pre-order
+    ///
+    /// Subgraphs are visited in node order across the whole graph, not only
+    /// those reachable from the first node. A nested graph whose ID already
+    /// appears on the path from the root is skipped, so self-containing copies
+    /// cannot recurse forever.
+    pub fn walk_hierarchy<F>(&self, visitor: F)
+    where
+        F: FnMut(&ContextGraph<N, E>, &HierarchyContext<'_>) -> WalkControl,
+    {
+        self.walk_hierarchy_with(WalkOptions::default(), visitor);
+    }
+
+    /// Walk the hierarchy with explicit limits
+    pub fn walk_hierarchy_with<F>(&self, options: WalkOptions, mut visitor: F)
+    where
+        F: FnMut(&ContextGraph<N, E>, &HierarchyContext<'_>) -> WalkControl,
+    {
+        let mut path = Vec::new();
+        let mut ancestors = vec![self.id];
+        self.walk_impl(&options, &mut visitor, &mut path, &mut ancestors);
+    }
+
+    fn walk_impl<F>(
+        &self,
+        options: &WalkOptions,
+        visitor: &mut F,
+        path: &mut Vec<HierarchyStep>,
+        ancestors: &mut Vec<ContextGraphId>,
+    ) -> bool
+    where
+        F: FnMut(&ContextGraph<N, E>, &HierarchyContext<'_>) -> WalkControl,
+    {
+        match visitor(self, &HierarchyContext { path }) {
+            WalkControl::Stop => return false,
+            WalkControl::SkipChildren => return true,
+            WalkControl::Continue => {}
+        }
+        if options.max_depth.is_some_and(|max| path.len() >= max) {
+            return true;
+        }
+
+        for (node_id, node) in self.get_all_nodes() {
+            let Some(subgraph) = node.get_component::<Subgraph<N, E>>() else {
+                continue;
+            };
+            if ancestors.contains(&subgraph.graph.id) {
+                continue;
+            }
+
+            path.push(HierarchyStep {
+                graph_id: self.id,
+                node_id,
+            });
+            ancestors.push(subgraph.graph.id);
+            let keep_going = subgraph.graph.walk_impl(options, visitor, path, ancestors);
+            ancestors.pop();
+            path.pop();
+
+            if !keep_going {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Walk the hierarchy with mutable access to every graph
+    ///
+    /// Each nested graph is visited after its parent, so the visitor may add
+    /// or remove Subgraph components before they are descended into.
+    pub fn walk_hierarchy_mut<F>(&mut self, visitor: F)
+    where
+        F: FnMut(&mut ContextGraph<N, E>, &HierarchyContext<'_>) -> WalkControl,
+    {
+        self.walk_hierarchy_mut_with(WalkOptions::default(), visitor);
+    }
+
+    /// Walk the hierarchy mutably with explicit limits
+    pub fn walk_hierarchy_mut_with<F>(&mut self, options: WalkOptions, mut visitor: F)
+    where
+        F: FnMut(&mut ContextGraph<N, E>, &HierarchyContext<'_>) -> WalkControl,
+    {
+        let mut path = Vec::new();
+        let mut ancestors = vec![self.id];
+        self.walk_mut_impl(&options, &mut visitor, &mut path, &mut ancestors);
+    }
+
+    fn walk_mut_impl<F>(
+        &mut self,
+        options: &WalkOptions,
+        visitor: &mut F,
+        path: &mut Vec<HierarchyStep>,
+        ancestors: &mut Vec<ContextGraphId>,
+    ) -> bool
+    where
+        F: FnMut(&mut ContextGraph<N, E>, &HierarchyContext<'_>) -> WalkControl,
+    {
+        match visitor(self, &HierarchyContext { path }) {
+            WalkControl::Stop => return false,
+            WalkControl::SkipChildren => return true,
+            WalkControl::Continue => {}
+        }
+        if options.max_depth.is_some_and(|max| path.len() >= max) {
+            return true;
+        }
+
+        for node_id in self.get_subgraph_nodes() {
+            let Some(node) = self.get_node_mut(node_id) else {
+                continue;
+            };
+            // Components are immutable, so take the subgraph out and put the
+            // updated one back once its walk is done
+            let Some(mut subgraph) = node.components.take::<Subgraph<N, E>>() else {
+                continue;
+            };
+
+            let mut keep_going = true;
+            if !ancestors.contains(&subgraph.graph.id) {
+                path.push(HierarchyStep {
+                    graph_id: self.id,
+                    node_id,
+                });
+                ancestors.push(subgraph.graph.id);
+                keep_going = subgraph
+                    .graph
+                    .walk_mut_impl(options, visitor, path, ancestors);
+                ancestors.pop();
+                path.pop();
+            }
+
+            if let Some(node) = self.get_node_mut(node_id) {
+                // The slot was emptied by take above
+                let _ = node.components.add(subgraph);
+            }
+            if !keep_going {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// How edges that touched a container node are treated when it is flattened
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ContainerRewiring {
+    /// Keep the container node and its edges; inlined nodes stay unconnected
+    KeepContainer,
+    /// Remove the container; incoming edges go to the subgraph's entry nodes
+    /// (no incoming edges inside it) and outgoing edges leave from its exit
+    /// nodes (no outgoing edges inside it)
+    #[default]
+    EntryExit,
+    /// Remove the container; its edges are copied to every inlined node
+    AllNodes,
+    /// Remove the container together with its edges
+    Drop,
+}
+
+/// Options for [`ContextGraph::flatten_with`]
+#[derive(Debug, Clone, Copy)]
+pub struct FlattenOptions {
+    pub rewiring: ContainerRewiring,
+    /// Attach a [`Provenance`] component to inlined nodes and edges
+    pub record_provenance: bool,
+}
+
+impl Default for FlattenOptions {
+    fn default() -> Self {
+        Self {
+            rewiring: ContainerRewiring::default(),
+            record_provenance: true,
+        }
+    }
+}
+
+impl FlattenOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn rewiring(mut self, rewiring: ContainerRewiring) -> Self {
+        self.rewiring = rewiring;
+        self
+    }
+
+    pub fn record_provenance(mut self, record: bool) -> Self {
+        self.record_provenance = record;
+        self
+    }
+}
+
+/// A flattened nested graph waiting to be wired into its parent
+struct Inlined {
+    nodes: Vec<NodeId>,
+    entries: Vec<NodeId>,
+    exits: Vec<NodeId>,
+}
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    /// Inline every nested subgraph into a single graph with default options
+    pub fn flatten(&self) -> ContextGraph<N, E> {
+        self.flatten_with(FlattenOptions::default())
+    }
+
+    /// Inline every nested subgraph into a single graph
+    ///
+    /// The result keeps this graph's ID, metadata and ID generator but no
+    /// invariants. Nested nodes and edges keep their IDs unless they clash with
+    /// an element that is already present, in which case they get fresh ones
+    /// from the generator, as do edges added by rewiring. Subgraphs that
+    /// repeat a graph ID from their own ancestry are left in place.
+    pub fn flatten_with(&self, options: FlattenOptions) -> ContextGraph<N, E> {
+        let mut ancestors = vec![self.id];
+        self.flatten_impl(&options, &mut ancestors)
+    }
+
+    fn flatten_impl(
+        &self,
+        options: &FlattenOptions,
+        ancestors: &mut Vec<ContextGraphId>,
+    ) -> ContextGraph<N, E> {
+        let mut flat = self.empty_like();
+        flat.invariants.clear();
+
+        // Copy our own nodes, pulling the nested graphs out of containers
+        let mut nested = Vec::new();
+        for (node_id, node) in self.get_all_nodes() {
+            let mut entry = node.clone();
+            if let Some(subgraph) = entry.components.take::<Subgraph<N, E>>() {
+                if ancestors.contains(&subgraph.graph.id) {
+                    let _ = entry.components.add(subgraph);
+                } else {
+                    ancestors.push(subgraph.graph.id);
+                    nested.push((node_id, subgraph.graph.flatten_impl(options, ancestors)));
+                    ancestors.pop();
+                }
+            }
+            flat.insert_node_entry(entry);
+        }
+
+        // Inline the nested nodes, remembering where container edges attach
+        let mut inlined = HashMap::new();
+        let mut nested_edges = Vec::new();
+        for (container, inner) in nested {
+            let mut remap = HashMap::new();
+            let mut nodes = Vec::new();
+            for (node_id, node) in inner.get_all_nodes() {
+                let mut entry = node.clone();
+                if flat.get_node(node_id).is_some() {
+                    entry.id = flat.fresh_node_id();
+                }
+                remap.insert(node_id, entry.id);
+                nodes.push(entry.id);
+                if options.record_provenance && !entry.has_component::<Provenance>() {
+                    let _ = entry.add_component(Provenance {
+                        graph_id: inner.id,
+                        container,
+                    });
+                }
+                flat.insert_node_entry(entry);
+            }
+
+            let ends = |direction: Direction| -> Vec<NodeId> {
+                let ends: Vec<NodeId> = inner
+                    .get_all_nodes()
+                    .filter(|(id, _)| match direction {
+                        Direction::Incoming => inner.predecessors(*id).is_empty(),
+                        Direction::Outgoing => inner.successors(*id).is_empty(),
+                    })
+                    .map(|(id, _)| remap[&id])
+                    .collect();
+                if ends.is_empty() {
+                    nodes.clone()
+                } else {
+                    ends
+                }
+            };
+            inlined.insert(
+                container,
+                Inlined {
+                    entries: ends(Direction::Incoming),
+                    exits: ends(Direction::Outgoing),
+                    nodes,
+                },
+            );
+
+            for (_, edge) in inner.get_all_edges() {
+                let mut entry = edge.clone();
+                entry.source = remap[&edge.source];
+                entry.target = remap[&edge.target];
+                if options.record_provenance && !entry.has_component::<Provenance>() {
+                    let _ = entry.add_component(Provenance {
+                        graph_id: inner.id,
+                        container,
+                    });
+                }
+                nested_edges.push(entry);
+            }
+        }
+
+        // Our own edges, rewired away from containers that are going away
+        let rewire = options.rewiring != ContainerRewiring::KeepContainer;
+        for (_, edge) in self.get_all_edges() {
+            let endpoints = |node_id: NodeId, outgoing: bool| -> Vec<NodeId> {
+                match inlined.get(&node_id) {
+                    Some(inner) if rewire => match options.rewiring {
+                        ContainerRewiring::EntryExit if outgoing => inner.exits.clone(),
+                        ContainerRewiring::EntryExit => inner.entries.clone(),
+                        ContainerRewiring::AllNodes => inner.nodes.clone(),
+                        ContainerRewiring::Drop | ContainerRewiring::KeepContainer => Vec::new(),
+                    },
+                    _ => vec![node_id],
+                }
+            };
+
+            let mut first = true;
+            for source in endpoints(edge.source, true) {
+                for target in endpoints(edge.target, false) {
+                    let mut entry = edge.clone();
+                    if !first {
+                        entry.id = flat.fresh_edge_id();
+                    }
+                    first = false;
+                    entry.source = source;
+                    entry.target = target;
+                    let _ = flat.insert_edge_entry(entry);
+                }
+            }
+        }
+
+        for mut entry in nested_edges {
+            if flat.get_edge(entry.id).is_some() {
+                entry.id = flat.fresh_edge_id();
+            }
+            let _ = flat.insert_edge_entry(entry);
+        }
+
+        if rewire {
+            flat.remove_nodes(&inlined.keys().copied().collect());
+        }
+
+        flat
+    }
+
+    /// Extract a set of nodes into a Subgraph on a new container node
+    ///
+    /// The selected nodes and the edges among them move into the nested
+    /// graph, keeping their IDs. Edges crossing the boundary are reattached
+    /// to the container, keeping their IDs, values and components. Returns the
+    /// container's ID.
+    pub fn collapse(
+        &mut self,
+        nodes: impl IntoIterator<Item = NodeId>,
+        container_value: N,
+    ) -> GraphResult<NodeId> {
+        let selected: HashSet<NodeId> = nodes.into_iter().collect();
+        if selected.is_empty() {
+            return Err(GraphError::InvalidOperation(
+                "Cannot collapse an empty node set".to_string(),
+            ));
+        }
+        if let Some(missing) = selected.iter().find(|id| self.get_node(**id).is_none()) {
+            return Err(GraphError::NodeNotFound(*missing));
+        }
+
+        let ordered: Vec<NodeId> = self
+            .get_all_nodes()
+            .map(|(id, _)| id)
+            .filter(|id| selected.contains(id))
+            .collect();
+        let inner = self.induced_subgraph(ordered);
+
+        let crossing: Vec<EdgeEntry<E>> = self
+            .get_all_edges()
+            .filter(|(_, edge)| selected.contains(&edge.source) != selected.contains(&edge.target))
+            .map(|(_, edge)| edge.clone())
+            .collect();
+
+        // Collapse a copy-on-write clone and keep it only if everything succeeds
+        let mut collapsed = self.clone();
+        collapsed.remove_nodes(&selected);
+
+        let container = collapsed.add_node(container_value);
+        if let Some(node) = collapsed.get_node_mut(container) {
+            node.add_component(Subgraph {
+                graph: Box::new(inner),
+            })?;
+        }
+
+        for mut edge in crossing {
+            if selected.contains(&edge.source) {
+                edge.source = container;
+            } else {
+                edge.target = container;
+            }
+            collapsed.insert_edge_entry(edge)?;
+        }
+
+        collapsed.check_invariants()?;
+        *self = collapsed;
+        Ok(container)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context_graph::GraphInvariant;
+    use crate::diff::diff;
+
+    fn nested(
+        name: &str,
+        inner: Option<ContextGraph<String, String>>,
+    ) -> ContextGraph<String, String> {
+        let mut graph = ContextGraph::new(name);
+        let a = graph.add_node(format!("{name}.a"));
+        let b = graph.add_node(format!("{name}.b"));
+        graph.add_edge(a, b, "link".to_string()).unwrap();
+        if let Some(inner) = inner {
+            graph
+                .get_node_mut(b)
+                .unwrap()
+                .add_component(Subgraph {
+                    graph: Box::new(inner),
+                })
+                .unwrap();
+        }
+        graph
+    }
+
+    #[test]
+    fn test_walk_visits_disconnected_subgraphs_with_paths() {
+        let leaf = nested("leaf", None);
+        let middle = nested("middle", Some(leaf));
+        let mut root = nested("root", None);
+
+        // A container in a separate component from the first node
+        let isolated = root.add_node("isolated".to_string());
+        root.get_node_mut(isolated)
+            .unwrap()
+            .add_component(Subgraph {
+                graph: Box::new(middle),
+            })
+            .unwrap();
+
+        let mut seen = Vec::new();
+        root.walk_hierarchy(|graph, ctx| {
+            seen.push((graph.node_count(), ctx.depth()));
+            WalkControl::Continue
+        });
+        assert_eq!(seen, vec![(3, 0), (2, 1), (2, 2)]);
+
+        let mut limited = 0;
+        root.walk_hierarchy_with(WalkOptions::new().max_depth(1), |_, _| {
+            limited += 1;
+            WalkControl::Continue
+        });
+        assert_eq!(limited, 2);
+
+        let mut parents = Vec::new();
+        root.walk_hierarchy(|_, ctx| {
+            parents.push(ctx.parent().map(|step| step.node_id));
+            WalkControl::Continue
+        });
+        assert_eq!(parents[1], Some(isolated));
+
+        assert_eq!(root.total_node_count(), 7);
+        assert_eq!(root.total_edge_count(), 3);
+    }
+
+    #[test]
+    fn test_walk_skips_repeated_graph_ids() {
+        let mut root = nested("root", None);
+        let copy = root.clone();
+        let first = root.get_all_nodes().next().map(|(id, _)| id).unwrap();
+        root.get_node_mut(first)
+            .unwrap()
+            .add_component(Subgraph {
+                graph: Box::new(copy),
+            })
+            .unwrap();
+
+        let mut visits = 0;
+        root.walk_hierarchy(|_, _| {
+            visits += 1;
+            WalkControl::Continue
+        });
+        assert_eq!(visits, 1);
+    }
+
+    #[test]
+    fn test_walk_hierarchy_mut() {
+        let leaf = nested("leaf", None);
+        let mut root = nested("root", Some(leaf));
+
+        root.walk_hierarchy_mut(|graph, ctx| {
+            graph.add_node(format!("added at depth {}", ctx.depth()));
+            WalkControl::Continue
+        });
+
+        assert_eq!(root.node_count(), 3);
+        assert_eq!(root.total_node_count(), 6);
+        assert_eq!(root.get_subgraph_nodes().len(), 1);
+    }
+
+    fn container_graph() -> (ContextGraph<String, String>, [NodeId; 5]) {
+        let mut inner = ContextGraph::new("inner");
+        let a = inner.add_node("a".to_string());
+        let b = inner.add_node("b".to_string());
+        inner.add_edge(a, b, "inner".to_string()).unwrap();
+
+        let mut root = ContextGraph::new("root");
+        let x = root.add_node("x".to_string());
+        let c = root.add_node("container".to_string());
+        let y = root.add_node("y".to_string());
+        root.add_edge(x, c, "in".to_string()).unwrap();
+        root.add_edge(c, y, "out".to_string()).unwrap();
+        root.get_node_mut(c)
+            .unwrap()
+            .add_component(Subgraph {
+                graph: Box::new(inner),
+            })
+            .unwrap();
+
+        (root, [x, c, y, a, b])
+    }
+
+    #[test]
+    fn test_flatten_rewires_container_edges() {
+        let (root, [x, c, y, a, b]) = container_graph();
+
+        let flat = root.flatten();
+        assert_eq!(flat.id, root.id);
+        assert_eq!(flat.node_count(), 4);
+        assert!(flat.get_node(c).is_none());
+        assert_eq!(flat.successors(x), vec![a]);
+        assert_eq!(flat.successors(a), vec![b]);
+        assert_eq!(flat.successors(b), vec![y]);
+        assert!(flat.get_subgraph_nodes().is_empty());
+
+        let provenance = flat.get_node(a).unwrap().get_component::<Provenance>();
+        assert_eq!(provenance.map(|p| p.container), Some(c));
+        assert!(flat
+            .get_node(x)
+            .unwrap()
+            .get_component::<Provenance>()
+            .is_none());
+
+        let kept = root.flatten_with(
+            FlattenOptions::new()
+                .rewiring(ContainerRewiring::KeepContainer)
+                .record_provenance(false),
+        );
+        assert_eq!(kept.node_count(), 5);
+        assert_eq!(kept.successors(x), vec![c]);
+        assert!(kept.get_node(a).unwrap().components.is_empty());
+
+        let dropped = root.flatten_with(FlattenOptions::new().rewiring(ContainerRewiring::Drop));
+        assert_eq!(dropped.node_count(), 4);
+        assert_eq!(dropped.edge_count(), 1);
+    }
+
+    #[test]
+    fn test_collapse_round_trips_with_flatten() {
+        let (root, [x, _, y, a, b]) = container_graph();
+        let mut flat = root.flatten_with(FlattenOptions::new().record_provenance(false));
+
+        let container = flat.collapse([a, b], "group".to_string()).unwrap();
+        assert_eq!(flat.node_count(), 3);
+        assert_eq!(flat.successors(x), vec![container]);
+        assert_eq!(flat.successors(container), vec![y]);
+
+        let subgraph = flat
+            .get_node(container)
+            .unwrap()
+            .get_component::<Subgraph<String, String>>()
+            .unwrap();
+        assert_eq!(subgraph.graph.node_count(), 2);
+        assert_eq!(subgraph.graph.successors(a), vec![b]);
+
+        assert!(flat.collapse(Vec::new(), "empty".to_string()).is_err());
+        assert!(matches!(
+            flat.collapse([a], "gone".to_string()),
+            Err(GraphError::NodeNotFound(_))
+        ));
+    }
+
+    #[derive(Clone)]
+    struct NoSubgraphs;
+
+    impl GraphInvariant<String, String> for NoSubgraphs {
+        fn check(&self, graph: &ContextGraph<String, String>) -> GraphResult<()> {
+            match graph.get_subgraph_nodes().first() {
+                Some(node_id) => Err(GraphError::InvariantViolation(format!(
+                    "{node_id} holds a subgraph"
+                ))),
+                None => Ok(()),
+            }
+        }
+
+        fn name(&self) -> &str {
+            "NoSubgraphs"
+        }
+
+        fn clone_box(&self) -> Box<dyn GraphInvariant<String, String>> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_failed_collapse_leaves_graph_unchanged() {
+        let (root, [x, _, _, a, b]) = container_graph();
+        let mut flat = root.flatten_with(FlattenOptions::new().record_provenance(false));
+        flat.invariants.push(Box::new(NoSubgraphs));
+        let before = flat.clone();
+
+        assert!(flat.collapse([a, b], "group".to_string()).is_err());
+        assert!(diff(&before, &flat).unwrap().is_empty());
+        assert_eq!(flat.successors(x), vec![a]);
+    }
+}
@@ -0,0 +1,442 @@
+//! Versioned graphs with time-travel queries
+//!
+//! A [`VersionedGraph`] keeps an editable working copy on top of a linear
+//! history of commits. Each commit gets a version number, a timestamp and an
+//! author, and stores only the [`GraphDiff`] it introduced. Full graphs are
+//! kept as shared checkpoints every few versions, so an old version is
+//! rebuilt by patching forward from the nearest checkpoint (or backward from
+//! the head, whichever is closer) instead of storing a copy per commit.
+//!
+//! [`VersionedGraph::as_of`] returns an ordinary [`ContextGraph`], so every
+//! existing query, traversal and export works against historical versions.
+//! Diffs only carry components with a codec in the history's
+//! [`ComponentCodecs`]; a commit that adds, removes or replaces any other
+//! component (such as a [`Subgraph`]) also stores a checkpoint, so rebuilt
+//! versions always match what was committed.
+//!
+//! Committing and discarding clone the graph in O(1): the working copy and
+//! the head share their storage, and later edits copy only the entries they
+//! touch.
+
+use crate::binary::ComponentCodecs;
+use crate::context_graph::ContextGraph;
+use crate::diff::{diff_with, GraphDiff};
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// Default number of versions between full checkpoints
+const DEFAULT_CHECKPOINT_INTERVAL: u64 = 16;
+
+/// One entry in the version log
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VersionInfo {
+    pub version: u64,
+    pub timestamp: SystemTime,
+    pub author: String,
+    pub message: String,
+}
+
+struct Revision<N, E> {
+    info: VersionInfo,
+    changes: GraphDiff<N, E>,
+    /// Components without a codec changed, so `changes` is incomplete
+    untracked: bool,
+    checkpoint: Option<Arc<ContextGraph<N, E>>>,
+}
+
+/// A graph with a linear, queryable history of committed versions
+pub struct VersionedGraph<N, E> {
+    working: ContextGraph<N, E>,
+    head: Arc<ContextGraph<N, E>>,
+    revisions: Vec<Revision<N, E>>,
+    codecs: ComponentCodecs,
+    checkpoint_interval: u64,
+}
+
+impl<N, E> VersionedGraph<N, E>
+where
+    N: Clone + Debug + PartialEq + 'static + Send + Sync,
+    E: Clone + Debug + PartialEq + 'static + Send + Sync,
+{
+    /// Start a history whose version 0 is `graph`
+    pub fn new(graph: ContextGraph<N, E>, author: impl Into<String>) -> GraphResult<Self> {
+        Self::with_codecs(graph, author, ComponentCodecs::default())
+    }
+
+    /// Start a history that tracks the component types in `codecs`
+    pub fn with_codecs(
+        graph: ContextGraph<N, E>,
+        author: impl Into<String>,
+        codecs: ComponentCodecs,
+    ) -> GraphResult<Self> {
+        let mut empty = ContextGraph::new("");
+        empty.id = graph.id;
+        empty.metadata = Metadata::default();
+        let changes = diff_with(&empty, &graph, &codecs)?;
+
+        let head = Arc::new(graph.clone());
+        Ok(Self {
+            working: graph,
+            revisions: vec![Revision {
+                info: VersionInfo {
+                    version: 0,
+                    timestamp: SystemTime::now(),
+                    author: author.into(),
+                    message: "Initial version".to_string(),
+                },
+                changes,
+                untracked: false,
+                checkpoint: Some(head.clone()),
+            }],
+            head,
+            codecs,
+            checkpoint_interval: DEFAULT_CHECKPOINT_INTERVAL,
+        })
+    }
+
+    /// Keep a full checkpoint every `interval` versions (at least 1)
+    pub fn with_checkpoint_interval(mut self, interval: u64) -> Self {
+        self.checkpoint_interval = interval.max(1);
+        self
+    }
+
+    /// The uncommitted working copy
+    pub fn working(&self) -> &ContextGraph<N, E> {
+        &self.working
+    }
+
+    /// Edit the working copy; changes become a version on [`commit`](Self::commit)
+    pub fn working_mut(&mut self) -> &mut ContextGraph<N, E> {
+        &mut self.working
+    }
+
+    /// Whether the working copy differs from the head version
+    pub fn has_uncommitted_changes(&self) -> GraphResult<bool> {
+        Ok(!diff_with(&self.head, &self.working, &self.codecs)?.is_empty())
+    }
+
+    /// Throw away uncommitted edits
+    pub fn discard(&mut self) {
+        self.working = ContextGraph::clone(&self.head);
+    }
+
+    /// Commit the working copy as a new version stamped with the current time
+    pub fn commit(
+        &mut self,
+        author: impl Into<String>,
+        message: impl Into<String>,
+    ) -> GraphResult<u64> {
+        let timestamp = SystemTime::now().max(self.latest().timestamp);
+        self.commit_at(author, message, timestamp)
+    }
+
+    /// Commit with an explicit timestamp, e.g. when importing history
+    ///
+    /// Timestamps must not go backwards, so time-based lookups stay ordered.
+    pub fn commit_at(
+        &mut self,
+        author: impl Into<String>,
+        message: impl Into<String>,
+        timestamp: SystemTime,
+    ) -> GraphResult<u64> {
+        if timestamp < self.latest().timestamp {
+            return Err(GraphError::InvalidOperation(
+                "Commit timestamp is earlier than the previous version".to_string(),
+            ));
+        }
+
+        let changes = diff_with(&self.head, &self.working, &self.codecs)?;
+        let untracked = self.untracked_changed(&self.head, &self.working);
+        let version = self.head_version() + 1;
+        // O(1): the head shares storage with the working copy
+        self.head = Arc::new(self.working.clone());
+        let checkpoint = (untracked || version.is_multiple_of(self.checkpoint_interval))
+            .then(|| self.head.clone());
+
+        self.revisions.push(Revision {
+            info: VersionInfo {
+                version,
+                timestamp,
+                author: author.into(),
+                message: message.into(),
+            },
+            changes,
+            untracked,
+            checkpoint,
+        });
+        Ok(version)
+    }
+
+    /// The most recently committed version number
+    pub fn head_version(&self) -> u64 {
+        self.latest().version
+    }
+
+    /// The most recently committed graph
+    pub fn head(&self) -> Arc<ContextGraph<N, E>> {
+        self.head.clone()
+    }
+
+    /// The version log, oldest first
+    pub fn log(&self) -> impl Iterator<Item = &VersionInfo> {
+        self.revisions.iter().map(|revision| &revision.info)
+    }
+
+    /// Log entry for one version
+    pub fn version_info(&self, version: u64) -> Option<&VersionInfo> {
+        self.revision(version).map(|revision| &revision.info)
+    }
+
+    /// The changes a version introduced over its predecessor
+    pub fn changes(&self, version: u64) -> Option<&GraphDiff<N, E>> {
+        self.revision(version).map(|revision| &revision.changes)
+    }
+
+    /// The latest version committed at or before `time`
+    pub fn version_at(&self, time: SystemTime) -> Option<u64> {
+        let count = self
+            .revisions
+            .partition_point(|revision| revision.info.timestamp <= time);
+        count.checked_sub(1).map(|index| index as u64)
+    }
+
+    /// The graph as it was at `version`
+    pub fn as_of(&self, version: u64) -> GraphResult<Arc<ContextGraph<N, E>>> {
+        let head = self.head_version();
+        if version > head {
+            return Err(GraphError::InvalidOperation(format!(
+                "Unknown version {version}; head is {head}"
+            )));
+        }
+        if version == head {
+            return Ok(self.head.clone());
+        }
+
+        let (base, checkpoint) = (0..=version)
+            .rev()
+            .find_map(|v| {
+                self.revisions[v as usize]
+                    .checkpoint
+                    .as_ref()
+                    .map(|c| (v, c))
+            })
+            .expect("version 0 is always a checkpoint");
+        if base == version {
+            return Ok(checkpoint.clone());
+        }
+
+        // Patching backwards would drop untracked components changed after
+        // `version`; those versions are checkpoints, so forwards is exact
+        let later = &self.revisions[version as usize + 1..];
+        let mut graph;
+        if head - version < version - base && !later.iter().any(|r| r.untracked) {
+            graph = ContextGraph::clone(&self.head);
+            for revision in later.iter().rev() {
+                graph.apply_patch_with(&revision.changes.inverse(), &self.codecs)?;
+            }
+        } else {
+            graph = ContextGraph::clone(checkpoint);
+            for revision in &self.revisions[base as usize + 1..=version as usize] {
+                graph.apply_patch_with(&revision.changes, &self.codecs)?;
+            }
+        }
+        Ok(Arc::new(graph))
+    }
+
+    /// The graph as it was at `time`
+    pub fn as_of_time(&self, time: SystemTime) -> GraphResult<Arc<ContextGraph<N, E>>> {
+        let version = self.version_at(time).ok_or_else(|| {
+            GraphError::InvalidOperation("Time predates the first version".to_string())
+        })?;
+        self.as_of(version)
+    }
+
+    /// Everything that changed between two versions
+    pub fn diff_versions(&self, from: u64, to: u64) -> GraphResult<GraphDiff<N, E>> {
+        let (old, new) = (self.as_of(from)?, self.as_of(to)?);
+        diff_with(&old, &new, &self.codecs)
+    }
+
+    /// Whether a component without a codec was added, removed or replaced
+    fn untracked_changed(&self, old: &ContextGraph<N, E>, new: &ContextGraph<N, E>) -> bool {
+        let nodes = new.get_all_nodes().any(|(id, node)| {
+            let before = old.get_node(id).map(|o| &o.components);
+            self.untracked_differ(before, Some(&node.components))
+        }) || old.get_all_nodes().any(|(id, node)| {
+            new.get_node(id).is_none() && self.untracked_differ(Some(&node.components), None)
+        });
+        let edges = new.get_all_edges().any(|(id, edge)| {
+            let before = old.get_edge(id).map(|o| &o.components);
+            self.untracked_differ(before, Some(&edge.components))
+        }) || old.get_all_edges().any(|(id, edge)| {
+            new.get_edge(id).is_none() && self.untracked_differ(Some(&edge.components), None)
+        });
+        nodes || edges
+    }
+
+    fn untracked_differ(
+        &self,
+        old: Option<&ComponentStorage>,
+        new: Option<&ComponentStorage>,
+    ) -> bool {
+        if let (Some(old), Some(new)) = (old, new) {
+            if std::ptr::eq(old, new) {
+                return false;
+            }
+        }
+        // Components are immutable, so an unchanged one is the same allocation
+        let untracked = |storage: Option<&ComponentStorage>| -> HashMap<TypeId, *const ()> {
+            storage
+                .into_iter()
+                .flat_map(|storage| storage.iter())
+                .filter(|(type_id, _)| self.codecs.name_of(type_id).is_none())
+                .map(|(type_id, component)| {
+                    (*type_id, component as *const dyn Component as *const ())
+                })
+                .collect()
+        };
+        untracked(old) != untracked(new)
+    }
+
+    fn latest(&self) -> &VersionInfo {
+        &self.revisions.last().expect("history is never empty").info
+    }
+
+    fn revision(&self, version: u64) -> Option<&Revision<N, E>> {
+        usize::try_from(version)
+            .ok()
+            .and_then(|index| self.revisions.get(index))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_as_of_rebuilds_every_version() {
+        let graph = ContextGraph::<String, u32>::new("Org");
+        let mut history = VersionedGraph::new(graph, "alice")
+            .unwrap()
+            .with_checkpoint_interval(3);
+
+        let mut expected = vec![history.head()];
+        let mut previous = None;
+        for i in 0..7u32 {
+            let working = history.working_mut();
+            let node = working.add_node(format!("n{i}"));
+            if let Some(prev) = previous {
+                working.add_edge(prev, node, i).unwrap();
+            }
+            if i == 4 {
+                working.remove_node(previous.unwrap());
+            }
+            previous = Some(node);
+            history.commit("bob", format!("step {i}")).unwrap();
+            expected.push(history.head());
+        }
+
+        assert_eq!(history.head_version(), 7);
+        for (version, graph) in expected.iter().enumerate() {
+            let rebuilt = history.as_of(version as u64).unwrap();
+            assert!(diff_with(graph, &rebuilt, &ComponentCodecs::default())
+                .unwrap()
+                .is_empty());
+        }
+        assert!(history.as_of(8).is_err());
+    }
+
+    #[test]
+    fn test_untracked_components_are_checkpointed() {
+        let graph = ContextGraph::<String, ()>::new("Org");
+        let mut history = VersionedGraph::new(graph, "alice").unwrap();
+
+        let team = ContextGraph::<String, ()>::new("Team");
+        let node = history.working_mut().add_node("platform".to_string());
+        history
+            .working_mut()
+            .get_node_mut(node)
+            .unwrap()
+            .add_component(Subgraph {
+                graph: Box::new(team),
+            })
+            .unwrap();
+        history.commit("bob", "Add team").unwrap();
+        history.working_mut().add_node("infra".to_string());
+        history.commit("bob", "Add infra").unwrap();
+        history.working_mut().remove_node(node);
+        history.commit("bob", "Drop team").unwrap();
+        history.working_mut().add_node("data".to_string());
+        history.commit("bob", "Add data").unwrap();
+
+        let has_team = |version| {
+            history
+                .as_of(version)
+                .unwrap()
+                .get_node(node)
+                .is_some_and(|n| n.has_component::<Subgraph<String, ()>>())
+        };
+        assert!(has_team(1));
+        assert!(has_team(2));
+        assert!(history.as_of(3).unwrap().get_node(node).is_none());
+        assert!(!history.revisions[2].untracked);
+        assert!(history.revisions[3].checkpoint.is_some());
+    }
+
+    #[test]
+    fn test_time_travel_by_timestamp() {
+        let start = SystemTime::now();
+        let mut graph = ContextGraph::<String, ()>::new("Org");
+        let ceo = graph.add_node("ceo".to_string());
+        let mut history = VersionedGraph::new(graph, "alice").unwrap();
+
+        let cto = history.working_mut().add_node("cto".to_string());
+        history.working_mut().add_edge(ceo, cto, ()).unwrap();
+        history
+            .commit_at("alice", "Hire CTO", start + Duration::from_secs(100))
+            .unwrap();
+        history.working_mut().remove_node(cto);
+        history
+            .commit_at("carol", "CTO left", start + Duration::from_secs(200))
+            .unwrap();
+
+        let march = history
+            .as_of_time(start + Duration::from_secs(150))
+            .unwrap();
+        assert_eq!(march.node_count(), 2);
+        assert_eq!(march.edge_count(), 1);
+        assert_eq!(
+            history
+                .as_of_time(start + Duration::from_secs(250))
+                .unwrap()
+                .node_count(),
+            1
+        );
+        assert!(history
+            .commit_at("dave", "Backdated", start + Duration::from_secs(50))
+            .is_err());
+
+        let authors: Vec<&str> = history.log().map(|info| info.author.as_str()).collect();
+        assert_eq!(authors, vec!["alice", "alice", "carol"]);
+        assert_eq!(history.changes(2).unwrap().nodes.len(), 1);
+    }
+
+    #[test]
+    fn test_discard_uncommitted_changes() {
+        let graph = ContextGraph::<String, ()>::new("Org");
+        let mut history = VersionedGraph::new(graph, "alice").unwrap();
+        assert!(!history.has_uncommitted_changes().unwrap());
+
+        history.working_mut().add_node("temp".to_string());
+        assert!(history.has_uncommitted_changes().unwrap());
+        history.discard();
+        assert!(!history.has_uncommitted_changes().unwrap());
+        assert_eq!(history.working().node_count(), 0);
+    }
+}
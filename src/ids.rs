@@ -0,0 +1,204 @@
+//! Pluggable generation of node and edge IDs
+//!
+//! Each [`ContextGraph`](crate::ContextGraph) asks its [`IdGenerator`] for the
+//! IDs of nodes and edges added without one. The default, [`RandomIds`],
+//! keeps the original random v4 behaviour; [`TimeOrderedIds`] sorts by
+//! creation time and [`SeededIds`] makes graphs reproducible.
+//!
+//! A cloned graph carries a copy of its generator, so a deterministic
+//! generator yields the same IDs in both clones. Flattening keeps the
+//! generator, and [`GraphBuilder`](crate::GraphBuilder) and
+//! [`ReplicatedGraph`](crate::ReplicatedGraph) take one too. The text
+//! importers (`from_mermaid`, `from_graphml`, `from_jgf`, `from_ntriples`)
+//! start from [`RandomIds`], so elements without an ID in the source get
+//! random v4 IDs; call [`set_id_generator`](crate::ContextGraph::set_id_generator)
+//! on the result to choose IDs for later additions.
+
+use crate::types::{EdgeId, NodeId};
+use uuid::Uuid;
+
+/// Source of IDs for nodes and edges added without one
+pub trait IdGenerator: Send + Sync {
+    fn node_id(&mut self) -> NodeId;
+    fn edge_id(&mut self) -> EdgeId;
+    fn clone_box(&self) -> Box<dyn IdGenerator>;
+}
+
+/// Random (v4) IDs
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RandomIds;
+
+impl IdGenerator for RandomIds {
+    fn node_id(&mut self) -> NodeId {
+        NodeId::new()
+    }
+
+    fn edge_id(&mut self) -> EdgeId {
+        EdgeId::new()
+    }
+
+    fn clone_box(&self) -> Box<dyn IdGenerator> {
+        Box::new(*self)
+    }
+}
+
+/// Time-ordered (v7) IDs, so IDs sort in creation order
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TimeOrderedIds;
+
+impl IdGenerator for TimeOrderedIds {
+    fn node_id(&mut self) -> NodeId {
+        NodeId::new_v7()
+    }
+
+    fn edge_id(&mut self) -> EdgeId {
+        EdgeId::new_v7()
+    }
+
+    fn clone_box(&self) -> Box<dyn IdGenerator> {
+        Box::new(*self)
+    }
+}
+
+/// Name-based (v5) IDs derived from a seed and a counter
+///
+/// Building the same graph in the same order with the same seed gives the
+/// same IDs every time.
+#[derive(Debug, Clone)]
+pub struct SeededIds {
+    namespace: Uuid,
+    next: u64,
+}
+
+impl SeededIds {
+    pub fn new(seed: impl AsRef<[u8]>) -> Self {
+        Self {
+            namespace: Uuid::new_v5(&Uuid::NAMESPACE_OID, seed.as_ref()),
+            next: 0,
+        }
+    }
+
+    fn next_name(&mut self, kind: &str) -> String {
+        let name = format!("{kind}:{}", self.next);
+        self.next += 1;
+        name
+    }
+}
+
+impl IdGenerator for SeededIds {
+    fn node_id(&mut self) -> NodeId {
+        let name = self.next_name("node");
+        NodeId::new_v5(&self.namespace, name)
+    }
+
+    fn edge_id(&mut self) -> EdgeId {
+        let name = self.next_name("edge");
+        EdgeId::new_v5(&self.namespace, name)
+    }
+
+    fn clone_box(&self) -> Box<dyn IdGenerator> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::GraphBuilder;
+    use crate::context_graph::ContextGraph;
+    use crate::hierarchy::{ContainerRewiring, FlattenOptions};
+    use crate::replicated::{ReplicaId, ReplicatedGraph};
+    use crate::types::{ContextGraphId, GraphError, Subgraph};
+
+    fn build(seed: &str) -> (Vec<NodeId>, EdgeId) {
+        let mut graph =
+            ContextGraph::<&str, ()>::new("Seeded").with_id_generator(SeededIds::new(seed));
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let edge = graph.add_edge(a, b, ()).unwrap();
+        (vec![a, b], edge)
+    }
+
+    #[test]
+    fn test_seeded_ids_are_reproducible() {
+        assert_eq!(build("fixture"), build("fixture"));
+        assert_ne!(build("fixture"), build("other"));
+
+        let mut generator = TimeOrderedIds;
+        let ids: Vec<NodeId> = (0..10).map(|_| generator.node_id()).collect();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+
+    #[test]
+    fn test_caller_provided_ids() {
+        let namespace = Uuid::new_v5(&Uuid::NAMESPACE_URL, b"https://example.com/people");
+        let alice = NodeId::new_v5(&namespace, "alice");
+        assert_eq!(alice, NodeId::new_v5(&namespace, "alice"));
+        assert_eq!(alice.to_string().parse::<NodeId>().unwrap(), alice);
+        assert_eq!(NodeId::from_uuid(*alice.as_uuid()), alice);
+        assert!("not-a-uuid".parse::<ContextGraphId>().is_err());
+
+        let mut graph = ContextGraph::<&str, ()>::new("People");
+        let bob = NodeId::new_v5(&namespace, "bob");
+        graph.add_node_with_id(alice, "Alice").unwrap();
+        graph.add_node_with_id(bob, "Bob").unwrap();
+        assert!(matches!(
+            graph.add_node_with_id(alice, "Again"),
+            Err(GraphError::DuplicateNode(id)) if id == alice
+        ));
+
+        let knows = EdgeId::new_v5(&namespace, "alice-knows-bob");
+        graph.add_edge_with_id(knows, alice, bob, ()).unwrap();
+        assert!(matches!(
+            graph.add_edge_with_id(knows, bob, alice, ()),
+            Err(GraphError::DuplicateEdge(id)) if id == knows
+        ));
+        assert_eq!(graph.get_node_value(alice), Some(&"Alice"));
+        assert_eq!(graph.edge_count(), 1);
+    }
+
+    #[test]
+    fn test_generator_reaches_derived_graphs() {
+        let flattened = || {
+            let mut inner =
+                ContextGraph::<&str, ()>::new("Inner").with_id_generator(SeededIds::new("inner"));
+            inner.add_node("p");
+            inner.add_node("q");
+            let mut outer =
+                ContextGraph::<&str, ()>::new("Outer").with_id_generator(SeededIds::new("outer"));
+            let x = outer.add_node("x");
+            let container = outer.add_node("container");
+            outer
+                .get_node_mut(container)
+                .unwrap()
+                .add_component(Subgraph {
+                    graph: Box::new(inner),
+                })
+                .unwrap();
+            outer.add_edge(x, container, ()).unwrap();
+            let options = FlattenOptions::new().rewiring(ContainerRewiring::AllNodes);
+            let mut ids: Vec<EdgeId> = outer
+                .flatten_with(options)
+                .get_all_edges()
+                .map(|(id, _)| id)
+                .collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(flattened().len(), 2);
+        assert_eq!(flattened(), flattened());
+
+        let mut expected = SeededIds::new("bulk");
+        let mut builder =
+            GraphBuilder::<&str, ()>::new("Bulk").with_id_generator(SeededIds::new("bulk"));
+        let a = builder.next_node_id();
+        builder.add_node(a, "a");
+        let mut graph = builder.build().unwrap();
+        assert_eq!(a, expected.node_id());
+        assert_eq!(graph.add_node("b"), expected.node_id());
+
+        let mut replica = ReplicatedGraph::<&str, ()>::new(ContextGraphId::new(), ReplicaId::new())
+            .with_id_generator(SeededIds::new("east"));
+        assert_eq!(replica.add_node("c"), SeededIds::new("east").node_id());
+    }
+}
@@ -0,0 +1,443 @@
+//! JSON Graph Format (JGF) import and export
+//!
+//! Graphs are written in the JGF v2 shape, with nodes keyed by [`NodeId`]
+//! in storage order; both the keyed and the v1 list form are read. Labels
+//! map to the JGF `label`, and node and edge `metadata` holds the value
+//! under `value`, the [`Metadata`] description and tags under `description`
+//! and `tags`, its properties as further entries, and a nested graph under
+//! `subgraph`. Nested graphs are plain JSON metadata, so their nodes come
+//! back in id order rather than storage order.
+
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use serde::de::{DeserializeOwned, MapAccess, SeqAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt::{self, Debug};
+
+const RESERVED: [&str; 4] = ["value", "description", "tags", "subgraph"];
+
+#[derive(Serialize, Deserialize)]
+struct JgfDocument {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    graph: Option<JgfGraph>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    graphs: Vec<JgfGraph>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JgfGraph {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default = "directed")]
+    directed: bool,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    metadata: Map<String, Value>,
+    #[serde(default)]
+    nodes: JgfNodes,
+    #[serde(default)]
+    edges: Vec<JgfEdge>,
+}
+
+fn directed() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize)]
+struct JgfNode {
+    /// Only present in the v1 list form
+    #[serde(default, skip_serializing)]
+    id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    metadata: Map<String, Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JgfEdge {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    source: String,
+    target: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    relation: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    label: Option<String>,
+    #[serde(default, skip_serializing_if = "Map::is_empty")]
+    metadata: Map<String, Value>,
+}
+
+/// Nodes in document order, whichever form they were written in
+#[derive(Default)]
+struct JgfNodes(Vec<(String, JgfNode)>);
+
+impl Serialize for JgfNodes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut map = serializer.serialize_map(Some(self.0.len()))?;
+        for (id, node) in &self.0 {
+            map.serialize_entry(id, node)?;
+        }
+        map.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for JgfNodes {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct NodesVisitor;
+
+        impl<'de> Visitor<'de> for NodesVisitor {
+            type Value = JgfNodes;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("an object of nodes keyed by id, or a list of nodes")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<JgfNodes, A::Error> {
+                let mut nodes = Vec::new();
+                while let Some(entry) = map.next_entry::<String, JgfNode>()? {
+                    nodes.push(entry);
+                }
+                Ok(JgfNodes(nodes))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<JgfNodes, A::Error> {
+                let mut nodes = Vec::new();
+                while let Some(node) = seq.next_element::<JgfNode>()? {
+                    let id = node
+                        .id
+                        .clone()
+                        .ok_or_else(|| serde::de::Error::missing_field("id"))?;
+                    nodes.push((id, node));
+                }
+                Ok(JgfNodes(nodes))
+            }
+        }
+
+        deserializer.deserialize_any(NodesVisitor)
+    }
+}
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    /// Export as a JSON Graph Format document
+    pub fn to_jgf(&self) -> GraphResult<String>
+    where
+        N: Serialize,
+        E: Serialize,
+    {
+        let document = JgfDocument {
+            graph: Some(export_graph(self)?),
+            graphs: Vec::new(),
+        };
+        serde_json::to_string_pretty(&document)
+            .map_err(|err| GraphError::InvalidOperation(format!("JGF export failed: {err}")))
+    }
+
+    /// Import a JSON Graph Format document
+    ///
+    /// Reads `graph`, or the first entry of `graphs`. Ids that are not
+    /// `NodeId`/`EdgeId` strings get fresh ids. Without a `value` in the
+    /// metadata, nodes fall back to `null` and then their JGF id, edges to
+    /// `null`, their `relation`, and then the empty string. Errors found after
+    /// JSON parsing are located at the first mention of the offending id.
+    pub fn from_jgf(text: &str) -> GraphResult<Self>
+    where
+        N: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        let document: JgfDocument = serde_json::from_str(text)
+            .map_err(|err| parse_error(err.line(), err.column(), err.to_string()))?;
+        let graph = document
+            .graph
+            .or_else(|| document.graphs.into_iter().next())
+            .ok_or_else(|| parse_error(1, 1, "no graph in document".to_string()))?;
+        import_graph(graph, text)
+    }
+}
+
+fn parse_error(line: usize, column: usize, message: String) -> GraphError {
+    GraphError::Parse {
+        format: "JGF".to_string(),
+        line,
+        column,
+        message,
+    }
+}
+
+/// Error located at the first quoted mention of `id` in the source
+fn error_at(source: &str, id: &str, message: String) -> GraphError {
+    match source.find(&format!("\"{id}\"")) {
+        Some(offset) => {
+            let before = &source[..offset];
+            let line = before.matches('\n').count() + 1;
+            let column = before.len() - before.rfind('\n').map_or(0, |i| i + 1) + 1;
+            parse_error(line, column, message)
+        }
+        None => parse_error(0, 0, message),
+    }
+}
+
+fn export_graph<N, E>(graph: &ContextGraph<N, E>) -> GraphResult<JgfGraph>
+where
+    N: Clone + Debug + Serialize + 'static + Send + Sync,
+    E: Clone + Debug + Serialize + 'static + Send + Sync,
+{
+    let mut nodes = Vec::new();
+    for (node_id, node) in graph.get_all_nodes() {
+        let mut metadata = export_metadata(&node.value, &node.components)?;
+        if let Some(subgraph) = node.get_component::<Subgraph<N, E>>() {
+            let inner = serde_json::to_value(export_graph(&subgraph.graph)?)
+                .map_err(|err| GraphError::InvalidOperation(err.to_string()))?;
+            metadata.insert("subgraph".to_string(), inner);
+        }
+        nodes.push((
+            node_id.to_string(),
+            JgfNode {
+                id: None,
+                label: node.get_component::<Label>().map(|label| label.0.clone()),
+                metadata,
+            },
+        ));
+    }
+
+    let mut edges = Vec::new();
+    for (edge_id, edge) in graph.get_all_edges() {
+        edges.push(JgfEdge {
+            id: Some(edge_id.to_string()),
+            source: edge.source.to_string(),
+            target: edge.target.to_string(),
+            relation: None,
+            label: edge.get_component::<Label>().map(|label| label.0.clone()),
+            metadata: export_metadata(&edge.value, &edge.components)?,
+        });
+    }
+
+    Ok(JgfGraph {
+        id: Some(graph.id.to_string()),
+        label: graph
+            .metadata
+            .properties
+            .get("name")
+            .and_then(|v| v.as_str())
+            .map(str::to_string),
+        directed: true,
+        metadata: Map::new(),
+        nodes: JgfNodes(nodes),
+        edges,
+    })
+}
+
+fn export_metadata<T: Serialize>(
+    value: &T,
+    components: &ComponentStorage,
+) -> GraphResult<Map<String, Value>> {
+    let mut map = Map::new();
+    if let Some(metadata) = components.get::<Metadata>() {
+        for (name, value) in &metadata.properties {
+            if !RESERVED.contains(&name.as_str()) {
+                map.insert(name.clone(), value.clone());
+            }
+        }
+        if let Some(description) = &metadata.description {
+            map.insert("description".to_string(), description.clone().into());
+        }
+        if !metadata.tags.is_empty() {
+            map.insert("tags".to_string(), metadata.tags.clone().into());
+        }
+    }
+    let value = serde_json::to_value(value)
+        .map_err(|err| GraphError::InvalidOperation(format!("value not serializable: {err}")))?;
+    map.insert("value".to_string(), value);
+    Ok(map)
+}
+
+fn import_graph<N, E>(jgf: JgfGraph, source: &str) -> GraphResult<ContextGraph<N, E>>
+where
+    N: Clone + Debug + DeserializeOwned + 'static + Send + Sync,
+    E: Clone + Debug + DeserializeOwned + 'static + Send + Sync,
+{
+    let name = jgf
+        .label
+        .clone()
+        .or_else(|| jgf.id.clone())
+        .unwrap_or_else(|| "JGF".to_string());
+    let mut graph = ContextGraph::new(name);
+    if let Some(id) = jgf.id.as_deref().and_then(|id| id.parse().ok()) {
+        graph.id = id;
+    }
+
+    let mut ids = HashMap::new();
+    for (name, node) in jgf.nodes.0 {
+        let node_id = name.parse::<NodeId>().unwrap_or_default();
+        if ids.contains_key(&name) || graph.get_node(node_id).is_some() {
+            return Err(error_at(
+                source,
+                &name,
+                format!("duplicate node id `{name}`"),
+            ));
+        }
+
+        let mut metadata = node.metadata;
+        let value = import_value(metadata.remove("value"), Value::String(name.clone()))
+            .map_err(|err| error_at(source, &name, format!("invalid value: {err}")))?;
+        let subgraph = metadata.remove("subgraph");
+        let mut entry = NodeEntry {
+            id: node_id,
+            value,
+            components: import_components(node.label, metadata),
+        };
+        if let Some(inner) = subgraph {
+            let inner: JgfGraph = serde_json::from_value(inner)
+                .map_err(|err| error_at(source, &name, format!("invalid subgraph: {err}")))?;
+            entry.add_component(Subgraph {
+                graph: Box::new(import_graph::<N, E>(inner, source)?),
+            })?;
+        }
+        graph.insert_node_entry(entry);
+        ids.insert(name, node_id);
+    }
+
+    for edge in jgf.edges {
+        let endpoint = |name: &str| {
+            ids.get(name).copied().ok_or_else(|| {
+                error_at(
+                    source,
+                    name,
+                    format!("`{name}` is not a node of this graph"),
+                )
+            })
+        };
+        let (source_id, target_id) = (endpoint(&edge.source)?, endpoint(&edge.target)?);
+        let edge_id = edge
+            .id
+            .as_deref()
+            .and_then(|id| id.parse().ok())
+            .unwrap_or_default();
+        if graph.get_edge(edge_id).is_some() {
+            let id = edge_id.to_string();
+            return Err(error_at(source, &id, format!("duplicate edge id `{id}`")));
+        }
+
+        let mut metadata = edge.metadata;
+        let fallback = Value::String(edge.relation.clone().unwrap_or_default());
+        let value = import_value(metadata.remove("value"), fallback).map_err(|err| {
+            let at = edge.id.as_deref().unwrap_or(&edge.source);
+            error_at(source, at, format!("invalid edge value: {err}"))
+        })?;
+        if let Some(relation) = edge.relation {
+            metadata.insert("relation".to_string(), relation.into());
+        }
+        graph.insert_edge_entry(EdgeEntry {
+            id: edge_id,
+            source: source_id,
+            target: target_id,
+            value,
+            components: import_components(edge.label, metadata),
+        })?;
+    }
+    Ok(graph)
+}
+
+fn import_value<T: DeserializeOwned>(
+    value: Option<Value>,
+    fallback: Value,
+) -> Result<T, serde_json::Error> {
+    match value {
+        Some(value) => serde_json::from_value(value),
+        None => serde_json::from_value(Value::Null).or_else(|_| serde_json::from_value(fallback)),
+    }
+}
+
+fn import_components(label: Option<String>, mut map: Map<String, Value>) -> ComponentStorage {
+    let mut components = ComponentStorage::new();
+    if let Some(label) = label {
+        let _ = components.add(Label(label));
+    }
+
+    let mut metadata = Metadata::default();
+    if let Some(Value::String(description)) = map.remove("description") {
+        metadata.description = Some(description);
+    }
+    if let Some(tags) = map.remove("tags") {
+        metadata.tags = serde_json::from_value(tags).unwrap_or_default();
+    }
+    metadata.properties = map;
+    if metadata != Metadata::default() {
+        let _ = components.add(metadata);
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_jgf_round_trip_preserves_order_and_ids() {
+        let mut graph = ContextGraph::<String, f64>::new("Partners");
+        let ids: Vec<NodeId> = ["zeta", "alpha", "mid"]
+            .into_iter()
+            .map(|name| graph.add_node(name.to_string()))
+            .collect();
+        let mut metadata = Metadata {
+            tags: vec!["vip".to_string()],
+            ..Default::default()
+        };
+        metadata
+            .properties
+            .insert("region".to_string(), serde_json::json!({"code": "EU"}));
+        let node = graph.get_node_mut(ids[1]).unwrap();
+        node.add_component(Label("Alpha Corp".to_string())).unwrap();
+        node.add_component(metadata.clone()).unwrap();
+        let edge = graph.add_edge(ids[0], ids[1], 0.5).unwrap();
+
+        let json = graph.to_jgf().unwrap();
+        let parsed = ContextGraph::<String, f64>::from_jgf(&json).unwrap();
+
+        assert_eq!(parsed.id, graph.id);
+        let order: Vec<NodeId> = parsed.get_all_nodes().map(|(id, _)| id).collect();
+        assert_eq!(order, ids);
+        let alpha = parsed.get_node(ids[1]).unwrap();
+        assert_eq!(alpha.get_component::<Label>().unwrap().0, "Alpha Corp");
+        assert_eq!(alpha.get_component::<Metadata>(), Some(&metadata));
+        assert_eq!(parsed.get_edge(edge).unwrap().value, 0.5);
+    }
+
+    #[test]
+    fn test_jgf_v1_list_and_errors() {
+        let json = r#"{"graph": {
+  "nodes": [{"id": "a", "label": "A"}, {"id": "b"}],
+  "edges": [{"source": "a", "target": "b", "relation": "knows"}]
+}}"#;
+        let graph = ContextGraph::<String, String>::from_jgf(json).unwrap();
+        assert_eq!(graph.node_count(), 2);
+        let (_, edge) = graph.get_all_edges().next().unwrap();
+        assert_eq!(edge.value, "knows");
+
+        let broken = json.replace("\"target\": \"b\"", "\"target\": \"c\"");
+        let err = ContextGraph::<String, String>::from_jgf(&broken).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                GraphError::Parse {
+                    line: 3,
+                    column: 39,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+
+        let err =
+            ContextGraph::<String, String>::from_jgf("{\"graph\": {\"edges\": [{}]}}").unwrap_err();
+        assert!(err.to_string().contains("missing field `source`"), "{err}");
+    }
+}
@@ -0,0 +1,464 @@
+//! Spatial positions and layout algorithms for visualisation
+//!
+//! A [`Layout`] computes a [`Position3D`] for every node of a
+//! [`ContextGraph`]; [`ContextGraph::apply_layout`] stores the result as
+//! components. All layouts are deterministic: the same graph, options and
+//! seed always produce the same positions.
+
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use nalgebra::{Point3, Vector3};
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::f32::consts::TAU;
+use std::fmt::Debug;
+
+/// Position of a node in 3D space
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Position3D(pub Point3<f32>);
+
+impl Position3D {
+    pub fn new(x: f32, y: f32, z: f32) -> Self {
+        Self(Point3::new(x, y, z))
+    }
+
+    /// The position as a plain tuple, as carried by domain events
+    pub fn to_tuple(&self) -> (f32, f32, f32) {
+        (self.0.x, self.0.y, self.0.z)
+    }
+}
+
+impl From<(f32, f32, f32)> for Position3D {
+    fn from((x, y, z): (f32, f32, f32)) -> Self {
+        Self::new(x, y, z)
+    }
+}
+
+impl Component for Position3D {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(*self)
+    }
+    fn type_name(&self) -> &'static str {
+        "Position3D"
+    }
+}
+
+/// Positions computed by a layout, keyed by node
+pub type Positions = HashMap<NodeId, Point3<f32>>;
+
+/// A layout algorithm assigning a position to every node
+pub trait Layout {
+    fn compute<N, E>(&self, graph: &ContextGraph<N, E>) -> GraphResult<Positions>
+    where
+        N: Clone + Debug,
+        E: Clone + Debug;
+}
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug,
+    E: Clone + Debug,
+{
+    /// Run a layout and store the result as Position3D components
+    ///
+    /// Existing Position3D components are replaced.
+    pub fn apply_layout(&mut self, layout: &impl Layout) -> GraphResult<()> {
+        for (node_id, position) in layout.compute(self)? {
+            if let Some(node) = self.get_node_mut(node_id) {
+                node.components.remove::<Position3D>();
+                node.add_component(Position3D(position))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Get a node's position, if it has one
+    pub fn node_position(&self, node_id: NodeId) -> Option<Point3<f32>> {
+        self.get_node(node_id)
+            .and_then(|node| node.get_component::<Position3D>())
+            .map(|position| position.0)
+    }
+}
+
+/// Nodes evenly spaced on a circle in the XY plane
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircularLayout {
+    pub radius: f32,
+    pub center: Point3<f32>,
+}
+
+impl Default for CircularLayout {
+    fn default() -> Self {
+        Self {
+            radius: 10.0,
+            center: Point3::origin(),
+        }
+    }
+}
+
+impl Layout for CircularLayout {
+    fn compute<N, E>(&self, graph: &ContextGraph<N, E>) -> GraphResult<Positions>
+    where
+        N: Clone + Debug,
+        E: Clone + Debug,
+    {
+        let count = graph.node_count().max(1) as f32;
+        Ok(graph
+            .get_all_nodes()
+            .enumerate()
+            .map(|(i, (id, _))| {
+                let angle = TAU * i as f32 / count;
+                let offset = Vector3::new(angle.cos(), angle.sin(), 0.0) * self.radius;
+                (id, self.center + offset)
+            })
+            .collect())
+    }
+}
+
+/// Concentric rings around a root, one ring per hop (edges in either direction)
+///
+/// Nodes not connected to the root are placed on an extra outer ring.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RadialLayout {
+    pub root: NodeId,
+    pub ring_spacing: f32,
+}
+
+impl RadialLayout {
+    pub fn new(root: NodeId) -> Self {
+        Self {
+            root,
+            ring_spacing: 5.0,
+        }
+    }
+}
+
+impl Layout for RadialLayout {
+    fn compute<N, E>(&self, graph: &ContextGraph<N, E>) -> GraphResult<Positions>
+    where
+        N: Clone + Debug,
+        E: Clone + Debug,
+    {
+        if graph.get_node(self.root).is_none() {
+            return Err(GraphError::NodeNotFound(self.root));
+        }
+
+        // Breadth-first rings keep each node near its parent's angular slot
+        let mut depth = HashMap::from([(self.root, 0usize)]);
+        let mut rings: Vec<Vec<NodeId>> = vec![vec![self.root]];
+        let mut queue = VecDeque::from([self.root]);
+        while let Some(node_id) = queue.pop_front() {
+            let next = depth[&node_id] + 1;
+            for neighbor in graph.neighbors(node_id) {
+                if let Entry::Vacant(slot) = depth.entry(neighbor) {
+                    slot.insert(next);
+                    if rings.len() <= next {
+                        rings.push(Vec::new());
+                    }
+                    rings[next].push(neighbor);
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+        let unreached: Vec<NodeId> = graph
+            .get_all_nodes()
+            .map(|(id, _)| id)
+            .filter(|id| !depth.contains_key(id))
+            .collect();
+        if !unreached.is_empty() {
+            rings.push(unreached);
+        }
+
+        let mut positions = Positions::new();
+        for (ring, nodes) in rings.iter().enumerate() {
+            let radius = ring as f32 * self.ring_spacing;
+            for (i, node_id) in nodes.iter().enumerate() {
+                let angle = TAU * i as f32 / nodes.len() as f32;
+                positions.insert(
+                    *node_id,
+                    Point3::new(radius * angle.cos(), radius * angle.sin(), 0.0),
+                );
+            }
+        }
+        Ok(positions)
+    }
+}
+
+/// Layered layout for directed acyclic graphs
+///
+/// Each node sits on the layer given by the longest path reaching it from a
+/// source, layers going down the Y axis. Within a layer nodes are ordered by
+/// the average position of their predecessors to reduce crossings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HierarchicalLayout {
+    pub layer_spacing: f32,
+    pub node_spacing: f32,
+}
+
+impl Default for HierarchicalLayout {
+    fn default() -> Self {
+        Self {
+            layer_spacing: 5.0,
+            node_spacing: 3.0,
+        }
+    }
+}
+
+impl Layout for HierarchicalLayout {
+    fn compute<N, E>(&self, graph: &ContextGraph<N, E>) -> GraphResult<Positions>
+    where
+        N: Clone + Debug,
+        E: Clone + Debug,
+    {
+        let order = graph.topological_sort()?;
+
+        let mut layer_of: HashMap<NodeId, usize> = HashMap::new();
+        for node_id in &order {
+            let layer = graph
+                .predecessors(*node_id)
+                .iter()
+                .filter_map(|p| layer_of.get(p))
+                .map(|l| l + 1)
+                .max()
+                .unwrap_or(0);
+            layer_of.insert(*node_id, layer);
+        }
+
+        // Stable starting order within layers follows node storage order
+        let mut layers: Vec<Vec<NodeId>> = Vec::new();
+        for (node_id, _) in graph.get_all_nodes() {
+            let layer = layer_of[&node_id];
+            if layers.len() <= layer {
+                layers.resize_with(layer + 1, Vec::new);
+            }
+            layers[layer].push(node_id);
+        }
+
+        // One downward barycenter pass
+        let mut slot: HashMap<NodeId, f32> = HashMap::new();
+        for nodes in layers.iter_mut() {
+            let keyed: Vec<(NodeId, f32)> = nodes
+                .iter()
+                .enumerate()
+                .map(|(i, id)| {
+                    let parents: Vec<f32> = graph
+                        .predecessors(*id)
+                        .iter()
+                        .filter_map(|p| slot.get(p).copied())
+                        .collect();
+                    let key = if parents.is_empty() {
+                        i as f32
+                    } else {
+                        parents.iter().sum::<f32>() / parents.len() as f32
+                    };
+                    (*id, key)
+                })
+                .collect();
+            let mut sorted = keyed;
+            sorted.sort_by(|a, b| a.1.total_cmp(&b.1));
+            *nodes = sorted.into_iter().map(|(id, _)| id).collect();
+            for (i, id) in nodes.iter().enumerate() {
+                slot.insert(*id, i as f32);
+            }
+        }
+
+        let mut positions = Positions::new();
+        for (layer, nodes) in layers.iter().enumerate() {
+            let width = (nodes.len().saturating_sub(1)) as f32 * self.node_spacing;
+            for (i, node_id) in nodes.iter().enumerate() {
+                let x = i as f32 * self.node_spacing - width / 2.0;
+                let y = -(layer as f32) * self.layer_spacing;
+                positions.insert(*node_id, Point3::new(x, y, 0.0));
+            }
+        }
+        Ok(positions)
+    }
+}
+
+/// Fruchterman-Reingold force-directed layout
+///
+/// Connected nodes attract, all nodes repel, and movement is limited by a
+/// temperature that cools every iteration. Starting positions are drawn from
+/// a generator seeded with `seed`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ForceDirectedLayout {
+    pub iterations: usize,
+    pub seed: u64,
+    /// Preferred distance between connected nodes
+    pub spring_length: f32,
+    /// Lay out in 3D rather than the XY plane
+    pub three_dimensional: bool,
+}
+
+impl Default for ForceDirectedLayout {
+    fn default() -> Self {
+        Self {
+            iterations: 200,
+            seed: 0,
+            spring_length: 3.0,
+            three_dimensional: false,
+        }
+    }
+}
+
+impl ForceDirectedLayout {
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            seed,
+            ..Self::default()
+        }
+    }
+}
+
+impl Layout for ForceDirectedLayout {
+    fn compute<N, E>(&self, graph: &ContextGraph<N, E>) -> GraphResult<Positions>
+    where
+        N: Clone + Debug,
+        E: Clone + Debug,
+    {
+        let nodes: Vec<NodeId> = graph.get_all_nodes().map(|(id, _)| id).collect();
+        let index: HashMap<NodeId, usize> =
+            nodes.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+        let edges: Vec<(usize, usize)> = graph
+            .get_all_edges()
+            .map(|(_, edge)| (index[&edge.source], index[&edge.target]))
+            .filter(|(a, b)| a != b)
+            .collect();
+
+        let k = self.spring_length;
+        let extent = k * (nodes.len() as f32).sqrt().max(1.0);
+        let mut rng = SplitMix64(self.seed);
+        let mut positions: Vec<Vector3<f32>> = nodes
+            .iter()
+            .map(|_| {
+                let z = if self.three_dimensional {
+                    rng.next_f32() - 0.5
+                } else {
+                    0.0
+                };
+                Vector3::new(rng.next_f32() - 0.5, rng.next_f32() - 0.5, z) * extent
+            })
+            .collect();
+
+        let mut temperature = extent / 10.0;
+        let cooling = temperature / (self.iterations.max(1) as f32 + 1.0);
+
+        for _ in 0..self.iterations {
+            let mut displacement = vec![Vector3::zeros(); nodes.len()];
+
+            for i in 0..nodes.len() {
+                for j in (i + 1)..nodes.len() {
+                    let delta = positions[i] - positions[j];
+                    let distance = delta.norm().max(0.01);
+                    let push = delta / distance * (k * k / distance);
+                    displacement[i] += push;
+                    displacement[j] -= push;
+                }
+            }
+
+            for (a, b) in &edges {
+                let delta = positions[*a] - positions[*b];
+                let distance = delta.norm().max(0.01);
+                let pull = delta / distance * (distance * distance / k);
+                displacement[*a] -= pull;
+                displacement[*b] += pull;
+            }
+
+            for (position, moved) in positions.iter_mut().zip(displacement) {
+                let length = moved.norm();
+                if length > 0.0 {
+                    *position += moved / length * length.min(temperature);
+                }
+            }
+            temperature = (temperature - cooling).max(0.0);
+        }
+
+        Ok(nodes
+            .into_iter()
+            .zip(positions)
+            .map(|(id, v)| (id, Point3::from(v)))
+            .collect())
+    }
+}
+
+/// Small deterministic generator for layout seeding
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform value in `[0, 1)`
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain() -> (ContextGraph<&'static str, ()>, Vec<NodeId>) {
+        let mut graph = ContextGraph::new("Layout");
+        let ids: Vec<NodeId> = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|name| graph.add_node(name))
+            .collect();
+        graph.add_edge(ids[0], ids[1], ()).unwrap();
+        graph.add_edge(ids[0], ids[2], ()).unwrap();
+        graph.add_edge(ids[1], ids[3], ()).unwrap();
+        (graph, ids)
+    }
+
+    #[test]
+    fn test_force_directed_is_deterministic() {
+        let (mut graph, ids) = chain();
+        let layout = ForceDirectedLayout::with_seed(42);
+
+        let first = layout.compute(&graph).unwrap();
+        let second = layout.compute(&graph).unwrap();
+        assert_eq!(first, second);
+        assert_ne!(
+            first,
+            ForceDirectedLayout::with_seed(7).compute(&graph).unwrap()
+        );
+
+        graph.apply_layout(&layout).unwrap();
+        graph.apply_layout(&layout).unwrap();
+        assert_eq!(graph.node_position(ids[0]), Some(first[&ids[0]]));
+        assert!(first.values().all(|p| p.z == 0.0));
+    }
+
+    #[test]
+    fn test_structured_layouts() {
+        let (graph, ids) = chain();
+
+        let layered = HierarchicalLayout::default().compute(&graph).unwrap();
+        assert_eq!(layered[&ids[0]].y, 0.0);
+        assert_eq!(layered[&ids[1]].y, layered[&ids[2]].y);
+        assert!(layered[&ids[3]].y < layered[&ids[1]].y);
+
+        let circle = CircularLayout::default().compute(&graph).unwrap();
+        assert!(circle
+            .values()
+            .all(|p| (p.coords.norm() - 10.0).abs() < 1e-4));
+
+        let radial = RadialLayout::new(ids[1]).compute(&graph).unwrap();
+        assert_eq!(radial[&ids[1]], Point3::origin());
+        assert!((radial[&ids[2]].coords.norm() - 10.0).abs() < 1e-4);
+
+        let mut cyclic = graph.clone();
+        cyclic.add_edge(ids[3], ids[0], ()).unwrap();
+        assert!(HierarchicalLayout::default().compute(&cyclic).is_err());
+    }
+}
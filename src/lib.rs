@@ -35,19 +35,78 @@
 //!     .add_component(Label("Greeting".to_string()));
 //! ```
 
+pub mod analytics;
+pub mod binary;
+pub mod builder;
 pub mod composition;
+pub mod conceptual_space;
+pub mod concurrent;
 pub mod context_graph;
+pub mod diff;
+pub mod dot;
+pub mod graphml;
+pub mod hierarchy;
+pub mod history;
+pub mod ids;
 pub mod invariants;
+pub mod jgf;
+pub mod layout;
+pub mod merge;
+pub mod mermaid;
+pub mod persistent;
+pub mod query;
+pub mod rdf;
+pub mod registry;
+pub mod replicated;
+pub mod snapshot;
+pub mod temporal;
+pub mod traversal;
 pub mod types;
+pub mod view;
 
 // TODO: These modules will be implemented next
 // pub mod morphisms;
 
 // Re-export core types
+pub use binary::{BinaryValue, ComponentCodecs};
+pub use builder::{BuildReport, BuildViolation, GraphBuilder};
 pub use composition::{compose, intersection, product, union};
+pub use conceptual_space::{
+    ConceptPoint, ConceptualSpace, ConvexRegion, DimensionScale, DistanceMetric, QualityDimension,
+};
+pub use concurrent::{GraphReader, GraphSnapshot, GraphWriter};
 pub use context_graph::{ContextGraph, GraphInvariant};
+pub use diff::{
+    diff, diff_with, Change, ChangeTarget, ComponentChange, ComponentValue, Conflict, EdgeChange,
+    GraphDiff, NodeChange,
+};
+pub use dot::{DisplayLabels, DotAttributes, DotStyle};
+pub use hierarchy::{
+    ContainerRewiring, FlattenOptions, HierarchyContext, HierarchyStep, WalkControl, WalkOptions,
+};
+pub use history::{VersionInfo, VersionedGraph};
+pub use ids::{IdGenerator, RandomIds, SeededIds, TimeOrderedIds};
 pub use invariants::{Acyclic, Connected};
+pub use layout::{
+    CircularLayout, ForceDirectedLayout, HierarchicalLayout, Layout, Position3D, Positions,
+    RadialLayout,
+};
+pub use merge::{
+    merge, merge_with, ConflictKind, ConflictResolver, MergeConflict, MergeReport, MergeStrategy,
+    Outcome, Resolution, Side,
+};
+pub use persistent::PersistentGraph;
+pub use query::{EdgeQuery, NodeQuery};
+pub use rdf::RdfOptions;
+pub use registry::{
+    CrossGraphEdge, DanglingReference, GraphMut, GraphRegistry, QualifiedNodeId, ReferentialAction,
+};
+pub use replicated::{GraphOp, ReplicaId, ReplicatedGraph, Stamp};
+pub use snapshot::MappedGraph;
+pub use temporal::{TemporalSnapshot, TemporalStep, ValidityInterval};
+pub use traversal::{Bfs, Dfs, DfsPostOrder, Topological, Traversal, TraversalDirection, Visit};
 pub use types::{
     Component, ComponentStorage, ConceptGraphId, ContextGraphId, EdgeEntry, EdgeId, GraphError,
-    GraphReference, GraphResult, Label, Metadata, NodeEntry, NodeId, Subgraph,
+    GraphReference, GraphResult, Label, Metadata, NodeEntry, NodeId, Provenance, Subgraph,
 };
+pub use view::GraphView;
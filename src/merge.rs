@@ -0,0 +1,863 @@
+//! Three-way merge of divergent graph versions
+//!
+//! [`merge`] combines two branches, `ours` and `theirs`, that both started
+//! from a common `base`. Changes made on one branch only are taken as they
+//! are; values, components, endpoints or metadata changed differently on
+//! both branches are conflicts, settled by a [`ConflictResolver`]. The
+//! built-in [`MergeStrategy`] covers the usual policies, and every conflict
+//! is recorded in the [`MergeReport`].
+//!
+//! As in [`diff`](crate::diff()), components are compared through their
+//! JSON encoding; components without a codec are carried over from `ours`
+//! (or from `theirs` for elements only it has) without being merged.
+
+use crate::binary::ComponentCodecs;
+use crate::context_graph::ContextGraph;
+use crate::diff::{diff_with, encode, ChangeTarget, EdgeChange, Encoded, NodeChange};
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::{self, Debug};
+use std::str::FromStr;
+
+/// A branch of a three-way merge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Side {
+    Ours,
+    Theirs,
+}
+
+/// What both branches disagree about
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConflictKind {
+    /// The node or edge value
+    Value,
+    /// A component, by registered name
+    Component(String),
+    /// One branch removed an element the other modified
+    RemovedAndModified { removed_by: Side },
+    /// An edge was reconnected differently
+    Endpoints,
+    /// An edge survives but one of its nodes does not; resolving drops it
+    MissingEndpoint,
+    /// The graph metadata
+    Metadata,
+}
+
+/// Result of asking a resolver about a conflict
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution<T> {
+    Resolved(T),
+    Unresolved,
+}
+
+/// How a conflict was settled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Outcome {
+    Ours,
+    Theirs,
+    /// The resolver produced a value neither branch had
+    Custom,
+    /// An edge was dropped because an endpoint was removed
+    Dropped,
+}
+
+/// A conflict met during a merge and how it was settled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeConflict {
+    pub target: ChangeTarget,
+    pub kind: ConflictKind,
+    pub outcome: Outcome,
+}
+
+/// What a merge changed relative to the base, and which conflicts it settled
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeReport {
+    /// Resolver name, e.g. `"Union"`
+    pub strategy: String,
+    pub conflicts: Vec<MergeConflict>,
+    pub nodes_added: usize,
+    pub nodes_removed: usize,
+    pub nodes_modified: usize,
+    pub edges_added: usize,
+    pub edges_removed: usize,
+    pub edges_modified: usize,
+}
+
+/// Settles conflicts between the two branches of a merge
+///
+/// Every method defaults to [`Resolution::Unresolved`], which fails the
+/// merge, so a custom resolver only implements the cases it handles.
+/// Components are given in their JSON encoding; `None` means the branch
+/// does not have the component, and resolving to `None` drops it.
+pub trait ConflictResolver<N, E> {
+    /// Name recorded in the merge report
+    fn name(&self) -> String {
+        "Custom".to_string()
+    }
+
+    fn node_value(&self, _id: NodeId, _base: Option<&N>, _ours: &N, _theirs: &N) -> Resolution<N> {
+        Resolution::Unresolved
+    }
+
+    fn edge_value(&self, _id: EdgeId, _base: Option<&E>, _ours: &E, _theirs: &E) -> Resolution<E> {
+        Resolution::Unresolved
+    }
+
+    fn component(
+        &self,
+        _target: ChangeTarget,
+        _name: &str,
+        _base: Option<&Value>,
+        _ours: Option<&Value>,
+        _theirs: Option<&Value>,
+    ) -> Resolution<Option<Value>> {
+        Resolution::Unresolved
+    }
+
+    /// Pick the branch whose version wins for removal, endpoint, dangling
+    /// edge and metadata conflicts
+    fn structure(&self, _target: ChangeTarget, _kind: &ConflictKind) -> Resolution<Side> {
+        Resolution::Unresolved
+    }
+}
+
+/// Built-in conflict policies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MergeStrategy {
+    /// Our branch wins every conflict
+    Ours,
+    /// Their branch wins every conflict
+    Theirs,
+    /// Keep whatever either branch has: modifications survive removals,
+    /// components present on one branch survive, and our branch wins
+    /// conflicting values
+    Union,
+    /// Any conflict fails the merge
+    FailOnConflict,
+}
+
+impl fmt::Display for MergeStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            MergeStrategy::Ours => "Ours",
+            MergeStrategy::Theirs => "Theirs",
+            MergeStrategy::Union => "Union",
+            MergeStrategy::FailOnConflict => "FailOnConflict",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Parses names case-insensitively, ignoring `-` and `_`, so `"Union"`,
+/// `"theirs"` and `"fail-on-conflict"` (or just `"fail"`) are accepted
+impl FromStr for MergeStrategy {
+    type Err = GraphError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name: String = s
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_'))
+            .collect::<String>()
+            .to_lowercase();
+        match name.as_str() {
+            "ours" => Ok(MergeStrategy::Ours),
+            "theirs" => Ok(MergeStrategy::Theirs),
+            "union" => Ok(MergeStrategy::Union),
+            "fail" | "failonconflict" => Ok(MergeStrategy::FailOnConflict),
+            _ => Err(GraphError::InvalidOperation(format!(
+                "unknown merge strategy: {s}"
+            ))),
+        }
+    }
+}
+
+impl MergeStrategy {
+    fn pick<T: Clone>(&self, ours: &T, theirs: &T) -> Resolution<T> {
+        match self {
+            MergeStrategy::Ours | MergeStrategy::Union => Resolution::Resolved(ours.clone()),
+            MergeStrategy::Theirs => Resolution::Resolved(theirs.clone()),
+            MergeStrategy::FailOnConflict => Resolution::Unresolved,
+        }
+    }
+}
+
+impl<N: Clone, E: Clone> ConflictResolver<N, E> for MergeStrategy {
+    fn name(&self) -> String {
+        self.to_string()
+    }
+
+    fn node_value(&self, _id: NodeId, _base: Option<&N>, ours: &N, theirs: &N) -> Resolution<N> {
+        self.pick(ours, theirs)
+    }
+
+    fn edge_value(&self, _id: EdgeId, _base: Option<&E>, ours: &E, theirs: &E) -> Resolution<E> {
+        self.pick(ours, theirs)
+    }
+
+    fn component(
+        &self,
+        _target: ChangeTarget,
+        _name: &str,
+        _base: Option<&Value>,
+        ours: Option<&Value>,
+        theirs: Option<&Value>,
+    ) -> Resolution<Option<Value>> {
+        match self {
+            MergeStrategy::Union => Resolution::Resolved(ours.or(theirs).cloned()),
+            _ => self.pick(&ours.cloned(), &theirs.cloned()),
+        }
+    }
+
+    fn structure(&self, _target: ChangeTarget, kind: &ConflictKind) -> Resolution<Side> {
+        match (self, kind) {
+            (
+                MergeStrategy::Union,
+                ConflictKind::RemovedAndModified {
+                    removed_by: Side::Ours,
+                },
+            ) => Resolution::Resolved(Side::Theirs),
+            _ => self.pick(&Side::Ours, &Side::Theirs),
+        }
+    }
+}
+
+/// Merge two branches of `base` with a built-in strategy
+pub fn merge<N, E>(
+    base: &ContextGraph<N, E>,
+    ours: &ContextGraph<N, E>,
+    theirs: &ContextGraph<N, E>,
+    strategy: MergeStrategy,
+) -> GraphResult<(ContextGraph<N, E>, MergeReport)>
+where
+    N: Clone + Debug + PartialEq + 'static + Send + Sync,
+    E: Clone + Debug + PartialEq + 'static + Send + Sync,
+{
+    merge_with(base, ours, theirs, &strategy, &ComponentCodecs::default())
+}
+
+/// Merge two branches of `base`, settling conflicts with `resolver`
+///
+/// The merged graph keeps our graph id, invariants and ID generator. Fails
+/// with [`GraphError::Conflict`] listing every conflict the resolver left
+/// unresolved, or if the merged graph breaks one of our invariants.
+pub fn merge_with<N, E>(
+    base: &ContextGraph<N, E>,
+    ours: &ContextGraph<N, E>,
+    theirs: &ContextGraph<N, E>,
+    resolver: &impl ConflictResolver<N, E>,
+    codecs: &ComponentCodecs,
+) -> GraphResult<(ContextGraph<N, E>, MergeReport)>
+where
+    N: Clone + Debug + PartialEq + 'static + Send + Sync,
+    E: Clone + Debug + PartialEq + 'static + Send + Sync,
+{
+    let mut merger = Merger {
+        resolver,
+        codecs,
+        conflicts: Vec::new(),
+        unresolved: Vec::new(),
+    };
+    let mut merged = ours.empty_like();
+    merged.metadata = merger
+        .metadata(base, ours, theirs)
+        .unwrap_or_else(|| ours.metadata.clone());
+
+    let node_ids = ordered(
+        [base, ours, theirs]
+            .iter()
+            .flat_map(|graph| graph.get_all_nodes().map(|(id, _)| id)),
+    );
+    for id in node_ids {
+        let sides = [base, ours, theirs].map(|graph| graph.get_node(id));
+        if let Some(node) = merger.node(id, sides)? {
+            merged.insert_node_entry(node);
+        }
+    }
+
+    let edge_ids = ordered(
+        [base, ours, theirs]
+            .iter()
+            .flat_map(|graph| graph.get_all_edges().map(|(id, _)| id)),
+    );
+    for id in edge_ids {
+        let sides = [base, ours, theirs].map(|graph| graph.get_edge(id));
+        let Some(edge) = merger.edge(id, sides)? else {
+            continue;
+        };
+        if merged.get_node(edge.source).is_some() && merged.get_node(edge.target).is_some() {
+            merged.insert_edge_entry(edge)?;
+        } else {
+            let target = ChangeTarget::Edge(id);
+            let kind = ConflictKind::MissingEndpoint;
+            if let Resolution::Resolved(_) = resolver.structure(target, &kind) {
+                merger.record(target, kind, Outcome::Dropped);
+            } else {
+                merger.unresolved.push((target, kind));
+            }
+        }
+    }
+
+    if !merger.unresolved.is_empty() {
+        let messages: Vec<String> = merger
+            .unresolved
+            .iter()
+            .map(|(target, kind)| format!("{target}: unresolved {kind:?} conflict"))
+            .collect();
+        return Err(GraphError::Conflict(messages.join("; ")));
+    }
+    if let Err(error) = merged.check_invariants() {
+        return Err(GraphError::Conflict(format!(
+            "merged graph breaks an invariant: {error}"
+        )));
+    }
+
+    let mut report = MergeReport {
+        strategy: resolver.name(),
+        conflicts: merger.conflicts,
+        nodes_added: 0,
+        nodes_removed: 0,
+        nodes_modified: 0,
+        edges_added: 0,
+        edges_removed: 0,
+        edges_modified: 0,
+    };
+    let changes = diff_with(base, &merged, codecs)?;
+    for change in &changes.nodes {
+        match change {
+            NodeChange::Added { .. } => report.nodes_added += 1,
+            NodeChange::Removed { .. } => report.nodes_removed += 1,
+            NodeChange::Modified { .. } => report.nodes_modified += 1,
+        }
+    }
+    for change in &changes.edges {
+        match change {
+            EdgeChange::Added { .. } => report.edges_added += 1,
+            EdgeChange::Removed { .. } => report.edges_removed += 1,
+            EdgeChange::Modified { .. } => report.edges_modified += 1,
+        }
+    }
+    Ok((merged, report))
+}
+
+/// Ids in first-seen order without repeats
+fn ordered<T: Copy + Eq + std::hash::Hash>(ids: impl Iterator<Item = T>) -> Vec<T> {
+    let mut seen = HashSet::new();
+    ids.filter(|id| seen.insert(*id)).collect()
+}
+
+/// The agreed outcome of a three-way comparison, or `None` on conflict
+fn three_way<T: PartialEq + Clone>(
+    base: Option<&T>,
+    ours: Option<&T>,
+    theirs: Option<&T>,
+) -> Option<Option<T>> {
+    if ours == theirs || theirs == base {
+        Some(ours.cloned())
+    } else if ours == base {
+        Some(theirs.cloned())
+    } else {
+        None
+    }
+}
+
+struct Merger<'a, R> {
+    resolver: &'a R,
+    codecs: &'a ComponentCodecs,
+    conflicts: Vec<MergeConflict>,
+    unresolved: Vec<(ChangeTarget, ConflictKind)>,
+}
+
+/// A node or edge as seen by the merge
+struct Version<'a, V> {
+    value: &'a V,
+    components: &'a ComponentStorage,
+    encoded: Encoded,
+    endpoints: Option<(NodeId, NodeId)>,
+}
+
+impl<V: PartialEq> Version<'_, V> {
+    fn same_as(&self, other: &Self) -> bool {
+        self.value == other.value
+            && self.encoded == other.encoded
+            && self.endpoints == other.endpoints
+    }
+}
+
+impl<R> Merger<'_, R> {
+    fn record(&mut self, target: ChangeTarget, kind: ConflictKind, outcome: Outcome) {
+        self.conflicts.push(MergeConflict {
+            target,
+            kind,
+            outcome,
+        });
+    }
+
+    /// Settle a value conflict, recording whose value won
+    fn settle<T: Clone + PartialEq>(
+        &mut self,
+        target: ChangeTarget,
+        kind: ConflictKind,
+        ours: &T,
+        theirs: &T,
+        resolution: Resolution<T>,
+    ) -> T {
+        match resolution {
+            Resolution::Resolved(value) => {
+                let outcome = if value == *ours {
+                    Outcome::Ours
+                } else if value == *theirs {
+                    Outcome::Theirs
+                } else {
+                    Outcome::Custom
+                };
+                self.record(target, kind, outcome);
+                value
+            }
+            Resolution::Unresolved => {
+                self.unresolved.push((target, kind));
+                ours.clone()
+            }
+        }
+    }
+
+    /// Settle a structural conflict, returning the winning side
+    fn side<N, E>(&mut self, target: ChangeTarget, kind: ConflictKind) -> Side
+    where
+        R: ConflictResolver<N, E>,
+    {
+        match self.resolver.structure(target, &kind) {
+            Resolution::Resolved(side) => {
+                let outcome = match side {
+                    Side::Ours => Outcome::Ours,
+                    Side::Theirs => Outcome::Theirs,
+                };
+                self.record(target, kind, outcome);
+                side
+            }
+            Resolution::Unresolved => {
+                self.unresolved.push((target, kind));
+                Side::Ours
+            }
+        }
+    }
+
+    fn metadata<N, E>(
+        &mut self,
+        base: &ContextGraph<N, E>,
+        ours: &ContextGraph<N, E>,
+        theirs: &ContextGraph<N, E>,
+    ) -> Option<Metadata>
+    where
+        R: ConflictResolver<N, E>,
+    {
+        match three_way(
+            Some(&base.metadata),
+            Some(&ours.metadata),
+            Some(&theirs.metadata),
+        ) {
+            Some(agreed) => agreed,
+            None => match self.side(ChangeTarget::Metadata, ConflictKind::Metadata) {
+                Side::Ours => Some(ours.metadata.clone()),
+                Side::Theirs => Some(theirs.metadata.clone()),
+            },
+        }
+    }
+
+    /// Merge the components two present versions agree on or resolve
+    fn components<N, E, V>(
+        &mut self,
+        target: ChangeTarget,
+        base: Option<&Version<V>>,
+        ours: &Version<V>,
+        theirs: &Version<V>,
+    ) -> GraphResult<ComponentStorage>
+    where
+        R: ConflictResolver<N, E>,
+    {
+        let names: BTreeSet<&'static str> = [base.map(|b| &b.encoded), Some(&ours.encoded)]
+            .into_iter()
+            .flatten()
+            .chain([&theirs.encoded])
+            .flat_map(|encoded| encoded.keys().copied())
+            .collect();
+
+        let mut storage = ours.components.clone();
+        for name in names {
+            let base_data = base.and_then(|b| b.encoded.get(name));
+            let (our_data, their_data) = (ours.encoded.get(name), theirs.encoded.get(name));
+            let data = match three_way(base_data, our_data, their_data) {
+                Some(agreed) => agreed,
+                None => {
+                    let resolution = self
+                        .resolver
+                        .component(target, name, base_data, our_data, their_data);
+                    self.settle(
+                        target,
+                        ConflictKind::Component(name.to_string()),
+                        &our_data.cloned(),
+                        &their_data.cloned(),
+                        resolution,
+                    )
+                }
+            };
+            if our_data == data.as_ref() {
+                continue;
+            }
+            let type_id = self
+                .codecs
+                .type_id_of(name)
+                .expect("encoded components have codecs");
+            storage.remove_type(&type_id);
+            if let Some(data) = data {
+                storage.add_boxed(self.codecs.decode_json(name, &data)?)?;
+            }
+        }
+        Ok(storage)
+    }
+
+    /// Decide which single version survives when a branch lacks the element
+    fn presence<'v, N, E, V: PartialEq>(
+        &mut self,
+        target: ChangeTarget,
+        base: Option<&Version<V>>,
+        ours: Option<&'v Version<'v, V>>,
+        theirs: Option<&'v Version<'v, V>>,
+    ) -> Option<&'v Version<'v, V>>
+    where
+        R: ConflictResolver<N, E>,
+    {
+        let (present, removed_by) = match (ours, theirs) {
+            (Some(ours), None) => (ours, Side::Theirs),
+            (None, Some(theirs)) => (theirs, Side::Ours),
+            _ => return None,
+        };
+        let Some(base) = base else {
+            // Added on one branch only
+            return Some(present);
+        };
+        if present.same_as(base) {
+            return None;
+        }
+        let kind = ConflictKind::RemovedAndModified { removed_by };
+        let keep = self.side(target, kind) != removed_by;
+        keep.then_some(present)
+    }
+
+    fn node<N, E>(
+        &mut self,
+        id: NodeId,
+        [base, ours, theirs]: [Option<&NodeEntry<N>>; 3],
+    ) -> GraphResult<Option<NodeEntry<N>>>
+    where
+        N: Clone + PartialEq,
+        R: ConflictResolver<N, E>,
+    {
+        let [base, ours, theirs] = [base, ours, theirs].map(|entry| {
+            entry.map(|node| {
+                encode(&node.components, self.codecs).map(|encoded| Version {
+                    value: &node.value,
+                    components: &node.components,
+                    encoded,
+                    endpoints: None,
+                })
+            })
+        });
+        let (base, ours, theirs) = (base.transpose()?, ours.transpose()?, theirs.transpose()?);
+        let target = ChangeTarget::Node(id);
+
+        let (Some(our), Some(their)) = (&ours, &theirs) else {
+            let survivor =
+                self.presence::<N, E, N>(target, base.as_ref(), ours.as_ref(), theirs.as_ref());
+            return Ok(survivor.map(|version| NodeEntry {
+                id,
+                value: version.value.clone(),
+                components: version.components.clone(),
+            }));
+        };
+
+        let base_value = base.as_ref().map(|b| b.value);
+        let value = match three_way(base_value, Some(our.value), Some(their.value)) {
+            Some(agreed) => agreed.expect("both branches have the node"),
+            None => {
+                let resolution = self
+                    .resolver
+                    .node_value(id, base_value, our.value, their.value);
+                self.settle(
+                    target,
+                    ConflictKind::Value,
+                    our.value,
+                    their.value,
+                    resolution,
+                )
+            }
+        };
+        let components = self.components::<N, E, N>(target, base.as_ref(), our, their)?;
+        Ok(Some(NodeEntry {
+            id,
+            value,
+            components,
+        }))
+    }
+
+    fn edge<N, E>(
+        &mut self,
+        id: EdgeId,
+        [base, ours, theirs]: [Option<&EdgeEntry<E>>; 3],
+    ) -> GraphResult<Option<EdgeEntry<E>>>
+    where
+        E: Clone + PartialEq,
+        R: ConflictResolver<N, E>,
+    {
+        let [base, ours, theirs] = [base, ours, theirs].map(|entry| {
+            entry.map(|edge| {
+                encode(&edge.components, self.codecs).map(|encoded| Version {
+                    value: &edge.value,
+                    components: &edge.components,
+                    encoded,
+                    endpoints: Some((edge.source, edge.target)),
+                })
+            })
+        });
+        let (base, ours, theirs) = (base.transpose()?, ours.transpose()?, theirs.transpose()?);
+        let target = ChangeTarget::Edge(id);
+        let entry = |(source, target), value, components| EdgeEntry {
+            id,
+            source,
+            target,
+            value,
+            components,
+        };
+
+        let (Some(our), Some(their)) = (&ours, &theirs) else {
+            let survivor =
+                self.presence::<N, E, E>(target, base.as_ref(), ours.as_ref(), theirs.as_ref());
+            return Ok(survivor.map(|version| {
+                entry(
+                    version_endpoints(version),
+                    version.value.clone(),
+                    version.components.clone(),
+                )
+            }));
+        };
+
+        let base_endpoints = base.as_ref().map(version_endpoints);
+        let (our_endpoints, their_endpoints) = (version_endpoints(our), version_endpoints(their));
+        let endpoints = match three_way(
+            base_endpoints.as_ref(),
+            Some(&our_endpoints),
+            Some(&their_endpoints),
+        ) {
+            Some(agreed) => agreed.expect("both branches have the edge"),
+            None => match self.side(target, ConflictKind::Endpoints) {
+                Side::Ours => our_endpoints,
+                Side::Theirs => their_endpoints,
+            },
+        };
+
+        let base_value = base.as_ref().map(|b| b.value);
+        let value = match three_way(base_value, Some(our.value), Some(their.value)) {
+            Some(agreed) => agreed.expect("both branches have the edge"),
+            None => {
+                let resolution = self
+                    .resolver
+                    .edge_value(id, base_value, our.value, their.value);
+                self.settle(
+                    target,
+                    ConflictKind::Value,
+                    our.value,
+                    their.value,
+                    resolution,
+                )
+            }
+        };
+        let components = self.components::<N, E, E>(target, base.as_ref(), our, their)?;
+        Ok(Some(entry(endpoints, value, components)))
+    }
+}
+
+fn version_endpoints<V>(version: &Version<V>) -> (NodeId, NodeId) {
+    version.endpoints.expect("edge versions have endpoints")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context_graph::GraphInvariant;
+
+    struct Branches {
+        base: ContextGraph<String, u32>,
+        ours: ContextGraph<String, u32>,
+        theirs: ContextGraph<String, u32>,
+        a: NodeId,
+        b: NodeId,
+    }
+
+    /// Both branches rename `a`; ours removes `c` while theirs relabels it
+    fn branches() -> Branches {
+        let mut base = ContextGraph::<String, u32>::new("Base");
+        let a = base.add_node("a".to_string());
+        let b = base.add_node("b".to_string());
+        let c = base.add_node("c".to_string());
+        base.add_edge(a, b, 1).unwrap();
+
+        let mut ours = base.clone();
+        ours.get_node_mut(a).unwrap().value = "a-ours".to_string();
+        ours.remove_node(c);
+        let d = ours.add_node("d".to_string());
+        ours.add_edge(b, d, 2).unwrap();
+
+        let mut theirs = base.clone();
+        theirs.get_node_mut(a).unwrap().value = "a-theirs".to_string();
+        theirs
+            .get_node_mut(c)
+            .unwrap()
+            .add_component(Label("kept".to_string()))
+            .unwrap();
+        theirs
+            .get_node_mut(b)
+            .unwrap()
+            .add_component(Label("B".to_string()))
+            .unwrap();
+
+        Branches {
+            base,
+            ours,
+            theirs,
+            a,
+            b,
+        }
+    }
+
+    #[test]
+    fn test_builtin_strategies() {
+        let Branches {
+            base,
+            ours,
+            theirs,
+            a,
+            b,
+        } = branches();
+
+        let (merged, report) = merge(&base, &ours, &theirs, MergeStrategy::Ours).unwrap();
+        assert_eq!(merged.get_node(a).unwrap().value, "a-ours");
+        assert_eq!(merged.node_count(), 3);
+        // Non-conflicting changes from both sides are kept
+        assert!(merged.get_node(b).unwrap().has_component::<Label>());
+        assert_eq!(report.strategy, "Ours");
+        assert_eq!(report.conflicts.len(), 2);
+        assert_eq!((report.nodes_added, report.nodes_removed), (1, 1));
+
+        let (merged, _) = merge(&base, &ours, &theirs, MergeStrategy::Theirs).unwrap();
+        assert_eq!(merged.get_node(a).unwrap().value, "a-theirs");
+        assert_eq!(merged.node_count(), 4);
+
+        let strategy: MergeStrategy = "Union".parse().unwrap();
+        let (merged, report) = merge(&base, &ours, &theirs, strategy).unwrap();
+        assert_eq!(merged.get_node(a).unwrap().value, "a-ours");
+        assert_eq!(merged.node_count(), 4);
+        assert!(report.conflicts.iter().any(|conflict| conflict.kind
+            == ConflictKind::RemovedAndModified {
+                removed_by: Side::Ours
+            }
+            && conflict.outcome == Outcome::Theirs));
+
+        let failed = merge(&base, &ours, &theirs, "fail-on-conflict".parse().unwrap());
+        assert!(matches!(failed, Err(GraphError::Conflict(_))));
+        assert!("rebase".parse::<MergeStrategy>().is_err());
+    }
+
+    /// Each node has at most one outgoing edge
+    #[derive(Clone)]
+    struct SingleSuccessor;
+
+    impl GraphInvariant<String, u32> for SingleSuccessor {
+        fn check(&self, graph: &ContextGraph<String, u32>) -> GraphResult<()> {
+            match graph
+                .get_all_nodes()
+                .find(|(id, _)| graph.outgoing_edges(*id).count() > 1)
+            {
+                Some((id, _)) => Err(GraphError::InvariantViolation(format!(
+                    "node {id} has several successors"
+                ))),
+                None => Ok(()),
+            }
+        }
+
+        fn name(&self) -> &str {
+            "SingleSuccessor"
+        }
+
+        fn clone_box(&self) -> Box<dyn GraphInvariant<String, u32>> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn test_merge_keeps_and_checks_invariants() {
+        let mut base = ContextGraph::<String, u32>::new("Chain");
+        base.invariants.push(Box::new(SingleSuccessor));
+        let a = base.add_node("a".to_string());
+        let b = base.add_node("b".to_string());
+        let c = base.add_node("c".to_string());
+
+        let mut ours = base.clone();
+        ours.add_edge(a, b, 1).unwrap();
+        let (merged, _) = merge(&base, &ours, &base, MergeStrategy::Ours).unwrap();
+        assert_eq!(merged.invariants.len(), 1);
+
+        // Each branch is valid on its own, but together `a` has two successors
+        let mut theirs = base.clone();
+        theirs.add_edge(a, c, 2).unwrap();
+        assert!(matches!(
+            merge(&base, &ours, &theirs, MergeStrategy::Union),
+            Err(GraphError::Conflict(message)) if message.contains("several successors")
+        ));
+    }
+
+    #[test]
+    fn test_custom_resolver() {
+        struct Concatenate;
+        impl ConflictResolver<String, u32> for Concatenate {
+            fn node_value(
+                &self,
+                _id: NodeId,
+                _base: Option<&String>,
+                ours: &String,
+                theirs: &String,
+            ) -> Resolution<String> {
+                Resolution::Resolved(format!("{ours}+{theirs}"))
+            }
+            fn structure(&self, _target: ChangeTarget, _kind: &ConflictKind) -> Resolution<Side> {
+                Resolution::Resolved(Side::Ours)
+            }
+        }
+
+        let Branches {
+            base,
+            ours,
+            theirs,
+            a,
+            ..
+        } = branches();
+        let (merged, report) = merge_with(
+            &base,
+            &ours,
+            &theirs,
+            &Concatenate,
+            &ComponentCodecs::default(),
+        )
+        .unwrap();
+        assert_eq!(merged.get_node(a).unwrap().value, "a-ours+a-theirs");
+        assert_eq!(report.strategy, "Custom");
+        assert_eq!(
+            report.conflicts[0],
+            MergeConflict {
+                target: ChangeTarget::Node(a),
+                kind: ConflictKind::Value,
+                outcome: Outcome::Custom,
+            }
+        );
+    }
+}
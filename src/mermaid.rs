@@ -0,0 +1,740 @@
+//! Mermaid flowchart export and import
+//!
+//! [`ContextGraph::to_mermaid`] renders a graph as a `flowchart`, using
+//! [`Label`] components for captions and nesting [`Subgraph`] components as
+//! Mermaid `subgraph` blocks. [`ContextGraph::from_mermaid`] goes the other
+//! way, so diagrams in documentation can be loaded and checked against code.
+
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Write};
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    /// Render as a Mermaid flowchart
+    ///
+    /// Captions come from `Label` components, falling back to the value's
+    /// `Display` output. Edges whose caption is empty are drawn unlabelled.
+    pub fn to_mermaid(&self) -> String
+    where
+        N: Display,
+        E: Display,
+    {
+        self.to_mermaid_with(|value| value.to_string(), |value| value.to_string())
+    }
+
+    /// Render as a Mermaid flowchart with custom fallback captions
+    ///
+    /// `node_text` and `edge_text` are used for entries without a `Label`.
+    pub fn to_mermaid_with(
+        &self,
+        node_text: impl Fn(&N) -> String,
+        edge_text: impl Fn(&E) -> String,
+    ) -> String {
+        let mut writer = MermaidWriter {
+            out: String::from("flowchart TD\n"),
+            next_id: 0,
+            node_text: &node_text,
+            edge_text: &edge_text,
+        };
+        writer.write_graph(self, 1);
+        writer.out
+    }
+}
+
+struct MermaidWriter<'f, N, E> {
+    out: String,
+    next_id: usize,
+    node_text: &'f dyn Fn(&N) -> String,
+    edge_text: &'f dyn Fn(&E) -> String,
+}
+
+impl<N, E> MermaidWriter<'_, N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    fn write_graph(&mut self, graph: &ContextGraph<N, E>, depth: usize) {
+        let pad = "    ".repeat(depth);
+        let mut ids = HashMap::new();
+
+        for (node_id, node) in graph.get_all_nodes() {
+            let id = format!("n{}", self.next_id);
+            self.next_id += 1;
+            let caption = match node.get_component::<Label>() {
+                Some(label) => label.0.clone(),
+                None => (self.node_text)(&node.value),
+            };
+            let caption = escape(&caption);
+
+            if let Some(subgraph) = node.get_component::<Subgraph<N, E>>() {
+                let _ = writeln!(self.out, "{pad}subgraph {id} [\"{caption}\"]");
+                self.write_graph(&subgraph.graph, depth + 1);
+                let _ = writeln!(self.out, "{pad}end");
+            } else {
+                let _ = writeln!(self.out, "{pad}{id}[\"{caption}\"]");
+            }
+            ids.insert(node_id, id);
+        }
+
+        for (_, edge) in graph.get_all_edges() {
+            let (source, target) = (&ids[&edge.source], &ids[&edge.target]);
+            let caption = match edge.get_component::<Label>() {
+                Some(label) => label.0.clone(),
+                None => (self.edge_text)(&edge.value),
+            };
+            if caption.is_empty() {
+                let _ = writeln!(self.out, "{pad}{source} --> {target}");
+            } else {
+                let caption = escape(&caption);
+                let _ = writeln!(self.out, "{pad}{source} -->|\"{caption}\"| {target}");
+            }
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('"', "#quot;").replace('\n', "<br/>")
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("#quot;", "\"").replace("<br/>", "\n")
+}
+
+impl ContextGraph<String, String> {
+    /// Parse a Mermaid flowchart
+    ///
+    /// Node values are the Mermaid node ids and shape text becomes a `Label`.
+    /// Edge values are the link text, empty when a link has none. Each
+    /// `subgraph` becomes a container node holding a `Subgraph` component;
+    /// links crossing a subgraph boundary are attached to the outermost
+    /// container below the scope both ends share. Styling statements are
+    /// ignored.
+    pub fn from_mermaid(source: &str) -> GraphResult<Self> {
+        let mut diagram = Diagram::default();
+        let mut line_no = 0;
+        for (index, line) in source.lines().enumerate() {
+            line_no = index + 1;
+            for (offset, statement) in split_statements(line) {
+                let trimmed = statement.trim_start();
+                let offset = offset + statement.len() - trimmed.len();
+                diagram
+                    .statement(trimmed.trim_end())
+                    .map_err(|(at, message)| {
+                        let column = line[..offset].chars().count() + at + 1;
+                        mermaid_error(line_no, column, message)
+                    })?;
+            }
+        }
+        if diagram.current != 0 {
+            return Err(mermaid_error(line_no, 0, "unclosed subgraph".to_string()));
+        }
+        diagram.build()
+    }
+}
+
+fn mermaid_error(line: usize, column: usize, message: String) -> GraphError {
+    GraphError::Parse {
+        format: "Mermaid".to_string(),
+        line,
+        column,
+        message,
+    }
+}
+
+/// Character offset within a statement and what went wrong there
+type Failure = (usize, String);
+
+/// Split a line on `;` outside quoted text, dropping `%%` comments
+///
+/// Each statement comes with its byte offset in the line.
+fn split_statements(line: &str) -> Vec<(usize, &str)> {
+    if line.trim_start().starts_with("%%") {
+        return Vec::new();
+    }
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                statements.push((start, &line[start..i]));
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    statements.push((start, &line[start..]));
+    statements
+}
+
+struct ParsedNode {
+    id: String,
+    caption: Option<String>,
+    scope: usize,
+    subgraph: Option<usize>,
+}
+
+struct Scope {
+    parent: usize,
+    container: usize,
+}
+
+/// Flat parse result, turned into nested graphs by [`Diagram::build`]
+struct Diagram {
+    nodes: Vec<ParsedNode>,
+    index: HashMap<String, usize>,
+    /// Scope 0 is the top-level graph; its parent and container are unused
+    scopes: Vec<Scope>,
+    edges: Vec<(usize, usize, String)>,
+    current: usize,
+}
+
+impl Default for Diagram {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            scopes: vec![Scope {
+                parent: 0,
+                container: usize::MAX,
+            }],
+            edges: Vec::new(),
+            current: 0,
+        }
+    }
+}
+
+impl Diagram {
+    fn statement(&mut self, statement: &str) -> Result<(), Failure> {
+        let keyword = statement.split_whitespace().next().unwrap_or("");
+        match keyword {
+            "" | "flowchart" | "graph" | "direction" | "classDef" | "class" | "style"
+            | "linkStyle" | "click" => Ok(()),
+            "end" => {
+                if self.current == 0 {
+                    return Err((0, "`end` without matching subgraph".to_string()));
+                }
+                self.current = self.scopes[self.current].parent;
+                Ok(())
+            }
+            "subgraph" => self.open_subgraph(statement),
+            _ => self.links(statement),
+        }
+    }
+
+    fn open_subgraph(&mut self, statement: &str) -> Result<(), Failure> {
+        let header = statement["subgraph".len()..].trim();
+        if header.is_empty() {
+            return Err((0, "subgraph needs an id or title".to_string()));
+        }
+        let mut cursor = Cursor::new(statement);
+        cursor.pos = "subgraph".len();
+        cursor.skip_ws();
+        let id_at = cursor.pos;
+        let ident = cursor.ident();
+        cursor.skip_ws();
+        let (id, caption) = if ident.is_empty() || (!cursor.at_end() && cursor.peek() != Some('['))
+        {
+            (header.to_string(), Some(unquote(header)))
+        } else if cursor.eat('[') {
+            let title = cursor.take_until(']')?;
+            (ident, Some(unquote(&title)))
+        } else {
+            (ident, None)
+        };
+
+        let node = self.node(&id, caption);
+        if self.nodes[node].subgraph.is_some() {
+            return Err((id_at, format!("subgraph `{id}` declared twice")));
+        }
+        if self.nodes[node].scope != self.current {
+            return Err((
+                id_at,
+                format!("subgraph `{id}` reuses a node declared in another subgraph"),
+            ));
+        }
+        self.scopes.push(Scope {
+            parent: self.current,
+            container: node,
+        });
+        self.current = self.scopes.len() - 1;
+        self.nodes[node].subgraph = Some(self.current);
+        Ok(())
+    }
+
+    /// A node group optionally followed by a chain of links to further groups
+    fn links(&mut self, statement: &str) -> Result<(), Failure> {
+        let mut cursor = Cursor::new(statement);
+        let mut previous = self.group(&mut cursor)?;
+        loop {
+            cursor.skip_ws();
+            if cursor.at_end() {
+                return Ok(());
+            }
+            let label = cursor.link()?;
+            let next = self.group(&mut cursor)?;
+            for source in &previous {
+                for target in &next {
+                    self.edges
+                        .push((*source, *target, label.clone().unwrap_or_default()));
+                }
+            }
+            previous = next;
+        }
+    }
+
+    /// `a`, or `a & b & c`, each with an optional shape
+    fn group(&mut self, cursor: &mut Cursor) -> Result<Vec<usize>, Failure> {
+        let mut group = Vec::new();
+        loop {
+            cursor.skip_ws();
+            let id = cursor.ident();
+            if id.is_empty() {
+                return Err(cursor.fail(match cursor.peek() {
+                    Some(c) => format!("expected node id, found `{c}`"),
+                    None => "expected node id".to_string(),
+                }));
+            }
+            let caption = cursor.shape()?;
+            group.push(self.node(&id, caption));
+            cursor.skip_ws();
+            if !cursor.eat('&') {
+                return Ok(group);
+            }
+        }
+    }
+
+    /// Look up a node, creating it in the current scope on first mention
+    fn node(&mut self, id: &str, caption: Option<String>) -> usize {
+        if let Some(&index) = self.index.get(id) {
+            if caption.is_some() {
+                self.nodes[index].caption = caption;
+            }
+            return index;
+        }
+        self.nodes.push(ParsedNode {
+            id: id.to_string(),
+            caption,
+            scope: self.current,
+            subgraph: None,
+        });
+        self.index.insert(id.to_string(), self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    /// Scopes from `scope` up to and including the top level
+    fn ancestry(&self, mut scope: usize) -> Vec<usize> {
+        let mut chain = vec![scope];
+        while scope != 0 {
+            scope = self.scopes[scope].parent;
+            chain.push(scope);
+        }
+        chain
+    }
+
+    /// The node standing in for `node` inside `scope`
+    fn lift(&self, node: usize, scope: usize) -> usize {
+        let mut lifted = node;
+        let mut at = self.nodes[node].scope;
+        while at != scope {
+            lifted = self.scopes[at].container;
+            at = self.scopes[at].parent;
+        }
+        lifted
+    }
+
+    fn build(self) -> GraphResult<ContextGraph<String, String>> {
+        let mut edges_by_scope: Vec<Vec<(usize, usize, String)>> =
+            self.scopes.iter().map(|_| Vec::new()).collect();
+        for (source, target, label) in &self.edges {
+            let target_chain = self.ancestry(self.nodes[*target].scope);
+            let shared = self
+                .ancestry(self.nodes[*source].scope)
+                .into_iter()
+                .find(|scope| target_chain.contains(scope))
+                .unwrap_or(0);
+            edges_by_scope[shared].push((
+                self.lift(*source, shared),
+                self.lift(*target, shared),
+                label.clone(),
+            ));
+        }
+        self.build_scope(0, "Mermaid", &mut edges_by_scope)
+    }
+
+    fn build_scope(
+        &self,
+        scope: usize,
+        name: &str,
+        edges_by_scope: &mut Vec<Vec<(usize, usize, String)>>,
+    ) -> GraphResult<ContextGraph<String, String>> {
+        let mut graph = ContextGraph::new(name);
+        let mut ids = HashMap::new();
+
+        for (index, parsed) in self.nodes.iter().enumerate() {
+            if parsed.scope != scope {
+                continue;
+            }
+            let node_id = graph.add_node(parsed.id.clone());
+            let node = graph.get_node_mut(node_id).expect("node was just added");
+            if let Some(caption) = &parsed.caption {
+                node.components
+                    .add(Label(caption.clone()))
+                    .expect("fresh node has no label");
+            }
+            if let Some(inner) = parsed.subgraph {
+                let title = parsed.caption.as_deref().unwrap_or(&parsed.id);
+                let subgraph = self.build_scope(inner, title, edges_by_scope)?;
+                node.components
+                    .add(Subgraph {
+                        graph: Box::new(subgraph),
+                    })
+                    .expect("fresh node has no subgraph");
+            }
+            ids.insert(index, node_id);
+        }
+
+        for (source, target, label) in std::mem::take(&mut edges_by_scope[scope]) {
+            let endpoint = |index: usize| {
+                ids.get(&index).copied().ok_or_else(|| {
+                    let id = &self.nodes[index].id;
+                    mermaid_error(
+                        0,
+                        0,
+                        format!("link endpoint `{id}` is outside its subgraph"),
+                    )
+                })
+            };
+            graph.add_edge(endpoint(source)?, endpoint(target)?, label)?;
+        }
+        Ok(graph)
+    }
+}
+
+fn unquote(text: &str) -> String {
+    let text = text.trim();
+    let text = text
+        .strip_prefix('"')
+        .and_then(|t| t.strip_suffix('"'))
+        .unwrap_or(text);
+    unescape(text)
+}
+
+struct Cursor {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Cursor {
+    fn new(text: &str) -> Self {
+        Self {
+            chars: text.chars().collect(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn at_end(&self) -> bool {
+        self.pos >= self.chars.len()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.pos += 1;
+        }
+    }
+
+    fn take_while(&mut self, keep: impl Fn(char) -> bool) -> String {
+        let start = self.pos;
+        while self.peek().is_some_and(&keep) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos].iter().collect()
+    }
+
+    fn fail(&self, message: String) -> Failure {
+        (self.pos, message)
+    }
+
+    fn take_until(&mut self, end: char) -> Result<String, Failure> {
+        let text = self.take_while(|c| c != end);
+        if !self.eat(end) {
+            return Err(self.fail(format!("missing `{end}`")));
+        }
+        Ok(text)
+    }
+
+    fn ident(&mut self) -> String {
+        self.take_while(|c| c.is_alphanumeric() || c == '_')
+    }
+
+    /// Optional node shape such as `[text]`, `(text)`, `{{text}}` or `>text]`
+    fn shape(&mut self) -> Result<Option<String>, Failure> {
+        let mut closer = Vec::new();
+        if self.eat('>') {
+            closer.push(']');
+        } else {
+            while closer.len() < 3 {
+                match self.peek() {
+                    Some('[') => closer.push(']'),
+                    Some('(') => closer.push(')'),
+                    Some('{') => closer.push('}'),
+                    _ => break,
+                }
+                self.pos += 1;
+            }
+            if closer.is_empty() {
+                self.skip_class();
+                return Ok(None);
+            }
+            if matches!(self.peek(), Some('/' | '\\')) {
+                self.pos += 1;
+                closer.push('/');
+            }
+        }
+        closer.reverse();
+
+        let text = if self.peek() == Some('"') {
+            self.pos += 1;
+            let text = self.take_until('"')?;
+            if !self.closes(&closer) {
+                return Err(self.fail("unterminated node shape".to_string()));
+            }
+            text
+        } else {
+            let start = self.pos;
+            while !self.closes(&closer) {
+                if self.at_end() {
+                    return Err(self.fail("unterminated node shape".to_string()));
+                }
+                self.pos += 1;
+            }
+            self.chars[start..self.pos - closer.len()].iter().collect()
+        };
+        self.skip_class();
+        Ok(Some(unescape(text.trim())))
+    }
+
+    /// Consume `closer` if it starts here; slashes match either direction
+    fn closes(&mut self, closer: &[char]) -> bool {
+        let matched = closer.iter().enumerate().all(|(i, c)| match c {
+            '/' => matches!(self.peek_at(i), Some('/' | '\\')),
+            c => self.peek_at(i) == Some(*c),
+        });
+        if matched {
+            self.pos += closer.len();
+        }
+        matched
+    }
+
+    /// Skip a `:::className` suffix
+    fn skip_class(&mut self) {
+        if self.chars[self.pos..].starts_with(&[':', ':', ':']) {
+            self.pos += 3;
+            self.ident();
+        }
+    }
+
+    /// A link such as `-->`, `---`, `-.->`, `==>`, `--o`, `-- text -->` or
+    /// `-->|text|`, returning its text if any
+    fn link(&mut self) -> Result<Option<String>, Failure> {
+        let (run, headed) = self.link_body();
+        if run.len() < 2 {
+            return Err(self.fail(match self.peek() {
+                Some(c) => format!("expected link, found `{c}`"),
+                None => "expected link".to_string(),
+            }));
+        }
+
+        let mut label = None;
+        if !headed
+            && matches!(run.as_str(), "--" | "==" | "-.")
+            && self.peek().is_some_and(char::is_whitespace)
+        {
+            let start = self.pos;
+            while !self.at_end() {
+                if matches!(
+                    (self.peek(), self.peek_at(1)),
+                    (Some('-'), Some('-')) | (Some('='), Some('=')) | (Some('.'), Some('-'))
+                ) {
+                    break;
+                }
+                self.pos += 1;
+            }
+            if self.at_end() {
+                return Err(self.fail("unterminated link text".to_string()));
+            }
+            let text: String = self.chars[start..self.pos].iter().collect();
+            label = Some(unquote(&text));
+            self.link_body();
+        }
+
+        self.skip_ws();
+        if self.eat('|') {
+            let text = self.take_until('|')?;
+            label = Some(unquote(&text));
+        }
+        Ok(label)
+    }
+
+    /// Consume link characters and an arrow head, returning the shaft and
+    /// whether a head was present
+    fn link_body(&mut self) -> (String, bool) {
+        self.eat('<');
+        let run = self.take_while(|c| matches!(c, '-' | '=' | '.'));
+        let headed = if self.eat('>') {
+            true
+        } else if matches!(self.peek(), Some('o' | 'x'))
+            && self
+                .peek_at(1)
+                .is_none_or(|c| c.is_whitespace() || c == '|')
+        {
+            self.pos += 1;
+            true
+        } else {
+            false
+        };
+        (run, headed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mermaid_round_trip_with_subgraph() {
+        let mut inner = ContextGraph::<String, String>::new("Review");
+        let draft = inner.add_node("draft".to_string());
+        let check = inner.add_node("check".to_string());
+        inner.add_edge(draft, check, String::new()).unwrap();
+
+        let mut graph = ContextGraph::<String, String>::new("Workflow");
+        let start = graph.add_node("start".to_string());
+        let review = graph.add_node("review".to_string());
+        graph
+            .get_node_mut(review)
+            .unwrap()
+            .add_component(Subgraph {
+                graph: Box::new(inner),
+            })
+            .unwrap();
+        graph
+            .get_node_mut(start)
+            .unwrap()
+            .add_component(Label("Say \"go\"".to_string()))
+            .unwrap();
+        graph.add_edge(start, review, "submit".to_string()).unwrap();
+
+        let text = graph.to_mermaid();
+        assert!(text.starts_with("flowchart TD\n"));
+        assert!(text.contains("n0[\"Say #quot;go#quot;\"]"));
+        assert!(text.contains("subgraph n1 [\"review\"]"));
+        assert!(text.contains("n0 -->|\"submit\"| n1"));
+
+        let parsed = ContextGraph::from_mermaid(&text).unwrap();
+        assert_eq!(parsed.node_count(), 2);
+        assert_eq!(parsed.total_node_count(), 4);
+        let (_, edge) = parsed.get_all_edges().next().unwrap();
+        assert_eq!(edge.value, "submit");
+        let (_, first) = parsed.get_all_nodes().next().unwrap();
+        assert_eq!(first.get_component::<Label>().unwrap().0, "Say \"go\"");
+        assert_eq!(parsed.to_mermaid(), text);
+    }
+
+    #[test]
+    fn test_parse_flowchart_syntax() {
+        let source = r#"
+            flowchart LR
+            %% an approval workflow
+            A[Start] --> B{Approved?}
+            B -- yes --> C([Done]) ; B -.->|no| D>Rework]
+            D ==> A & C
+            subgraph audit [Audit trail]
+                E[(Log)] --- F((Archive))
+            end
+            C --> E
+            classDef done fill:#9f6
+        "#;
+        let graph = ContextGraph::from_mermaid(source).unwrap();
+        assert_eq!(graph.node_count(), 5);
+        assert_eq!(graph.total_node_count(), 7);
+        assert_eq!(graph.edge_count(), 6);
+
+        let labels: Vec<&str> = graph
+            .get_all_edges()
+            .map(|(_, e)| e.value.as_str())
+            .collect();
+        assert_eq!(labels, ["", "yes", "no", "", "", ""]);
+
+        let (audit_id, audit) = graph
+            .query_nodes()
+            .where_value(|v| v == "audit")
+            .first()
+            .unwrap();
+        let (_, to_audit) = graph.incoming_edges(audit_id).next().unwrap();
+        assert_eq!(graph.get_node(to_audit.source).unwrap().value, "C");
+        let inner = &audit
+            .get_component::<Subgraph<String, String>>()
+            .unwrap()
+            .graph;
+        assert_eq!(inner.edge_count(), 1);
+    }
+
+    #[test]
+    fn test_parse_errors_report_location() {
+        let err = ContextGraph::from_mermaid("graph TD\n  A --> \n").unwrap_err();
+        assert!(
+            matches!(
+                err,
+                GraphError::Parse {
+                    line: 2,
+                    column: 8,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+
+        let err = ContextGraph::from_mermaid("graph TD\nsubgraph s\nA\n").unwrap_err();
+        assert!(err.to_string().contains("unclosed subgraph"), "{err}");
+
+        let err = ContextGraph::from_mermaid(
+            "flowchart TD\nsubgraph A\nB\nend\nsubgraph B\nC\nend\nC --> D\n",
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Mermaid parse error at line 5, column 10: \
+             subgraph `B` reuses a node declared in another subgraph"
+        );
+
+        let err = ContextGraph::from_mermaid("A[oops --> B").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Mermaid parse error at line 1, column 13: unterminated node shape"
+        );
+    }
+}
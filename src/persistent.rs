@@ -0,0 +1,658 @@
+//! Persistent graph storage for copy-on-write clones
+//!
+//! [`PersistentGraph`] keeps petgraph's adjacency-list layout — dense
+//! indices, removal by swapping in the last entry — but holds its nodes and
+//! edges in persistent vectors of `Arc` slots. Cloning is O(1), and a write
+//! copies only the entry it touches and the chunks on the path to it, so a
+//! what-if clone of a large graph stays cheap after a handful of edits.
+//!
+//! It implements petgraph's visit traits, so petgraph algorithms run on it
+//! directly.
+
+use fixedbitset::FixedBitSet;
+use imbl::shared_ptr::DefaultSharedPtr;
+use imbl::Vector;
+use petgraph::graph::{EdgeIndex, NodeIndex};
+use petgraph::visit::{
+    Data, EdgeCount, EdgeRef, GraphBase, GraphProp, IntoEdgeReferences, IntoEdges,
+    IntoEdgesDirected, IntoNeighbors, IntoNeighborsDirected, IntoNodeIdentifiers,
+    NodeCompactIndexable, NodeCount, NodeIndexable, Visitable,
+};
+use petgraph::{Directed, Direction};
+use std::fmt;
+use std::iter;
+use std::ops::{Index, Range};
+use std::sync::Arc;
+
+const OUTGOING: usize = 0;
+const INCOMING: usize = 1;
+
+struct NodeSlot<N> {
+    weight: Arc<N>,
+    /// Heads of the outgoing and incoming edge lists
+    next: [EdgeIndex; 2],
+}
+
+impl<N> Clone for NodeSlot<N> {
+    fn clone(&self) -> Self {
+        Self {
+            weight: self.weight.clone(),
+            next: self.next,
+        }
+    }
+}
+
+struct EdgeSlot<E> {
+    weight: Arc<E>,
+    /// Source and target
+    node: [NodeIndex; 2],
+    /// Next edge in the source's outgoing and the target's incoming list
+    next: [EdgeIndex; 2],
+}
+
+impl<E> Clone for EdgeSlot<E> {
+    fn clone(&self) -> Self {
+        Self {
+            weight: self.weight.clone(),
+            node: self.node,
+            next: self.next,
+        }
+    }
+}
+
+/// A directed graph whose clones share storage until written
+pub struct PersistentGraph<N, E> {
+    nodes: Vector<NodeSlot<N>>,
+    edges: Vector<EdgeSlot<E>>,
+}
+
+impl<N, E> Clone for PersistentGraph<N, E> {
+    fn clone(&self) -> Self {
+        Self {
+            nodes: self.nodes.clone(),
+            edges: self.edges.clone(),
+        }
+    }
+}
+
+impl<N, E> Default for PersistentGraph<N, E> {
+    fn default() -> Self {
+        Self {
+            nodes: Vector::new(),
+            edges: Vector::new(),
+        }
+    }
+}
+
+impl<N: fmt::Debug, E: fmt::Debug> fmt::Debug for PersistentGraph<N, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let edges: Vec<_> = self
+            .edge_references()
+            .map(|edge| (edge.source().index(), edge.target().index(), edge.weight))
+            .collect();
+        f.debug_struct("PersistentGraph")
+            .field("nodes", &self.node_weights().collect::<Vec<_>>())
+            .field("edges", &edges)
+            .finish()
+    }
+}
+
+/// Node indices in order, as yielded by [`PersistentGraph::node_indices`]
+pub type NodeIndices = iter::Map<Range<usize>, fn(usize) -> NodeIndex>;
+
+/// Edge indices in order, as yielded by [`PersistentGraph::edge_indices`]
+pub type EdgeIndices = iter::Map<Range<usize>, fn(usize) -> EdgeIndex>;
+
+impl<N, E> PersistentGraph<N, E> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+
+    pub fn node_weight(&self, a: NodeIndex) -> Option<&N> {
+        self.nodes.get(a.index()).map(|slot| &*slot.weight)
+    }
+
+    pub fn edge_weight(&self, e: EdgeIndex) -> Option<&E> {
+        self.edges.get(e.index()).map(|slot| &*slot.weight)
+    }
+
+    /// Source and target of an edge
+    pub fn edge_endpoints(&self, e: EdgeIndex) -> Option<(NodeIndex, NodeIndex)> {
+        self.edges
+            .get(e.index())
+            .map(|slot| (slot.node[OUTGOING], slot.node[INCOMING]))
+    }
+
+    pub fn node_indices(&self) -> NodeIndices {
+        (0..self.node_count()).map(NodeIndex::new)
+    }
+
+    pub fn edge_indices(&self) -> EdgeIndices {
+        (0..self.edge_count()).map(EdgeIndex::new)
+    }
+
+    pub fn node_weights(&self) -> impl Iterator<Item = &N> {
+        self.nodes.iter().map(|slot| &*slot.weight)
+    }
+
+    pub fn edge_weights(&self) -> impl Iterator<Item = &E> {
+        self.edges.iter().map(|slot| &*slot.weight)
+    }
+
+    /// Every edge with its index and endpoints
+    pub fn edge_references(&self) -> EdgeReferences<'_, E> {
+        EdgeReferences {
+            edges: self.edges.iter().enumerate(),
+        }
+    }
+
+    /// Outgoing edges of `a`, most recently added first
+    pub fn edges(&self, a: NodeIndex) -> Edges<'_, E> {
+        self.edges_directed(a, Direction::Outgoing)
+    }
+
+    /// Outgoing or incoming edges of `a`, most recently added first
+    pub fn edges_directed(&self, a: NodeIndex, direction: Direction) -> Edges<'_, E> {
+        let k = direction.index();
+        Edges {
+            edges: &self.edges,
+            next: self.head(a, k),
+            k,
+        }
+    }
+
+    /// Targets of the outgoing edges of `a`, one per edge
+    pub fn neighbors(&self, a: NodeIndex) -> Neighbors<'_, E> {
+        self.neighbors_directed(a, Direction::Outgoing)
+    }
+
+    /// Nodes across the outgoing or incoming edges of `a`, one per edge
+    pub fn neighbors_directed(&self, a: NodeIndex, direction: Direction) -> Neighbors<'_, E> {
+        let mut next = [EdgeIndex::end(); 2];
+        let k = direction.index();
+        next[k] = self.head(a, k);
+        Neighbors {
+            edges: &self.edges,
+            next,
+            skip_start: NodeIndex::end(),
+        }
+    }
+
+    /// Nodes across every edge of `a`, counting a self-loop once
+    pub fn neighbors_undirected(&self, a: NodeIndex) -> Neighbors<'_, E> {
+        Neighbors {
+            edges: &self.edges,
+            next: [self.head(a, OUTGOING), self.head(a, INCOMING)],
+            skip_start: a,
+        }
+    }
+
+    fn head(&self, a: NodeIndex, k: usize) -> EdgeIndex {
+        self.nodes
+            .get(a.index())
+            .map_or(EdgeIndex::end(), |slot| slot.next[k])
+    }
+}
+
+impl<N: Clone, E: Clone> PersistentGraph<N, E> {
+    pub(crate) fn add_node(&mut self, weight: N) -> NodeIndex {
+        let index = NodeIndex::new(self.nodes.len());
+        self.nodes.push_back(NodeSlot {
+            weight: Arc::new(weight),
+            next: [EdgeIndex::end(); 2],
+        });
+        index
+    }
+
+    /// Add an edge between two existing nodes
+    ///
+    /// Panics if either endpoint does not exist.
+    pub(crate) fn add_edge(&mut self, a: NodeIndex, b: NodeIndex, weight: E) -> EdgeIndex {
+        assert!(
+            a.index() < self.nodes.len() && b.index() < self.nodes.len(),
+            "edge endpoint out of bounds"
+        );
+        let index = EdgeIndex::new(self.edges.len());
+        let next = [self.head(a, OUTGOING), self.head(b, INCOMING)];
+        self.nodes[a.index()].next[OUTGOING] = index;
+        self.nodes[b.index()].next[INCOMING] = index;
+        self.edges.push_back(EdgeSlot {
+            weight: Arc::new(weight),
+            node: [a, b],
+            next,
+        });
+        index
+    }
+
+    /// Mutable access to one node, copying only that node if it is shared
+    pub(crate) fn node_weight_mut(&mut self, a: NodeIndex) -> Option<&mut N> {
+        self.nodes
+            .get_mut(a.index())
+            .map(|slot| Arc::make_mut(&mut slot.weight))
+    }
+
+    /// Mutable access to one edge, copying only that edge if it is shared
+    pub(crate) fn edge_weight_mut(&mut self, e: EdgeIndex) -> Option<&mut E> {
+        self.edges
+            .get_mut(e.index())
+            .map(|slot| Arc::make_mut(&mut slot.weight))
+    }
+
+    /// Remove an edge; the last edge takes its index
+    pub(crate) fn remove_edge(&mut self, e: EdgeIndex) -> Option<E> {
+        let (node, next) = self
+            .edges
+            .get(e.index())
+            .map(|slot| (slot.node, slot.next))?;
+        self.change_edge_links(node, e, next);
+
+        let last = EdgeIndex::new(self.edges.len() - 1);
+        let mut removed = self.edges.pop_back()?;
+        if e != last {
+            removed = std::mem::replace(&mut self.edges[e.index()], removed);
+            let moved = self.edges[e.index()].node;
+            self.change_edge_links(moved, last, [e, e]);
+        }
+        Some(Arc::unwrap_or_clone(removed.weight))
+    }
+
+    /// Remove a node and its edges; the last node takes its index
+    pub(crate) fn remove_node(&mut self, a: NodeIndex) -> Option<N> {
+        self.nodes.get(a.index())?;
+        for k in [OUTGOING, INCOMING] {
+            loop {
+                let next = self.nodes[a.index()].next[k];
+                if next == EdgeIndex::end() {
+                    break;
+                }
+                self.remove_edge(next);
+            }
+        }
+
+        let last = self.nodes.len() - 1;
+        let mut removed = self.nodes.pop_back()?;
+        if a.index() != last {
+            removed = std::mem::replace(&mut self.nodes[a.index()], removed);
+            // Point the moved node's edges at its new index
+            for k in [OUTGOING, INCOMING] {
+                let mut cur = self.nodes[a.index()].next[k];
+                while let Some(slot) = self.edges.get_mut(cur.index()) {
+                    slot.node[k] = a;
+                    cur = slot.next[k];
+                }
+            }
+        }
+        Some(Arc::unwrap_or_clone(removed.weight))
+    }
+
+    /// Replace every link to `e` in the lists of `node` with `replacement`
+    fn change_edge_links(
+        &mut self,
+        node: [NodeIndex; 2],
+        e: EdgeIndex,
+        replacement: [EdgeIndex; 2],
+    ) {
+        for k in [OUTGOING, INCOMING] {
+            let Some(slot) = self.nodes.get_mut(node[k].index()) else {
+                continue;
+            };
+            if slot.next[k] == e {
+                slot.next[k] = replacement[k];
+                continue;
+            }
+            let mut cur = slot.next[k];
+            while let Some(next) = self.edges.get(cur.index()).map(|slot| slot.next[k]) {
+                if next == e {
+                    self.edges[cur.index()].next[k] = replacement[k];
+                    break;
+                }
+                cur = next;
+            }
+        }
+    }
+}
+
+impl<N, E> Index<NodeIndex> for PersistentGraph<N, E> {
+    type Output = N;
+
+    fn index(&self, a: NodeIndex) -> &N {
+        &self.nodes[a.index()].weight
+    }
+}
+
+impl<N, E> Index<EdgeIndex> for PersistentGraph<N, E> {
+    type Output = E;
+
+    fn index(&self, e: EdgeIndex) -> &E {
+        &self.edges[e.index()].weight
+    }
+}
+
+/// An edge with its index and endpoints
+pub struct EdgeReference<'a, E> {
+    index: EdgeIndex,
+    node: [NodeIndex; 2],
+    weight: &'a E,
+}
+
+impl<E> Clone for EdgeReference<'_, E> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E> Copy for EdgeReference<'_, E> {}
+
+impl<'a, E> EdgeReference<'a, E> {
+    /// The edge weight, borrowed from the graph rather than this reference
+    pub fn weight(&self) -> &'a E {
+        self.weight
+    }
+}
+
+impl<E> EdgeRef for EdgeReference<'_, E> {
+    type NodeId = NodeIndex;
+    type EdgeId = EdgeIndex;
+    type Weight = E;
+
+    fn source(&self) -> NodeIndex {
+        self.node[OUTGOING]
+    }
+
+    fn target(&self) -> NodeIndex {
+        self.node[INCOMING]
+    }
+
+    fn weight(&self) -> &E {
+        self.weight
+    }
+
+    fn id(&self) -> EdgeIndex {
+        self.index
+    }
+}
+
+/// Iterator over the edges of a graph
+pub struct EdgeReferences<'a, E> {
+    edges: iter::Enumerate<imbl::vector::Iter<'a, EdgeSlot<E>, DefaultSharedPtr>>,
+}
+
+impl<'a, E> Iterator for EdgeReferences<'a, E> {
+    type Item = EdgeReference<'a, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.edges.next().map(|(i, slot)| EdgeReference {
+            index: EdgeIndex::new(i),
+            node: slot.node,
+            weight: &*slot.weight,
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.edges.size_hint()
+    }
+}
+
+/// Iterator over the outgoing or incoming edges of one node
+pub struct Edges<'a, E> {
+    edges: &'a Vector<EdgeSlot<E>>,
+    next: EdgeIndex,
+    k: usize,
+}
+
+impl<'a, E> Iterator for Edges<'a, E> {
+    type Item = EdgeReference<'a, E>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let index = self.next;
+        let slot = self.edges.get(index.index())?;
+        self.next = slot.next[self.k];
+        Some(EdgeReference {
+            index,
+            node: slot.node,
+            weight: &*slot.weight,
+        })
+    }
+}
+
+/// Iterator over the nodes adjacent to one node
+pub struct Neighbors<'a, E> {
+    edges: &'a Vector<EdgeSlot<E>>,
+    next: [EdgeIndex; 2],
+    skip_start: NodeIndex,
+}
+
+impl<E> Iterator for Neighbors<'_, E> {
+    type Item = NodeIndex;
+
+    fn next(&mut self) -> Option<NodeIndex> {
+        if let Some(slot) = self.edges.get(self.next[OUTGOING].index()) {
+            self.next[OUTGOING] = slot.next[OUTGOING];
+            return Some(slot.node[INCOMING]);
+        }
+        // Self-loops were already yielded from the outgoing list
+        while let Some(slot) = self.edges.get(self.next[INCOMING].index()) {
+            self.next[INCOMING] = slot.next[INCOMING];
+            if slot.node[OUTGOING] != self.skip_start {
+                return Some(slot.node[OUTGOING]);
+            }
+        }
+        None
+    }
+}
+
+// petgraph visit traits, so its algorithms run on the persistent storage
+
+impl<N, E> GraphBase for PersistentGraph<N, E> {
+    type NodeId = NodeIndex;
+    type EdgeId = EdgeIndex;
+}
+
+impl<N, E> GraphProp for PersistentGraph<N, E> {
+    type EdgeType = Directed;
+}
+
+impl<N, E> Data for PersistentGraph<N, E> {
+    type NodeWeight = N;
+    type EdgeWeight = E;
+}
+
+impl<N, E> NodeCount for PersistentGraph<N, E> {
+    fn node_count(&self) -> usize {
+        self.nodes.len()
+    }
+}
+
+impl<N, E> EdgeCount for PersistentGraph<N, E> {
+    fn edge_count(&self) -> usize {
+        self.edges.len()
+    }
+}
+
+impl<N, E> NodeIndexable for PersistentGraph<N, E> {
+    fn node_bound(&self) -> usize {
+        self.nodes.len()
+    }
+
+    fn to_index(&self, a: NodeIndex) -> usize {
+        a.index()
+    }
+
+    fn from_index(&self, i: usize) -> NodeIndex {
+        NodeIndex::new(i)
+    }
+}
+
+impl<N, E> NodeCompactIndexable for PersistentGraph<N, E> {}
+
+impl<N, E> Visitable for PersistentGraph<N, E> {
+    type Map = FixedBitSet;
+
+    fn visit_map(&self) -> FixedBitSet {
+        FixedBitSet::with_capacity(self.nodes.len())
+    }
+
+    fn reset_map(&self, map: &mut FixedBitSet) {
+        map.clear();
+        map.grow(self.nodes.len());
+    }
+}
+
+impl<N, E> IntoNodeIdentifiers for &PersistentGraph<N, E> {
+    type NodeIdentifiers = NodeIndices;
+
+    fn node_identifiers(self) -> NodeIndices {
+        self.node_indices()
+    }
+}
+
+impl<'a, N, E> IntoNeighbors for &'a PersistentGraph<N, E> {
+    type Neighbors = Neighbors<'a, E>;
+
+    fn neighbors(self, a: NodeIndex) -> Neighbors<'a, E> {
+        PersistentGraph::neighbors(self, a)
+    }
+}
+
+impl<'a, N, E> IntoNeighborsDirected for &'a PersistentGraph<N, E> {
+    type NeighborsDirected = Neighbors<'a, E>;
+
+    fn neighbors_directed(self, a: NodeIndex, direction: Direction) -> Neighbors<'a, E> {
+        PersistentGraph::neighbors_directed(self, a, direction)
+    }
+}
+
+impl<'a, N, E> IntoEdgeReferences for &'a PersistentGraph<N, E> {
+    type EdgeRef = EdgeReference<'a, E>;
+    type EdgeReferences = EdgeReferences<'a, E>;
+
+    fn edge_references(self) -> EdgeReferences<'a, E> {
+        PersistentGraph::edge_references(self)
+    }
+}
+
+impl<'a, N, E> IntoEdges for &'a PersistentGraph<N, E> {
+    type Edges = Edges<'a, E>;
+
+    fn edges(self, a: NodeIndex) -> Edges<'a, E> {
+        PersistentGraph::edges(self, a)
+    }
+}
+
+impl<'a, N, E> IntoEdgesDirected for &'a PersistentGraph<N, E> {
+    type EdgesDirected = Edges<'a, E>;
+
+    fn edges_directed(self, a: NodeIndex, direction: Direction) -> Edges<'a, E> {
+        PersistentGraph::edges_directed(self, a, direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use petgraph::graph::DiGraph;
+
+    /// Build the same graph in petgraph and in persistent storage
+    fn pair() -> (DiGraph<u32, u32>, PersistentGraph<u32, u32>) {
+        let mut reference = DiGraph::new();
+        let mut graph = PersistentGraph::new();
+        for i in 0..6 {
+            reference.add_node(i);
+            graph.add_node(i);
+        }
+        let edges = [
+            (0, 1),
+            (0, 2),
+            (1, 2),
+            (2, 2),
+            (3, 0),
+            (4, 5),
+            (5, 4),
+            (2, 0),
+        ];
+        for (w, (a, b)) in edges.into_iter().enumerate() {
+            let (a, b) = (NodeIndex::new(a), NodeIndex::new(b));
+            reference.add_edge(a, b, w as u32);
+            graph.add_edge(a, b, w as u32);
+        }
+        (reference, graph)
+    }
+
+    /// Both graphs have the same nodes, edges and adjacency order
+    fn assert_same(reference: &DiGraph<u32, u32>, graph: &PersistentGraph<u32, u32>) {
+        assert_eq!(reference.node_count(), graph.node_count());
+        assert_eq!(reference.edge_count(), graph.edge_count());
+        for a in reference.node_indices() {
+            assert_eq!(reference[a], graph[a]);
+            for direction in [Direction::Outgoing, Direction::Incoming] {
+                let expected: Vec<_> = reference
+                    .edges_directed(a, direction)
+                    .map(|e| (e.id(), e.source(), e.target(), *e.weight()))
+                    .collect();
+                let actual: Vec<_> = graph
+                    .edges_directed(a, direction)
+                    .map(|e| (e.id(), e.source(), e.target(), *e.weight()))
+                    .collect();
+                assert_eq!(expected, actual);
+                assert!(reference
+                    .neighbors_directed(a, direction)
+                    .eq(graph.neighbors_directed(a, direction)));
+            }
+            assert!(reference
+                .neighbors_undirected(a)
+                .eq(graph.neighbors_undirected(a)));
+        }
+    }
+
+    #[test]
+    fn test_matches_petgraph_through_removals() {
+        let (mut reference, mut graph) = pair();
+        assert_same(&reference, &graph);
+
+        for e in [1, 0, 3] {
+            let e = EdgeIndex::new(e);
+            assert_eq!(reference.remove_edge(e), graph.remove_edge(e));
+            assert_same(&reference, &graph);
+        }
+        for a in [2, 0] {
+            let a = NodeIndex::new(a);
+            assert_eq!(reference.remove_node(a), graph.remove_node(a));
+            assert_same(&reference, &graph);
+        }
+        assert_eq!(graph.remove_node(NodeIndex::new(10)), None);
+
+        let sccs = |mut sccs: Vec<Vec<NodeIndex>>| {
+            sccs.iter_mut().for_each(|scc| scc.sort());
+            sccs.sort();
+            sccs
+        };
+        assert_eq!(
+            sccs(petgraph::algo::kosaraju_scc(&reference)),
+            sccs(petgraph::algo::kosaraju_scc(&graph))
+        );
+    }
+
+    #[test]
+    fn test_writes_copy_only_the_touched_entry() {
+        let (_, graph) = pair();
+        let mut copy = graph.clone();
+        *copy.node_weight_mut(NodeIndex::new(1)).unwrap() = 10;
+        *copy.edge_weight_mut(EdgeIndex::new(2)).unwrap() = 20;
+
+        assert_eq!(graph[NodeIndex::new(1)], 1);
+        assert_eq!(copy[NodeIndex::new(1)], 10);
+        assert_eq!(graph[EdgeIndex::new(2)], 2);
+        assert_eq!(copy[EdgeIndex::new(2)], 20);
+        for a in [0, 2, 3] {
+            assert!(Arc::ptr_eq(&graph.nodes[a].weight, &copy.nodes[a].weight));
+        }
+    }
+}
@@ -0,0 +1,289 @@
+//! Fluent component queries over nodes and edges
+//!
+//! Queries are built from a [`ContextGraph`] with [`ContextGraph::query_nodes`]
+//! or [`ContextGraph::query_edges`] (or the same methods on any other
+//! [`GraphView`]) and narrowed with component and structural filters. All
+//! filters are combined with logical AND.
+//!
+//! ```rust,ignore
+//! let important = graph
+//!     .query_nodes()
+//!     .with::<Label>()
+//!     .where_component::<Metadata>(|m| m.tags.contains(&"important".to_string()))
+//!     .ids()
+//!     .collect::<Vec<_>>();
+//! ```
+
+#[cfg(doc)]
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use crate::view::GraphView;
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+type NodePredicate<'a, N> = Box<dyn Fn(&NodeEntry<N>) -> bool + 'a>;
+type EdgePredicate<'a, E> = Box<dyn Fn(&EdgeEntry<E>) -> bool + 'a>;
+
+/// Query builder over the nodes of a graph view
+pub struct NodeQuery<'a, G: GraphView> {
+    graph: &'a G,
+    filters: Vec<NodePredicate<'a, G::Node>>,
+}
+
+impl<'a, G: GraphView> NodeQuery<'a, G> {
+    pub fn new(graph: &'a G) -> Self {
+        Self {
+            graph,
+            filters: Vec::new(),
+        }
+    }
+
+    /// Keep nodes that have a component of type `T`
+    pub fn with<T: Component + 'static>(mut self) -> Self {
+        self.filters
+            .push(Box::new(|node| node.has_component::<T>()));
+        self
+    }
+
+    /// Keep nodes that do not have a component of type `T`
+    pub fn without<T: Component + 'static>(mut self) -> Self {
+        self.filters
+            .push(Box::new(|node| !node.has_component::<T>()));
+        self
+    }
+
+    /// Keep nodes whose component of type `T` satisfies the predicate
+    pub fn where_component<T: Component + 'static>(
+        mut self,
+        predicate: impl Fn(&T) -> bool + 'a,
+    ) -> Self {
+        self.filters.push(Box::new(move |node| {
+            node.get_component::<T>().is_some_and(&predicate)
+        }));
+        self
+    }
+
+    /// Keep nodes whose value satisfies the predicate
+    pub fn where_value<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&G::Node) -> bool + 'a,
+    {
+        self.filters
+            .push(Box::new(move |node| predicate(&node.value)));
+        self
+    }
+
+    /// Keep nodes adjacent to `node_id` in either direction
+    pub fn neighbors_of(mut self, node_id: NodeId) -> Self {
+        let neighbors: HashSet<NodeId> = self.graph.neighbors(node_id).into_iter().collect();
+        self.filters
+            .push(Box::new(move |node| neighbors.contains(&node.id)));
+        self
+    }
+
+    /// Iterate over matching node IDs
+    pub fn ids(self) -> impl Iterator<Item = NodeId> + 'a {
+        self.entries().map(|(id, _)| id)
+    }
+
+    /// Iterate over matching node entries
+    pub fn entries(self) -> impl Iterator<Item = (NodeId, Cow<'a, NodeEntry<G::Node>>)> + 'a {
+        let (graph, filters) = (self.graph, self.filters);
+        graph
+            .node_ids()
+            .filter_map(move |id| graph.node_entry(id).map(|node| (id, node)))
+            .filter(move |(_, node)| filters.iter().all(|f| f(node)))
+    }
+
+    /// Count matching nodes
+    pub fn count(self) -> usize {
+        self.entries().count()
+    }
+
+    /// Get the first matching node, if any
+    pub fn first(self) -> Option<(NodeId, Cow<'a, NodeEntry<G::Node>>)> {
+        self.entries().next()
+    }
+}
+
+/// Query builder over the edges of a graph view
+pub struct EdgeQuery<'a, G: GraphView> {
+    graph: &'a G,
+    filters: Vec<EdgePredicate<'a, G::Edge>>,
+}
+
+impl<'a, G: GraphView> EdgeQuery<'a, G> {
+    pub fn new(graph: &'a G) -> Self {
+        Self {
+            graph,
+            filters: Vec::new(),
+        }
+    }
+
+    /// Keep edges that have a component of type `T`
+    pub fn with<T: Component + 'static>(mut self) -> Self {
+        self.filters
+            .push(Box::new(|edge| edge.has_component::<T>()));
+        self
+    }
+
+    /// Keep edges that do not have a component of type `T`
+    pub fn without<T: Component + 'static>(mut self) -> Self {
+        self.filters
+            .push(Box::new(|edge| !edge.has_component::<T>()));
+        self
+    }
+
+    /// Keep edges whose component of type `T` satisfies the predicate
+    pub fn where_component<T: Component + 'static>(
+        mut self,
+        predicate: impl Fn(&T) -> bool + 'a,
+    ) -> Self {
+        self.filters.push(Box::new(move |edge| {
+            edge.get_component::<T>().is_some_and(&predicate)
+        }));
+        self
+    }
+
+    /// Keep edges whose value satisfies the predicate
+    pub fn where_value<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&G::Edge) -> bool + 'a,
+    {
+        self.filters
+            .push(Box::new(move |edge| predicate(&edge.value)));
+        self
+    }
+
+    /// Keep edges that start or end at `node_id`
+    pub fn incident_to(mut self, node_id: NodeId) -> Self {
+        self.filters.push(Box::new(move |edge| {
+            edge.source == node_id || edge.target == node_id
+        }));
+        self
+    }
+
+    /// Keep edges that start at `node_id`
+    pub fn from(mut self, node_id: NodeId) -> Self {
+        self.filters
+            .push(Box::new(move |edge| edge.source == node_id));
+        self
+    }
+
+    /// Keep edges that end at `node_id`
+    pub fn to(mut self, node_id: NodeId) -> Self {
+        self.filters
+            .push(Box::new(move |edge| edge.target == node_id));
+        self
+    }
+
+    /// Iterate over matching edge IDs
+    pub fn ids(self) -> impl Iterator<Item = EdgeId> + 'a {
+        self.entries().map(|(id, _)| id)
+    }
+
+    /// Iterate over matching edge entries
+    pub fn entries(self) -> impl Iterator<Item = (EdgeId, Cow<'a, EdgeEntry<G::Edge>>)> + 'a {
+        let (graph, filters) = (self.graph, self.filters);
+        graph
+            .edge_ids()
+            .filter_map(move |id| graph.edge_entry(id).map(|edge| (id, edge)))
+            .filter(move |(_, edge)| filters.iter().all(|f| f(edge)))
+    }
+
+    /// Count matching edges
+    pub fn count(self) -> usize {
+        self.entries().count()
+    }
+
+    /// Get the first matching edge, if any
+    pub fn first(self) -> Option<(EdgeId, Cow<'a, EdgeEntry<G::Edge>>)> {
+        self.entries().next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context_graph::ContextGraph;
+
+    #[test]
+    fn test_combined_node_filters() {
+        let mut graph = ContextGraph::<&str, &str>::new("QueryTest");
+
+        let a = graph.add_node("A");
+        let b = graph.add_node("B");
+        let c = graph.add_node("C");
+
+        graph
+            .get_node_mut(a)
+            .unwrap()
+            .add_component(Label("Alpha".to_string()))
+            .unwrap();
+        graph
+            .get_node_mut(b)
+            .unwrap()
+            .add_component(Label("Beta".to_string()))
+            .unwrap();
+        graph
+            .get_node_mut(b)
+            .unwrap()
+            .add_component(Metadata {
+                tags: vec!["archived".to_string()],
+                ..Default::default()
+            })
+            .unwrap();
+        graph.add_edge(a, c, "knows").unwrap();
+
+        let labeled: Vec<_> = graph
+            .query_nodes()
+            .with::<Label>()
+            .without::<Metadata>()
+            .ids()
+            .collect();
+        assert_eq!(labeled, vec![a]);
+
+        let archived: Vec<_> = graph
+            .query_nodes()
+            .where_component::<Metadata>(|m| m.tags.contains(&"archived".to_string()))
+            .ids()
+            .collect();
+        assert_eq!(archived, vec![b]);
+
+        let neighbors: Vec<_> = graph.query_nodes().neighbors_of(c).ids().collect();
+        assert_eq!(neighbors, vec![a]);
+    }
+
+    #[test]
+    fn test_edge_queries() {
+        let mut graph = ContextGraph::<&str, &str>::new("EdgeQueryTest");
+
+        let a = graph.add_node("A");
+        let b = graph.add_node("B");
+        let c = graph.add_node("C");
+
+        let ab = graph.add_edge(a, b, "owns").unwrap();
+        let bc = graph.add_edge(b, c, "reads").unwrap();
+        graph
+            .get_edge_mut(bc)
+            .unwrap()
+            .add_component(Label("Access".to_string()))
+            .unwrap();
+
+        assert_eq!(graph.query_edges_with_component::<Label>(), vec![bc]);
+        assert_eq!(graph.query_edges().incident_to(b).count(), 2);
+        assert_eq!(
+            graph.query_edges().from(a).ids().collect::<Vec<_>>(),
+            vec![ab]
+        );
+        assert_eq!(
+            graph
+                .query_edges()
+                .where_value(|v| *v == "reads")
+                .with::<Label>()
+                .first()
+                .map(|(id, _)| id),
+            Some(bc)
+        );
+    }
+}
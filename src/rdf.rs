@@ -0,0 +1,789 @@
+//! RDF projection: N-Triples, Turtle and JSON-LD
+//!
+//! Nodes become IRIs built from the graph and node ids by [`RdfOptions`].
+//! Each node gets an `rdf:value` with its value, an `rdfs:label` from
+//! [`Label`], an `rdfs:comment` from the [`Metadata`] description and one
+//! typed literal per `Metadata.properties` entry. Edges become triples whose
+//! predicate is the edge `Label`, or else its value, in the vocabulary
+//! namespace. Since RDF is a set of triples, parallel edges with the same
+//! predicate collapse into one and edge ids are not kept.
+
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Write};
+
+const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+const RDFS: &str = "http://www.w3.org/2000/01/rdf-schema#";
+const XSD: &str = "http://www.w3.org/2001/XMLSchema#";
+
+/// How graph, node and predicate IRIs are built
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RdfOptions {
+    /// Prefix of graph and node IRIs
+    pub base: String,
+    /// Namespace for predicates from edges and `Metadata` properties
+    pub vocabulary: String,
+}
+
+impl Default for RdfOptions {
+    fn default() -> Self {
+        Self {
+            base: "urn:cim:contextgraph:".to_string(),
+            vocabulary: "urn:cim:vocab:".to_string(),
+        }
+    }
+}
+
+impl RdfOptions {
+    pub fn new(base: impl Into<String>, vocabulary: impl Into<String>) -> Self {
+        Self {
+            base: base.into(),
+            vocabulary: vocabulary.into(),
+        }
+    }
+
+    /// `{base}graph/{graph_id}`
+    pub fn graph_iri(&self, graph_id: ContextGraphId) -> String {
+        format!("{}graph/{graph_id}", self.base)
+    }
+
+    /// `{base}graph/{graph_id}/node/{node_id}`
+    pub fn node_iri(&self, graph_id: ContextGraphId, node_id: NodeId) -> String {
+        format!("{}/node/{node_id}", self.graph_iri(graph_id))
+    }
+
+    /// Vocabulary IRI for a name, percent-encoding anything but
+    /// `[A-Za-z0-9_-]`
+    pub fn term_iri(&self, name: &str) -> String {
+        let mut iri = self.vocabulary.clone();
+        for byte in name.bytes() {
+            if byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-' {
+                iri.push(byte as char);
+            } else {
+                let _ = write!(iri, "%{byte:02X}");
+            }
+        }
+        iri
+    }
+
+    /// The name behind a vocabulary IRI, if it is one
+    fn term_name(&self, iri: &str) -> Option<String> {
+        let encoded = iri.strip_prefix(&self.vocabulary)?;
+        let bytes = encoded.as_bytes();
+        let mut decoded = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'%' {
+                let byte = std::str::from_utf8(bytes.get(i + 1..i + 3)?)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())?;
+                decoded.push(byte);
+                i += 3;
+            } else {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+        String::from_utf8(decoded).ok()
+    }
+
+    /// Graph and node ids behind a node IRI, if it is one
+    fn parse_node_iri(&self, iri: &str) -> Option<(ContextGraphId, NodeId)> {
+        let rest = iri.strip_prefix(&self.base)?.strip_prefix("graph/")?;
+        let (graph, node) = rest.split_once("/node/")?;
+        Some((graph.parse().ok()?, node.parse().ok()?))
+    }
+
+    fn parse_graph_iri(&self, iri: &str) -> Option<ContextGraphId> {
+        let rest = iri.strip_prefix(&self.base)?.strip_prefix("graph/")?;
+        rest.parse().ok()
+    }
+}
+
+/// Object of a triple
+#[derive(Debug, Clone, PartialEq)]
+enum Term {
+    Iri(String),
+    Blank(String),
+    Literal {
+        lexical: String,
+        datatype: Option<String>,
+    },
+}
+
+struct Triple {
+    subject: String,
+    predicate: String,
+    object: Term,
+}
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug + Display,
+    E: Clone + Debug + Display,
+{
+    fn triples(&self, options: &RdfOptions) -> Vec<Triple> {
+        let mut triples = Vec::new();
+        if let Some(name) = self
+            .metadata
+            .properties
+            .get("name")
+            .and_then(|v| v.as_str())
+        {
+            triples.push(Triple {
+                subject: options.graph_iri(self.id),
+                predicate: format!("{RDFS}label"),
+                object: literal(&Value::from(name)),
+            });
+        }
+
+        for (node_id, node) in self.get_all_nodes() {
+            let subject = options.node_iri(self.id, node_id);
+            let mut add = |predicate: String, object: Term| {
+                triples.push(Triple {
+                    subject: subject.clone(),
+                    predicate,
+                    object,
+                })
+            };
+            add(
+                format!("{RDF}value"),
+                literal(&Value::from(node.value.to_string())),
+            );
+            if let Some(label) = node.get_component::<Label>() {
+                add(
+                    format!("{RDFS}label"),
+                    literal(&Value::from(label.0.clone())),
+                );
+            }
+            if let Some(metadata) = node.get_component::<Metadata>() {
+                if let Some(description) = &metadata.description {
+                    add(
+                        format!("{RDFS}comment"),
+                        literal(&Value::from(description.clone())),
+                    );
+                }
+                for (name, value) in &metadata.properties {
+                    add(options.term_iri(name), literal(value));
+                }
+            }
+
+            let mut seen = Vec::new();
+            for (_, edge) in self.outgoing_edges(node_id) {
+                let predicate = match edge.get_component::<Label>() {
+                    Some(label) => label.0.clone(),
+                    None => edge.value.to_string(),
+                };
+                let link = (options.term_iri(&predicate), edge.target);
+                if !seen.contains(&link) {
+                    seen.push(link.clone());
+                    add(link.0, Term::Iri(options.node_iri(self.id, link.1)));
+                }
+            }
+        }
+        triples
+    }
+
+    /// Render as N-Triples
+    pub fn to_ntriples(&self, options: &RdfOptions) -> String {
+        let mut out = String::new();
+        for triple in self.triples(options) {
+            let _ = writeln!(
+                out,
+                "<{}> <{}> {} .",
+                triple.subject,
+                triple.predicate,
+                ntriples_term(&triple.object)
+            );
+        }
+        out
+    }
+
+    /// Render as Turtle, grouping triples by subject
+    pub fn to_turtle(&self, options: &RdfOptions) -> String {
+        let nodes = format!("{}/node/", options.graph_iri(self.id));
+        let prefixes = [
+            ("rdf", RDF),
+            ("rdfs", RDFS),
+            ("xsd", XSD),
+            ("v", options.vocabulary.as_str()),
+            ("n", nodes.as_str()),
+        ];
+        let mut out = String::new();
+        for (prefix, namespace) in prefixes {
+            let _ = writeln!(out, "@prefix {prefix}: <{namespace}> .");
+        }
+
+        let mut subject: Option<String> = None;
+        for triple in self.triples(options) {
+            let predicate = turtle_iri(&triple.predicate, &prefixes);
+            let object = match &triple.object {
+                Term::Iri(iri) => turtle_iri(iri, &prefixes),
+                Term::Literal {
+                    lexical,
+                    datatype: Some(datatype),
+                } => format!(
+                    "\"{}\"^^{}",
+                    escape(lexical),
+                    turtle_iri(datatype, &prefixes)
+                ),
+                other => ntriples_term(other),
+            };
+            if subject.as_deref() == Some(triple.subject.as_str()) {
+                let _ = write!(out, " ;\n    {predicate} {object}");
+            } else {
+                if subject.is_some() {
+                    out.push_str(" .\n");
+                }
+                let _ = write!(
+                    out,
+                    "\n{} {predicate} {object}",
+                    turtle_iri(&triple.subject, &prefixes)
+                );
+                subject = Some(triple.subject);
+            }
+        }
+        if subject.is_some() {
+            out.push_str(" .\n");
+        }
+        out
+    }
+
+    /// Render as a JSON-LD document with one `@graph` entry per node
+    pub fn to_jsonld(&self, options: &RdfOptions) -> Value {
+        let compact = |iri: &str| -> String {
+            if let Some(local) = iri.strip_prefix(RDF) {
+                format!("rdf:{local}")
+            } else if let Some(local) = iri.strip_prefix(RDFS) {
+                format!("rdfs:{local}")
+            } else if let Some(local) = iri.strip_prefix(&options.vocabulary) {
+                local.to_string()
+            } else {
+                iri.to_string()
+            }
+        };
+
+        let graph_iri = options.graph_iri(self.id);
+        let mut document = Map::new();
+        document.insert(
+            "@context".to_string(),
+            serde_json::json!({
+                "rdf": RDF,
+                "rdfs": RDFS,
+                "xsd": XSD,
+                "@vocab": options.vocabulary,
+            }),
+        );
+        document.insert("@id".to_string(), graph_iri.clone().into());
+
+        let mut subjects: Vec<(String, Map<String, Value>)> = Vec::new();
+        for triple in self.triples(options) {
+            let object = match triple.object {
+                Term::Iri(iri) => serde_json::json!({ "@id": iri }),
+                Term::Blank(label) => serde_json::json!({ "@id": format!("_:{label}") }),
+                Term::Literal { lexical, datatype } => jsonld_literal(lexical, datatype),
+            };
+            let key = compact(&triple.predicate);
+            let properties = if triple.subject == graph_iri {
+                &mut document
+            } else {
+                match subjects.iter().position(|(s, _)| *s == triple.subject) {
+                    Some(i) => &mut subjects[i].1,
+                    None => {
+                        let mut node = Map::new();
+                        node.insert("@id".to_string(), triple.subject.clone().into());
+                        subjects.push((triple.subject, node));
+                        &mut subjects.last_mut().expect("just pushed").1
+                    }
+                }
+            };
+            match properties.get_mut(&key) {
+                Some(Value::Array(values)) => values.push(object),
+                Some(existing) => *existing = Value::Array(vec![existing.take(), object]),
+                None => {
+                    properties.insert(key, object);
+                }
+            }
+        }
+
+        let nodes = subjects.into_iter().map(|(_, node)| Value::Object(node));
+        document.insert("@graph".to_string(), Value::Array(nodes.collect()));
+        Value::Object(document)
+    }
+}
+
+impl ContextGraph<String, String> {
+    /// Build a graph from N-Triples
+    ///
+    /// Every IRI or blank node used as a subject, or as an object of a
+    /// non-literal triple, becomes a node whose value is its `rdf:value`, or
+    /// else the IRI itself; node IRIs matching `options` keep their ids.
+    /// Triples between nodes become edges valued with the predicate's
+    /// vocabulary name, or its full IRI outside the vocabulary. Literals
+    /// become `Label` (`rdfs:label`), the `Metadata` description
+    /// (`rdfs:comment`) or typed `Metadata` properties; repeated predicates
+    /// collect into arrays.
+    pub fn from_ntriples(text: &str, options: &RdfOptions) -> GraphResult<Self> {
+        #[derive(Default)]
+        struct Pending {
+            value: Option<String>,
+            label: Option<String>,
+            metadata: Metadata,
+        }
+
+        let mut graph_id = None;
+        let mut name = None;
+        let mut order: Vec<String> = Vec::new();
+        let mut pending: HashMap<String, Pending> = HashMap::new();
+        let mut edges = Vec::new();
+
+        for (index, line) in text.lines().enumerate() {
+            let Some((subject, predicate, object)) =
+                parse_triple(line).map_err(|(column, message)| GraphError::Parse {
+                    format: "N-Triples".to_string(),
+                    line: index + 1,
+                    column,
+                    message,
+                })?
+            else {
+                continue;
+            };
+
+            if let Some(id) = options.parse_graph_iri(&subject) {
+                if predicate == format!("{RDFS}label") {
+                    if let Term::Literal { lexical, .. } = object {
+                        graph_id = Some(id);
+                        name = Some(lexical);
+                        continue;
+                    }
+                }
+            }
+
+            for term in [Some(&subject), object_node(&object)].into_iter().flatten() {
+                if !pending.contains_key(term) {
+                    pending.insert(term.clone(), Pending::default());
+                    order.push(term.clone());
+                }
+            }
+            match object {
+                Term::Literal { lexical, datatype } => {
+                    let node = pending.get_mut(&subject).expect("registered above");
+                    match predicate.strip_prefix(RDFS).or(predicate.strip_prefix(RDF)) {
+                        Some("value") if predicate.starts_with(RDF) => node.value = Some(lexical),
+                        Some("label") => node.label = Some(lexical),
+                        Some("comment") => node.metadata.description = Some(lexical),
+                        _ => {
+                            let key = options.term_name(&predicate).unwrap_or(predicate);
+                            let value = typed_value(lexical, datatype.as_deref());
+                            let properties = &mut node.metadata.properties;
+                            match properties.get_mut(&key) {
+                                Some(Value::Array(values)) => values.push(value),
+                                Some(existing) => {
+                                    *existing = Value::Array(vec![existing.take(), value])
+                                }
+                                None => {
+                                    properties.insert(key, value);
+                                }
+                            }
+                        }
+                    }
+                }
+                Term::Iri(target) | Term::Blank(target) => {
+                    let value = options.term_name(&predicate).unwrap_or(predicate);
+                    edges.push((subject, target, value));
+                }
+            }
+        }
+
+        let mut graph = ContextGraph::new(name.unwrap_or_else(|| "RDF".to_string()));
+        let mut ids = HashMap::new();
+        for term in order {
+            let node = pending
+                .remove(&term)
+                .expect("every ordered term is pending");
+            let parsed = options.parse_node_iri(&term);
+            if let Some((id, _)) = parsed {
+                graph_id.get_or_insert(id);
+            }
+            let node_id = match parsed.filter(|(_, id)| graph.get_node(*id).is_none()) {
+                Some((_, node_id)) => node_id,
+                None => graph.fresh_node_id(),
+            };
+            let mut entry = NodeEntry::with_id(node_id, node.value.unwrap_or_else(|| term.clone()));
+            if let Some(label) = node.label {
+                entry.add_component(Label(label))?;
+            }
+            if node.metadata != Metadata::default() {
+                entry.add_component(node.metadata)?;
+            }
+            ids.insert(term, entry.id);
+            graph.insert_node_entry(entry);
+        }
+        for (source, target, value) in edges {
+            graph.add_edge(ids[&source], ids[&target], value)?;
+        }
+        if let Some(id) = graph_id {
+            graph.id = id;
+        }
+        Ok(graph)
+    }
+}
+
+fn object_node(term: &Term) -> Option<&String> {
+    match term {
+        Term::Iri(iri) | Term::Blank(iri) => Some(iri),
+        Term::Literal { .. } => None,
+    }
+}
+
+fn literal(value: &Value) -> Term {
+    let (lexical, datatype) = match value {
+        Value::String(text) => (text.clone(), None),
+        Value::Bool(flag) => (flag.to_string(), Some(format!("{XSD}boolean"))),
+        Value::Number(number) if number.is_f64() => {
+            (number.to_string(), Some(format!("{XSD}double")))
+        }
+        Value::Number(number) => (number.to_string(), Some(format!("{XSD}integer"))),
+        other => (other.to_string(), Some(format!("{RDF}JSON"))),
+    };
+    Term::Literal { lexical, datatype }
+}
+
+fn typed_value(lexical: String, datatype: Option<&str>) -> Value {
+    let local = datatype.and_then(|d| d.strip_prefix(XSD));
+    let parsed = match (local, datatype) {
+        (Some("boolean"), _) => lexical.parse::<bool>().ok().map(Value::Bool),
+        (Some("integer" | "int" | "long" | "short" | "byte"), _) => {
+            lexical.parse::<i64>().ok().map(Value::from)
+        }
+        (Some("double" | "float" | "decimal"), _) => lexical
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number),
+        (_, Some(datatype)) if datatype == format!("{RDF}JSON") => {
+            serde_json::from_str(&lexical).ok()
+        }
+        _ => None,
+    };
+    parsed.unwrap_or(Value::String(lexical))
+}
+
+fn jsonld_literal(lexical: String, datatype: Option<String>) -> Value {
+    match datatype.as_deref() {
+        None => Value::String(lexical),
+        Some(datatype) => {
+            let value = typed_value(lexical.clone(), Some(datatype));
+            if datatype == format!("{RDF}JSON") {
+                serde_json::json!({ "@value": value, "@type": "@json" })
+            } else if value.is_string() {
+                serde_json::json!({ "@value": lexical, "@type": datatype })
+            } else {
+                value
+            }
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+fn ntriples_term(term: &Term) -> String {
+    match term {
+        Term::Iri(iri) => format!("<{iri}>"),
+        Term::Blank(label) => format!("_:{label}"),
+        Term::Literal {
+            lexical,
+            datatype: None,
+        } => format!("\"{}\"", escape(lexical)),
+        Term::Literal {
+            lexical,
+            datatype: Some(datatype),
+        } => format!("\"{}\"^^<{datatype}>", escape(lexical)),
+    }
+}
+
+/// Prefixed name when one of `prefixes` fits, otherwise `<iri>`
+fn turtle_iri(iri: &str, prefixes: &[(&str, &str)]) -> String {
+    for (prefix, namespace) in prefixes {
+        if let Some(local) = iri.strip_prefix(namespace) {
+            let valid = local
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+                && local
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '%'));
+            if valid {
+                return format!("{prefix}:{local}");
+            }
+        }
+    }
+    format!("<{iri}>")
+}
+
+/// Subject, predicate and object of a parsed line
+type ParsedTriple = (String, String, Term);
+
+/// Parse one N-Triples line; `None` for blank lines and comments
+///
+/// Errors carry a 1-based column.
+fn parse_triple(line: &str) -> Result<Option<ParsedTriple>, (usize, String)> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut pos = 0;
+    let skip_ws = |pos: &mut usize| {
+        while chars.get(*pos).is_some_and(|c| c.is_whitespace()) {
+            *pos += 1;
+        }
+    };
+    let fail = |pos: usize, message: &str| Err((pos + 1, message.to_string()));
+
+    skip_ws(&mut pos);
+    if pos == chars.len() || chars[pos] == '#' {
+        return Ok(None);
+    }
+
+    let mut terms = Vec::new();
+    for slot in ["subject", "predicate", "object"] {
+        skip_ws(&mut pos);
+        let start = pos;
+        let term = match chars.get(pos) {
+            Some('<') => {
+                let end = match chars[pos..].iter().position(|c| *c == '>') {
+                    Some(offset) => pos + offset,
+                    None => return fail(start, "unterminated IRI"),
+                };
+                let iri = unescape(&chars[pos + 1..end]).map_err(|m| (start + 1, m))?;
+                pos = end + 1;
+                Term::Iri(iri)
+            }
+            Some('_') if slot != "predicate" && chars.get(pos + 1) == Some(&':') => {
+                pos += 2;
+                let label_start = pos;
+                while chars
+                    .get(pos)
+                    .is_some_and(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.'))
+                {
+                    pos += 1;
+                }
+                // A trailing '.' ends the statement, not the label
+                while pos > label_start && chars[pos - 1] == '.' {
+                    pos -= 1;
+                }
+                Term::Blank(chars[label_start..pos].iter().collect())
+            }
+            Some('"') if slot == "object" => {
+                let mut end = pos + 1;
+                while end < chars.len() && chars[end] != '"' {
+                    end += if chars[end] == '\\' { 2 } else { 1 };
+                }
+                if end >= chars.len() {
+                    return fail(start, "unterminated literal");
+                }
+                let lexical = unescape(&chars[pos + 1..end]).map_err(|m| (start + 1, m))?;
+                pos = end + 1;
+                let datatype = if chars[pos..].starts_with(&['^', '^', '<']) {
+                    let close = match chars[pos..].iter().position(|c| *c == '>') {
+                        Some(offset) => pos + offset,
+                        None => return fail(pos, "unterminated datatype IRI"),
+                    };
+                    let datatype = chars[pos + 3..close].iter().collect();
+                    pos = close + 1;
+                    Some(datatype)
+                } else {
+                    if chars.get(pos) == Some(&'@') {
+                        while chars
+                            .get(pos + 1)
+                            .is_some_and(|c| c.is_alphanumeric() || *c == '-')
+                        {
+                            pos += 1;
+                        }
+                        pos += 1;
+                    }
+                    None
+                };
+                Term::Literal { lexical, datatype }
+            }
+            _ => return fail(start, &format!("expected {slot}")),
+        };
+        terms.push(term);
+    }
+
+    skip_ws(&mut pos);
+    if chars.get(pos) != Some(&'.') {
+        return fail(pos, "expected '.'");
+    }
+    pos += 1;
+    skip_ws(&mut pos);
+    if pos < chars.len() && chars[pos] != '#' {
+        return fail(pos, "unexpected text after '.'");
+    }
+
+    let object = terms.pop().expect("three terms");
+    let predicate = match terms.pop() {
+        Some(Term::Iri(iri)) => iri,
+        _ => unreachable!("predicates are always IRIs"),
+    };
+    let subject = match terms.pop() {
+        Some(Term::Iri(iri)) => iri,
+        Some(Term::Blank(label)) => format!("_:{label}"),
+        _ => unreachable!("subjects are IRIs or blank nodes"),
+    };
+    let object = match object {
+        Term::Blank(label) => Term::Blank(format!("_:{label}")),
+        other => other,
+    };
+    Ok(Some((subject, predicate, object)))
+}
+
+fn unescape(chars: &[char]) -> Result<String, String> {
+    let mut out = String::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        if chars[i] != '\\' {
+            out.push(chars[i]);
+            i += 1;
+            continue;
+        }
+        let escaped = chars.get(i + 1).copied();
+        i += 2;
+        match escaped {
+            Some('"') => out.push('"'),
+            Some('\\') => out.push('\\'),
+            Some('\'') => out.push('\''),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('b') => out.push('\u{8}'),
+            Some('f') => out.push('\u{c}'),
+            Some(kind @ ('u' | 'U')) => {
+                let len = if kind == 'u' { 4 } else { 8 };
+                let hex: String = chars.get(i..i + len).unwrap_or_default().iter().collect();
+                let c = u32::from_str_radix(&hex, 16)
+                    .ok()
+                    .filter(|_| hex.len() == len)
+                    .and_then(char::from_u32)
+                    .ok_or_else(|| format!("invalid \\{kind} escape"))?;
+                out.push(c);
+                i += len;
+            }
+            _ => return Err("invalid escape".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn org() -> (ContextGraph<String, String>, NodeId, NodeId) {
+        let mut graph = ContextGraph::new("Org");
+        let ceo = graph.add_node("ceo".to_string());
+        let cto = graph.add_node("cto".to_string());
+        let node = graph.get_node_mut(ceo).unwrap();
+        node.add_component(Label("Chief \"Exec\"".to_string()))
+            .unwrap();
+        let mut metadata = Metadata::default();
+        metadata
+            .properties
+            .insert("level".to_string(), serde_json::json!(1));
+        metadata
+            .properties
+            .insert("cost center".to_string(), serde_json::json!("HQ"));
+        node.add_component(metadata).unwrap();
+        graph.add_edge(ceo, cto, "manages".to_string()).unwrap();
+        (graph, ceo, cto)
+    }
+
+    #[test]
+    fn test_ntriples_round_trip() {
+        let (graph, ceo, cto) = org();
+        let options = RdfOptions::new("https://example.org/", "https://example.org/vocab#");
+        let text = graph.to_ntriples(&options);
+
+        let ceo_iri = options.node_iri(graph.id, ceo);
+        assert!(text.contains(&format!(
+            "<{ceo_iri}> <https://example.org/vocab#level> \"1\"^^<{XSD}integer> ."
+        )));
+        assert!(text.contains("<https://example.org/vocab#cost%20center> \"HQ\" ."));
+        assert!(text.contains(&format!(
+            "<{ceo_iri}> <https://example.org/vocab#manages> <{}> .",
+            options.node_iri(graph.id, cto)
+        )));
+
+        let parsed = ContextGraph::from_ntriples(&text, &options).unwrap();
+        assert_eq!(parsed.id, graph.id);
+        assert_eq!(parsed.node_count(), 2);
+        let node = parsed.get_node(ceo).unwrap();
+        assert_eq!(node.value, "ceo");
+        assert_eq!(node.get_component::<Label>().unwrap().0, "Chief \"Exec\"");
+        assert_eq!(
+            node.get_component::<Metadata>(),
+            graph.get_node(ceo).unwrap().get_component::<Metadata>()
+        );
+        let (_, edge) = parsed.get_all_edges().next().unwrap();
+        assert_eq!((edge.source, edge.target), (ceo, cto));
+        assert_eq!(edge.value, "manages");
+        assert_eq!(parsed.to_ntriples(&options), text);
+    }
+
+    #[test]
+    fn test_turtle_and_jsonld() {
+        let (graph, ceo, _) = org();
+        let options = RdfOptions::default();
+
+        let turtle = graph.to_turtle(&options);
+        assert!(turtle.contains("@prefix v: <urn:cim:vocab:> ."));
+        assert!(turtle.contains(&format!("n:{ceo} rdf:value \"ceo\" ;")));
+        assert!(turtle.contains("    v:level \"1\"^^xsd:integer ;"));
+
+        let jsonld = graph.to_jsonld(&options);
+        assert_eq!(jsonld["rdfs:label"], "Org");
+        let node = &jsonld["@graph"][0];
+        assert_eq!(node["@id"], options.node_iri(graph.id, ceo));
+        assert_eq!(node["level"], 1);
+        assert_eq!(node["cost%20center"], "HQ");
+        assert_eq!(
+            jsonld["@graph"][0]["manages"]["@id"],
+            jsonld["@graph"][1]["@id"]
+        );
+    }
+
+    #[test]
+    fn test_ntriples_import_errors_and_blank_nodes() {
+        let text = "_:a <urn:x:knows> _:b .\n# comment\n_:b <urn:x:age> \"3\"^^<http://www.w3.org/2001/XMLSchema#integer> .\n";
+        let graph = ContextGraph::from_ntriples(text, &RdfOptions::default()).unwrap();
+        assert_eq!(graph.node_count(), 2);
+        let (_, edge) = graph.get_all_edges().next().unwrap();
+        assert_eq!(edge.value, "urn:x:knows");
+
+        let err =
+            ContextGraph::from_ntriples("<a> <b> \"open .", &RdfOptions::default()).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                GraphError::Parse {
+                    line: 1,
+                    column: 9,
+                    ..
+                }
+            ),
+            "{err}"
+        );
+    }
+}
@@ -0,0 +1,881 @@
+//! Graph registry for resolving references between ContextGraphs
+//!
+//! A [`GraphRegistry`] owns many [`ContextGraph`]s keyed by their
+//! [`ContextGraphId`]. Nodes carrying a [`GraphReference`] component point at
+//! other graphs; the registry resolves those references (optionally loading
+//! missing graphs on demand), reports dangling references and reference
+//! cycles, and traverses across graph boundaries.
+//!
+//! Relationships that span bounded contexts are stored in the registry as
+//! [`CrossGraphEdge`]s between [`QualifiedNodeId`]s. The registry keeps them
+//! consistent whenever a graph changes: through [`GraphRegistry::remove_node`],
+//! [`GraphRegistry::remove`], [`GraphRegistry::replace`] or the [`GraphMut`]
+//! guard returned by [`GraphRegistry::get_mut`].
+
+use crate::context_graph::ContextGraph;
+use crate::hierarchy::WalkControl;
+use crate::types::*;
+use petgraph::graphmap::DiGraphMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::{self, Debug};
+use std::ops::{Deref, DerefMut};
+
+/// A node address that is unique across graphs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct QualifiedNodeId {
+    pub graph_id: ContextGraphId,
+    pub node_id: NodeId,
+}
+
+impl QualifiedNodeId {
+    pub fn new(graph_id: ContextGraphId, node_id: NodeId) -> Self {
+        Self { graph_id, node_id }
+    }
+}
+
+impl fmt::Display for QualifiedNodeId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.graph_id, self.node_id)
+    }
+}
+
+/// A GraphReference whose target graph is not in the registry
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DanglingReference {
+    /// The node carrying the reference
+    pub source: QualifiedNodeId,
+    /// The graph it points at
+    pub target: ContextGraphId,
+}
+
+/// An edge between nodes that live in different graphs
+#[derive(Debug, Clone)]
+pub struct CrossGraphEdge<E> {
+    pub id: EdgeId,
+    pub source: QualifiedNodeId,
+    pub target: QualifiedNodeId,
+    pub value: E,
+    pub components: ComponentStorage,
+}
+
+impl<E> CrossGraphEdge<E> {
+    pub fn new(source: QualifiedNodeId, target: QualifiedNodeId, value: E) -> Self {
+        Self {
+            id: EdgeId::new(),
+            source,
+            target,
+            value,
+            components: ComponentStorage::new(),
+        }
+    }
+
+    /// Add a component to this edge
+    pub fn add_component<T: Component + 'static>(&mut self, component: T) -> GraphResult<()> {
+        self.components.add(component)
+    }
+
+    /// Get a component from this edge (immutable)
+    pub fn get_component<T: Component + 'static>(&self) -> Option<&T> {
+        self.components.get::<T>()
+    }
+
+    /// Check if the edge touches the given node
+    pub fn touches(&self, address: QualifiedNodeId) -> bool {
+        self.source == address || self.target == address
+    }
+}
+
+/// What to do with cross-graph edges when one of their endpoints is removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReferentialAction {
+    /// Remove the cross-graph edges along with the endpoint
+    #[default]
+    Cascade,
+    /// Refuse the removal while cross-graph edges still use the endpoint
+    Restrict,
+}
+
+type GraphLoader<N, E> = Box<dyn Fn(ContextGraphId) -> Option<ContextGraph<N, E>> + Send + Sync>;
+
+/// The graph a replacement displaced and the cross-graph edges removed with it
+type Replaced<N, E> = (Option<ContextGraph<N, E>>, Vec<CrossGraphEdge<E>>);
+
+/// Owns a set of ContextGraphs and resolves references between them
+pub struct GraphRegistry<N, E> {
+    graphs: HashMap<ContextGraphId, ContextGraph<N, E>>,
+    loader: Option<GraphLoader<N, E>>,
+
+    // Cross-graph edges in insertion order, with an index by ID
+    cross_edges: Vec<CrossGraphEdge<E>>,
+    cross_edge_index: HashMap<EdgeId, usize>,
+}
+
+impl<N, E> Debug for GraphRegistry<N, E>
+where
+    N: Debug,
+    E: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GraphRegistry")
+            .field("graphs", &self.graphs.keys().collect::<Vec<_>>())
+            .field("has_loader", &self.loader.is_some())
+            .field("cross_edges", &self.cross_edges)
+            .finish()
+    }
+}
+
+impl<N, E> Default for GraphRegistry<N, E> {
+    fn default() -> Self {
+        Self {
+            graphs: HashMap::new(),
+            loader: None,
+            cross_edges: Vec::new(),
+            cross_edge_index: HashMap::new(),
+        }
+    }
+}
+
+impl<N, E> GraphRegistry<N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load graphs that are referenced but not yet registered on demand
+    pub fn with_loader(
+        mut self,
+        loader: impl Fn(ContextGraphId) -> Option<ContextGraph<N, E>> + Send + Sync + 'static,
+    ) -> Self {
+        self.loader = Some(Box::new(loader));
+        self
+    }
+
+    /// Register a graph, replacing any graph with the same ID
+    ///
+    /// Fails if the replacement lacks a node that cross-graph edges use; see
+    /// [`GraphRegistry::replace`] to remove those edges instead.
+    pub fn insert(&mut self, graph: ContextGraph<N, E>) -> GraphResult<Option<ContextGraph<N, E>>> {
+        self.replace(graph, ReferentialAction::Restrict)
+            .map(|(previous, _)| previous)
+    }
+
+    /// Register a graph, honouring cross-graph edges whose endpoints it lacks
+    ///
+    /// Returns the graph it replaced and any cross-graph edges removed with it.
+    pub fn replace(
+        &mut self,
+        graph: ContextGraph<N, E>,
+        action: ReferentialAction,
+    ) -> GraphResult<Replaced<N, E>> {
+        let id = graph.id;
+        let missing = |address: QualifiedNodeId| {
+            address.graph_id == id && graph.get_node(address.node_id).is_none()
+        };
+        let stranded = self
+            .cross_edges
+            .iter()
+            .filter(|edge| missing(edge.source) || missing(edge.target))
+            .count();
+        if action == ReferentialAction::Restrict && stranded > 0 {
+            return Err(GraphError::InvariantViolation(format!(
+                "Replacing graph {id} would strand {stranded} cross-graph edge(s)"
+            )));
+        }
+
+        let removed =
+            self.retain_cross_edges(|edge| !missing(edge.source) && !missing(edge.target));
+        Ok((self.graphs.insert(id, graph), removed))
+    }
+
+    /// Remove a graph from the registry
+    ///
+    /// Cross-graph edges touching the graph are removed with it.
+    pub fn remove(&mut self, id: ContextGraphId) -> Option<ContextGraph<N, E>> {
+        let graph = self.graphs.remove(&id)?;
+        self.retain_cross_edges(|edge| edge.source.graph_id != id && edge.target.graph_id != id);
+        Some(graph)
+    }
+
+    /// Get a registered graph
+    pub fn get(&self, id: ContextGraphId) -> Option<&ContextGraph<N, E>> {
+        self.graphs.get(&id)
+    }
+
+    /// Get a registered graph mutably
+    ///
+    /// Cross-graph edges that lose an endpoint are removed when the guard is
+    /// dropped; see [`GraphMut::finish`] to refuse such changes instead.
+    pub fn get_mut(&mut self, id: ContextGraphId) -> Option<GraphMut<'_, N, E>> {
+        let original = self.graphs.get(&id)?.clone();
+        Some(GraphMut {
+            registry: self,
+            original: Some(original),
+            id,
+        })
+    }
+
+    /// Check if a graph is registered
+    pub fn contains(&self, id: ContextGraphId) -> bool {
+        self.graphs.contains_key(&id)
+    }
+
+    /// Get the number of registered graphs
+    pub fn len(&self) -> usize {
+        self.graphs.len()
+    }
+
+    /// Check if no graphs are registered
+    pub fn is_empty(&self) -> bool {
+        self.graphs.is_empty()
+    }
+
+    /// Iterate over all registered graphs
+    pub fn graphs(&self) -> impl Iterator<Item = (ContextGraphId, &ContextGraph<N, E>)> {
+        self.graphs.iter().map(|(id, graph)| (*id, graph))
+    }
+
+    /// Look up the node at a qualified address
+    pub fn get_node(&self, address: QualifiedNodeId) -> Option<&NodeEntry<N>> {
+        self.get(address.graph_id)
+            .and_then(|graph| graph.get_node(address.node_id))
+    }
+
+    /// Remove a node from its graph, honouring cross-graph edges that use it
+    ///
+    /// Returns the removed node and any cross-graph edges removed with it.
+    pub fn remove_node(
+        &mut self,
+        address: QualifiedNodeId,
+        action: ReferentialAction,
+    ) -> GraphResult<(NodeEntry<N>, Vec<CrossGraphEdge<E>>)> {
+        let graph = self
+            .graphs
+            .get(&address.graph_id)
+            .ok_or(GraphError::GraphNotFound(address.graph_id))?;
+        if graph.get_node(address.node_id).is_none() {
+            return Err(GraphError::NodeNotFound(address.node_id));
+        }
+
+        let referencing = self.cross_edges_of(address).count();
+        if action == ReferentialAction::Restrict && referencing > 0 {
+            return Err(GraphError::InvariantViolation(format!(
+                "Node {address} is used by {referencing} cross-graph edge(s)"
+            )));
+        }
+
+        let removed = self.retain_cross_edges(|edge| !edge.touches(address));
+        let node = self
+            .graphs
+            .get_mut(&address.graph_id)
+            .and_then(|graph| graph.remove_node(address.node_id))
+            .ok_or(GraphError::NodeNotFound(address.node_id))?;
+        Ok((node, removed))
+    }
+
+    // Cross-graph edges
+
+    /// Connect nodes in two different registered graphs
+    pub fn add_cross_edge(
+        &mut self,
+        source: QualifiedNodeId,
+        target: QualifiedNodeId,
+        value: E,
+    ) -> GraphResult<EdgeId> {
+        self.insert_cross_edge(CrossGraphEdge::new(source, target, value))
+    }
+
+    /// Insert a prepared cross-graph edge, keeping its ID and components
+    pub fn insert_cross_edge(&mut self, edge: CrossGraphEdge<E>) -> GraphResult<EdgeId> {
+        if edge.source.graph_id == edge.target.graph_id {
+            return Err(GraphError::InvalidOperation(format!(
+                "Both endpoints are in graph {}; use ContextGraph::add_edge instead",
+                edge.source.graph_id
+            )));
+        }
+        for address in [edge.source, edge.target] {
+            self.check_endpoint(address)?;
+        }
+        if self.cross_edge_index.contains_key(&edge.id) {
+            return Err(GraphError::InvalidOperation(format!(
+                "Cross-graph edge {} already exists",
+                edge.id
+            )));
+        }
+
+        let id = edge.id;
+        self.cross_edge_index.insert(id, self.cross_edges.len());
+        self.cross_edges.push(edge);
+        Ok(id)
+    }
+
+    fn check_endpoint(&self, address: QualifiedNodeId) -> GraphResult<()> {
+        let graph = self
+            .graphs
+            .get(&address.graph_id)
+            .ok_or(GraphError::GraphNotFound(address.graph_id))?;
+        match graph.get_node(address.node_id) {
+            Some(_) => Ok(()),
+            None => Err(GraphError::NodeNotFound(address.node_id)),
+        }
+    }
+
+    /// Get a cross-graph edge by ID
+    pub fn get_cross_edge(&self, id: EdgeId) -> Option<&CrossGraphEdge<E>> {
+        self.cross_edge_index
+            .get(&id)
+            .map(|index| &self.cross_edges[*index])
+    }
+
+    /// Get a mutable cross-graph edge by ID
+    pub fn get_cross_edge_mut(&mut self, id: EdgeId) -> Option<&mut CrossGraphEdge<E>> {
+        self.cross_edge_index
+            .get(&id)
+            .map(|index| &mut self.cross_edges[*index])
+    }
+
+    /// Remove a cross-graph edge
+    pub fn remove_cross_edge(&mut self, id: EdgeId) -> Option<CrossGraphEdge<E>> {
+        self.retain_cross_edges(|edge| edge.id != id).pop()
+    }
+
+    /// Iterate over all cross-graph edges in insertion order
+    pub fn cross_edges(&self) -> impl Iterator<Item = &CrossGraphEdge<E>> {
+        self.cross_edges.iter()
+    }
+
+    /// Cross-graph edges leaving a node
+    pub fn cross_edges_from(
+        &self,
+        address: QualifiedNodeId,
+    ) -> impl Iterator<Item = &CrossGraphEdge<E>> {
+        self.cross_edges
+            .iter()
+            .filter(move |edge| edge.source == address)
+    }
+
+    /// Cross-graph edges arriving at a node
+    pub fn cross_edges_to(
+        &self,
+        address: QualifiedNodeId,
+    ) -> impl Iterator<Item = &CrossGraphEdge<E>> {
+        self.cross_edges
+            .iter()
+            .filter(move |edge| edge.target == address)
+    }
+
+    /// Cross-graph edges touching a node in either direction
+    pub fn cross_edges_of(
+        &self,
+        address: QualifiedNodeId,
+    ) -> impl Iterator<Item = &CrossGraphEdge<E>> {
+        self.cross_edges
+            .iter()
+            .filter(move |edge| edge.touches(address))
+    }
+
+    /// Cross-graph edges with an endpoint that no longer exists
+    ///
+    /// The registry removes such edges as graphs change, so this is empty
+    /// unless a [`GraphMut`] guard is still alive.
+    pub fn dangling_cross_edges(&self) -> Vec<EdgeId> {
+        self.cross_edges
+            .iter()
+            .filter(|edge| {
+                self.check_endpoint(edge.source).is_err()
+                    || self.check_endpoint(edge.target).is_err()
+            })
+            .map(|edge| edge.id)
+            .collect()
+    }
+
+    /// Remove cross-graph edges with an endpoint that no longer exists
+    pub fn prune_dangling_cross_edges(&mut self) -> Vec<CrossGraphEdge<E>> {
+        let dangling: HashSet<EdgeId> = self.dangling_cross_edges().into_iter().collect();
+        self.retain_cross_edges(|edge| !dangling.contains(&edge.id))
+    }
+
+    /// Fail if any cross-graph edge has an endpoint that no longer exists
+    pub fn check_cross_edges(&self) -> GraphResult<()> {
+        for edge in &self.cross_edges {
+            self.check_endpoint(edge.source)?;
+            self.check_endpoint(edge.target)?;
+        }
+        Ok(())
+    }
+
+    /// Keep cross-graph edges matching the predicate, returning the others
+    fn retain_cross_edges(
+        &mut self,
+        mut keep: impl FnMut(&CrossGraphEdge<E>) -> bool,
+    ) -> Vec<CrossGraphEdge<E>> {
+        let (kept, removed) = std::mem::take(&mut self.cross_edges)
+            .into_iter()
+            .partition(|edge| keep(edge));
+        self.cross_edges = kept;
+        self.cross_edge_index = self
+            .cross_edges
+            .iter()
+            .enumerate()
+            .map(|(index, edge)| (edge.id, index))
+            .collect();
+        removed
+    }
+
+    /// Resolve a graph ID, loading it through the loader if needed
+    pub fn resolve(&mut self, id: ContextGraphId) -> GraphResult<&ContextGraph<N, E>> {
+        self.ensure_loaded(id)?;
+        self.graphs.get(&id).ok_or(GraphError::GraphNotFound(id))
+    }
+
+    /// Resolve the graph a GraphReference points at
+    pub fn resolve_reference(
+        &mut self,
+        reference: &GraphReference,
+    ) -> GraphResult<&ContextGraph<N, E>> {
+        self.resolve(reference.0)
+    }
+
+    /// Resolve the graph referenced by a node, if it carries a GraphReference
+    pub fn resolve_node(
+        &mut self,
+        address: QualifiedNodeId,
+    ) -> GraphResult<Option<&ContextGraph<N, E>>> {
+        let node = self
+            .get(address.graph_id)
+            .ok_or(GraphError::GraphNotFound(address.graph_id))?
+            .get_node(address.node_id)
+            .ok_or(GraphError::NodeNotFound(address.node_id))?;
+        match node.get_component::<GraphReference>() {
+            Some(reference) => {
+                let target = reference.0;
+                self.resolve(target).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Load every graph reachable through references
+    ///
+    /// Fails with `GraphNotFound` for the first reference that can neither be
+    /// found in the registry nor produced by the loader.
+    pub fn resolve_all(&mut self) -> GraphResult<()> {
+        let mut pending: VecDeque<ContextGraphId> = self.graphs.keys().copied().collect();
+        let mut seen: HashSet<ContextGraphId> = pending.iter().copied().collect();
+
+        while let Some(id) = pending.pop_front() {
+            let targets: Vec<ContextGraphId> = self
+                .references_in(id)
+                .into_iter()
+                .map(|(_, target)| target)
+                .collect();
+            for target in targets {
+                if seen.insert(target) {
+                    self.ensure_loaded(target)?;
+                    pending.push_back(target);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn ensure_loaded(&mut self, id: ContextGraphId) -> GraphResult<()> {
+        if self.graphs.contains_key(&id) {
+            return Ok(());
+        }
+        let graph = self
+            .loader
+            .as_ref()
+            .and_then(|load| load(id))
+            .ok_or(GraphError::GraphNotFound(id))?;
+        if graph.id != id {
+            return Err(GraphError::InvalidOperation(format!(
+                "Loader returned graph {} for {}",
+                graph.id, id
+            )));
+        }
+        self.graphs.insert(id, graph);
+        Ok(())
+    }
+
+    /// All GraphReferences held by nodes of a graph, including nested subgraphs
+    pub fn references_in(&self, id: ContextGraphId) -> Vec<(QualifiedNodeId, ContextGraphId)> {
+        let mut references = Vec::new();
+        if let Some(graph) = self.graphs.get(&id) {
+            graph.walk_hierarchy(|graph, _| {
+                for (node_id, node) in graph.get_all_nodes() {
+                    if let Some(reference) = node.get_component::<GraphReference>() {
+                        references.push((QualifiedNodeId::new(graph.id, node_id), reference.0));
+                    }
+                }
+                WalkControl::Continue
+            });
+        }
+        references
+    }
+
+    /// References whose target graph is not registered
+    ///
+    /// The loader is not consulted; call [`GraphRegistry::resolve_all`] first
+    /// to pull in graphs it can provide.
+    pub fn dangling_references(&self) -> Vec<DanglingReference> {
+        let mut ids: Vec<ContextGraphId> = self.graphs.keys().copied().collect();
+        ids.sort();
+
+        ids.into_iter()
+            .flat_map(|id| self.references_in(id))
+            .filter(|(_, target)| !self.graphs.contains_key(target))
+            .map(|(source, target)| DanglingReference { source, target })
+            .collect()
+    }
+
+    /// Groups of registered graphs that reference each other in a cycle
+    pub fn reference_cycles(&self) -> Vec<Vec<ContextGraphId>> {
+        let mut references = DiGraphMap::<ContextGraphId, ()>::new();
+        for id in self.graphs.keys() {
+            references.add_node(*id);
+            for (_, target) in self.references_in(*id) {
+                if self.graphs.contains_key(&target) {
+                    references.add_edge(*id, target, ());
+                }
+            }
+        }
+
+        petgraph::algo::tarjan_scc(&references)
+            .into_iter()
+            .filter(|scc| scc.len() > 1 || references.contains_edge(scc[0], scc[0]))
+            .collect()
+    }
+
+    /// Fail with `CycleDetected` if any graphs reference each other in a cycle
+    pub fn check_reference_cycles(&self) -> GraphResult<()> {
+        if self.reference_cycles().is_empty() {
+            Ok(())
+        } else {
+            Err(GraphError::CycleDetected)
+        }
+    }
+
+    /// Breadth-first traversal that follows GraphReferences transparently
+    ///
+    /// Outgoing edges are followed within each graph, and outgoing
+    /// cross-graph edges lead into other graphs. When a node carries a
+    /// GraphReference the walk also continues into the referenced graph at
+    /// its entry nodes (nodes without incoming edges, or every node if there
+    /// are none). Referenced graphs are loaded on demand; references that
+    /// cannot be resolved are skipped.
+    pub fn traverse(&mut self, start: QualifiedNodeId) -> GraphResult<Vec<QualifiedNodeId>> {
+        if self.get_node(start).is_none() {
+            return match self.get(start.graph_id) {
+                Some(_) => Err(GraphError::NodeNotFound(start.node_id)),
+                None => Err(GraphError::GraphNotFound(start.graph_id)),
+            };
+        }
+
+        let mut order = Vec::new();
+        let mut visited = HashSet::from([start]);
+        let mut queue = VecDeque::from([start]);
+
+        while let Some(current) = queue.pop_front() {
+            order.push(current);
+
+            let (successors, reference) = {
+                let graph = &self.graphs[&current.graph_id];
+                let reference = graph
+                    .get_node(current.node_id)
+                    .and_then(|node| node.get_component::<GraphReference>())
+                    .map(|reference| reference.0);
+                (graph.successors(current.node_id), reference)
+            };
+
+            let mut next: Vec<QualifiedNodeId> = successors
+                .into_iter()
+                .map(|node_id| QualifiedNodeId::new(current.graph_id, node_id))
+                .chain(self.cross_edges_from(current).map(|edge| edge.target))
+                .filter(|address| self.get_node(*address).is_some())
+                .collect();
+            if let Some(target) = reference {
+                if let Ok(graph) = self.resolve(target) {
+                    next.extend(
+                        entry_nodes(graph)
+                            .into_iter()
+                            .map(|node_id| QualifiedNodeId::new(target, node_id)),
+                    );
+                }
+            }
+
+            for address in next {
+                if visited.insert(address) {
+                    queue.push_back(address);
+                }
+            }
+        }
+
+        Ok(order)
+    }
+}
+
+/// Mutable access to a registered graph, returned by [`GraphRegistry::get_mut`]
+///
+/// Dropping the guard removes cross-graph edges whose endpoint is no longer
+/// in the graph ([`ReferentialAction::Cascade`]).
+pub struct GraphMut<'a, N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    registry: &'a mut GraphRegistry<N, E>,
+    // The graph as it was when the guard was taken, for Restrict
+    original: Option<ContextGraph<N, E>>,
+    id: ContextGraphId,
+}
+
+impl<N, E> GraphMut<'_, N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    /// Apply `action` to cross-graph edges that lost an endpoint
+    ///
+    /// With [`ReferentialAction::Restrict`] the graph is restored to its state
+    /// before the guard was taken and an error is returned if any cross-graph
+    /// edge lost an endpoint. Returns the cross-graph edges removed.
+    pub fn finish(mut self, action: ReferentialAction) -> GraphResult<Vec<CrossGraphEdge<E>>> {
+        let dangling = self.dangling();
+        if action == ReferentialAction::Restrict && !dangling.is_empty() {
+            if let Some(original) = self.original.take() {
+                self.registry.graphs.insert(self.id, original);
+            }
+            return Err(GraphError::InvariantViolation(format!(
+                "Changes to graph {} would strand {} cross-graph edge(s)",
+                self.id,
+                dangling.len()
+            )));
+        }
+        Ok(self.cascade(&dangling))
+    }
+
+    fn dangling(&self) -> HashSet<EdgeId> {
+        let graph = &self.registry.graphs[&self.id];
+        let missing = |address: QualifiedNodeId| {
+            address.graph_id == self.id && graph.get_node(address.node_id).is_none()
+        };
+        self.registry
+            .cross_edges
+            .iter()
+            .filter(|edge| missing(edge.source) || missing(edge.target))
+            .map(|edge| edge.id)
+            .collect()
+    }
+
+    fn cascade(&mut self, dangling: &HashSet<EdgeId>) -> Vec<CrossGraphEdge<E>> {
+        if dangling.is_empty() {
+            return Vec::new();
+        }
+        self.registry
+            .retain_cross_edges(|edge| !dangling.contains(&edge.id))
+    }
+}
+
+impl<N, E> Deref for GraphMut<'_, N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    type Target = ContextGraph<N, E>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.registry.graphs[&self.id]
+    }
+}
+
+impl<N, E> DerefMut for GraphMut<'_, N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.registry
+            .graphs
+            .get_mut(&self.id)
+            .expect("the guard borrows the registry, so the graph is still registered")
+    }
+}
+
+impl<N, E> Drop for GraphMut<'_, N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    fn drop(&mut self) {
+        let dangling = self.dangling();
+        self.cascade(&dangling);
+    }
+}
+
+/// Nodes without incoming edges, or every node if the graph has none
+fn entry_nodes<N, E>(graph: &ContextGraph<N, E>) -> Vec<NodeId>
+where
+    N: Clone + Debug,
+    E: Clone + Debug,
+{
+    let roots: Vec<NodeId> = graph
+        .get_all_nodes()
+        .map(|(id, _)| id)
+        .filter(|id| graph.predecessors(*id).is_empty())
+        .collect();
+    if roots.is_empty() {
+        graph.get_all_nodes().map(|(id, _)| id).collect()
+    } else {
+        roots
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn referencing(
+        name: &str,
+        target: Option<ContextGraphId>,
+    ) -> (ContextGraph<String, ()>, NodeId) {
+        let mut graph = ContextGraph::new(name);
+        let start = graph.add_node(format!("{name}.start"));
+        let portal = graph.add_node(format!("{name}.portal"));
+        graph.add_edge(start, portal, ()).unwrap();
+        if let Some(target) = target {
+            graph
+                .get_node_mut(portal)
+                .unwrap()
+                .add_component(GraphReference(target))
+                .unwrap();
+        }
+        (graph, start)
+    }
+
+    #[test]
+    fn test_resolution_and_dangling_references() {
+        let (records, _) = referencing("records", None);
+        let records_id = records.id;
+        let (hr, hr_start) = referencing("hr", Some(records_id));
+        let hr_id = hr.id;
+
+        let mut registry = GraphRegistry::new();
+        registry.insert(hr).unwrap();
+        assert_eq!(registry.dangling_references().len(), 1);
+        assert!(matches!(
+            registry.resolve(records_id),
+            Err(GraphError::GraphNotFound(id)) if id == records_id
+        ));
+
+        let mut registry =
+            registry.with_loader(move |id| (id == records_id).then(|| records.clone()));
+        registry.resolve_all().unwrap();
+        assert!(registry.contains(records_id));
+        assert!(registry.dangling_references().is_empty());
+
+        let reached = registry
+            .traverse(QualifiedNodeId::new(hr_id, hr_start))
+            .unwrap();
+        assert_eq!(reached.len(), 4);
+        assert_eq!(reached[2].graph_id, records_id);
+        registry.check_reference_cycles().unwrap();
+    }
+
+    #[test]
+    fn test_reference_cycles() {
+        let (mut a, _) = referencing("a", None);
+        let (b, b_start) = referencing("b", Some(a.id));
+        let back = a.add_node("a.back".to_string());
+        a.get_node_mut(back)
+            .unwrap()
+            .add_component(GraphReference(b.id))
+            .unwrap();
+        let (a_id, b_id) = (a.id, b.id);
+
+        let mut registry = GraphRegistry::new();
+        registry.insert(a).unwrap();
+        registry.insert(b).unwrap();
+
+        let cycles = registry.reference_cycles();
+        assert_eq!(cycles.len(), 1);
+        assert!(cycles[0].contains(&a_id) && cycles[0].contains(&b_id));
+        assert!(matches!(
+            registry.check_reference_cycles(),
+            Err(GraphError::CycleDetected)
+        ));
+
+        // Traversal terminates despite the cycle
+        let reached = registry
+            .traverse(QualifiedNodeId::new(b_id, b_start))
+            .unwrap();
+        assert_eq!(reached.len(), 5);
+    }
+
+    #[test]
+    fn test_cross_graph_edges_and_integrity() {
+        let mut hr = ContextGraph::<String, ()>::new("HR");
+        let person = hr.add_node("Alice".to_string());
+        let mut records = ContextGraph::<String, ()>::new("Records");
+        let document = records.add_node("Contract".to_string());
+        let archive = records.add_node("Archive".to_string());
+        records.add_edge(document, archive, ()).unwrap();
+
+        let person = QualifiedNodeId::new(hr.id, person);
+        let document = QualifiedNodeId::new(records.id, document);
+        let archive = QualifiedNodeId::new(records.id, archive);
+
+        let mut registry = GraphRegistry::new();
+        registry.insert(hr).unwrap();
+        registry.insert(records.clone()).unwrap();
+
+        let owns = registry.add_cross_edge(person, document, ()).unwrap();
+        assert!(matches!(
+            registry.add_cross_edge(document, archive, ()),
+            Err(GraphError::InvalidOperation(_))
+        ));
+        assert_eq!(registry.cross_edges_from(person).count(), 1);
+        assert_eq!(registry.get_cross_edge(owns).unwrap().target, document);
+
+        let reached = registry.traverse(person).unwrap();
+        assert_eq!(reached, vec![person, document, archive]);
+
+        assert!(registry
+            .remove_node(document, ReferentialAction::Restrict)
+            .is_err());
+        let (_, removed) = registry
+            .remove_node(document, ReferentialAction::Cascade)
+            .unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(registry.cross_edges().count(), 0);
+
+        // Replacing a graph with a version lacking an endpoint
+        registry.add_cross_edge(person, archive, ()).unwrap();
+        registry.insert(records.clone()).unwrap();
+        let mut without_archive = records;
+        without_archive.remove_node(archive.node_id);
+        assert!(registry.insert(without_archive.clone()).is_err());
+        let (_, removed) = registry
+            .replace(without_archive, ReferentialAction::Cascade)
+            .unwrap();
+        assert_eq!(removed.len(), 1);
+        registry.check_cross_edges().unwrap();
+
+        // Mutating a graph directly goes through a guard
+        registry.add_cross_edge(person, document, ()).unwrap();
+        let mut hr = registry.get_mut(person.graph_id).unwrap();
+        hr.remove_node(person.node_id);
+        assert!(hr.finish(ReferentialAction::Restrict).is_err());
+        assert!(registry.get_node(person).is_some());
+
+        registry
+            .get_mut(person.graph_id)
+            .unwrap()
+            .remove_node(person.node_id);
+        assert_eq!(registry.cross_edges().count(), 0);
+        registry.check_cross_edges().unwrap();
+    }
+}
@@ -0,0 +1,637 @@
+//! Conflict-free replicated graphs for offline collaboration
+//!
+//! A [`ReplicatedGraph`] is one replica of a shared graph. Local edits are
+//! applied immediately and queued as serializable [`GraphOp`]s; replicas
+//! exchange those ops in any order, any number of times, and converge to the
+//! same graph once they have seen the same set.
+//!
+//! - Nodes and edges are add-wins observed-remove sets: a removal only
+//!   cancels the additions its replica had seen, so a concurrent re-add
+//!   survives.
+//! - Values, edge endpoints and components are last-writer-wins registers
+//!   ordered by [`Stamp`], a Lamport clock with the replica id as tie-break.
+//! - An edge is visible only while both of its nodes are; removing a node
+//!   hides its edges rather than deleting them.
+//!
+//! Components travel in their JSON encoding, so their types need a codec in
+//! the replica's [`ComponentCodecs`].
+//!
+//! New node and edge IDs come from the replica's [`IdGenerator`]. IDs must
+//! not collide across replicas, so deterministic generators need a different
+//! seed per replica.
+
+use crate::binary::ComponentCodecs;
+use crate::context_graph::ContextGraph;
+use crate::diff::ChangeTarget;
+use crate::ids::{IdGenerator, RandomIds};
+use crate::types::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::any::TypeId;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{self, Debug};
+use uuid::Uuid;
+
+/// Identifies one replica of a shared graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ReplicaId(Uuid);
+
+impl ReplicaId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for ReplicaId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Display for ReplicaId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Lamport timestamp; unique per operation and totally ordered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Stamp {
+    pub counter: u64,
+    pub replica: ReplicaId,
+}
+
+/// A replicated edit, exchanged between replicas
+///
+/// Additions carry a fresh tag; removals carry the tags their replica had
+/// observed. Component data of `None` removes the component.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum GraphOp<N, E> {
+    AddNode {
+        id: NodeId,
+        tag: Stamp,
+        value: N,
+    },
+    RemoveNode {
+        id: NodeId,
+        observed: Vec<Stamp>,
+    },
+    SetNodeValue {
+        id: NodeId,
+        stamp: Stamp,
+        value: N,
+    },
+    AddEdge {
+        id: EdgeId,
+        tag: Stamp,
+        source: NodeId,
+        target: NodeId,
+        value: E,
+    },
+    RemoveEdge {
+        id: EdgeId,
+        observed: Vec<Stamp>,
+    },
+    SetEdgeValue {
+        id: EdgeId,
+        stamp: Stamp,
+        value: E,
+    },
+    SetComponent {
+        target: ChangeTarget,
+        name: String,
+        stamp: Stamp,
+        data: Option<Value>,
+    },
+}
+
+/// Last-writer-wins register
+#[derive(Debug, Clone)]
+struct Lww<T> {
+    stamp: Stamp,
+    value: T,
+}
+
+fn write<T>(register: &mut Option<Lww<T>>, stamp: Stamp, value: T) {
+    if register
+        .as_ref()
+        .is_none_or(|current| stamp > current.stamp)
+    {
+        *register = Some(Lww { stamp, value });
+    }
+}
+
+/// Observed-remove set membership for one element
+#[derive(Debug, Clone, Default)]
+struct Presence {
+    tags: BTreeSet<Stamp>,
+    removed: BTreeSet<Stamp>,
+}
+
+impl Presence {
+    fn live(&self) -> impl Iterator<Item = Stamp> + '_ {
+        self.tags.difference(&self.removed).copied()
+    }
+
+    fn is_live(&self) -> bool {
+        self.live().next().is_some()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Element<V> {
+    presence: Presence,
+    value: Option<Lww<V>>,
+    components: BTreeMap<String, Lww<Option<Value>>>,
+}
+
+impl<V> Default for Element<V> {
+    fn default() -> Self {
+        Self {
+            presence: Presence::default(),
+            value: None,
+            components: BTreeMap::new(),
+        }
+    }
+}
+
+/// One replica of a conflict-free replicated graph
+pub struct ReplicatedGraph<N, E> {
+    id: ContextGraphId,
+    replica: ReplicaId,
+    clock: u64,
+    nodes: BTreeMap<NodeId, Element<N>>,
+    edges: BTreeMap<EdgeId, Element<E>>,
+    endpoints: BTreeMap<EdgeId, Lww<(NodeId, NodeId)>>,
+    pending: Vec<GraphOp<N, E>>,
+    codecs: ComponentCodecs,
+    id_generator: Box<dyn IdGenerator>,
+}
+
+impl<N, E> ReplicatedGraph<N, E>
+where
+    N: Clone + Debug + 'static + Send + Sync,
+    E: Clone + Debug + 'static + Send + Sync,
+{
+    /// A replica of the shared graph `id`, with the default component codecs
+    pub fn new(id: ContextGraphId, replica: ReplicaId) -> Self {
+        Self::with_codecs(id, replica, ComponentCodecs::default())
+    }
+
+    pub fn with_codecs(id: ContextGraphId, replica: ReplicaId, codecs: ComponentCodecs) -> Self {
+        Self {
+            id,
+            replica,
+            clock: 0,
+            nodes: BTreeMap::new(),
+            edges: BTreeMap::new(),
+            endpoints: BTreeMap::new(),
+            pending: Vec::new(),
+            codecs,
+            id_generator: Box::new(RandomIds),
+        }
+    }
+
+    /// Use `generator` for the IDs of nodes and edges added without one
+    pub fn with_id_generator(mut self, generator: impl IdGenerator + 'static) -> Self {
+        self.id_generator = Box::new(generator);
+        self
+    }
+
+    pub fn id(&self) -> ContextGraphId {
+        self.id
+    }
+
+    pub fn replica_id(&self) -> ReplicaId {
+        self.replica
+    }
+
+    /// Take the ops produced by local edits since the last call
+    pub fn drain_ops(&mut self) -> Vec<GraphOp<N, E>> {
+        std::mem::take(&mut self.pending)
+    }
+
+    fn tick(&mut self) -> Stamp {
+        self.clock += 1;
+        Stamp {
+            counter: self.clock,
+            replica: self.replica,
+        }
+    }
+
+    fn local(&mut self, op: GraphOp<N, E>) {
+        self.apply(op.clone());
+        self.pending.push(op);
+    }
+
+    pub fn add_node(&mut self, value: N) -> NodeId {
+        let mut id = self.id_generator.node_id();
+        while self.nodes.contains_key(&id) {
+            id = self.id_generator.node_id();
+        }
+        self.add_node_with_id(id, value);
+        id
+    }
+
+    /// Add a node under a known id, reviving it if it was removed
+    pub fn add_node_with_id(&mut self, id: NodeId, value: N) {
+        let tag = self.tick();
+        self.local(GraphOp::AddNode { id, tag, value });
+    }
+
+    pub fn remove_node(&mut self, id: NodeId) -> GraphResult<()> {
+        let observed = self
+            .observed(self.nodes.get(&id))
+            .ok_or(GraphError::NodeNotFound(id))?;
+        self.local(GraphOp::RemoveNode { id, observed });
+        Ok(())
+    }
+
+    pub fn set_node_value(&mut self, id: NodeId, value: N) -> GraphResult<()> {
+        if !self.contains_node(id) {
+            return Err(GraphError::NodeNotFound(id));
+        }
+        let stamp = self.tick();
+        self.local(GraphOp::SetNodeValue { id, stamp, value });
+        Ok(())
+    }
+
+    pub fn add_edge(&mut self, source: NodeId, target: NodeId, value: E) -> GraphResult<EdgeId> {
+        for node in [source, target] {
+            if !self.contains_node(node) {
+                return Err(GraphError::NodeNotFound(node));
+            }
+        }
+        let mut id = self.id_generator.edge_id();
+        while self.edges.contains_key(&id) {
+            id = self.id_generator.edge_id();
+        }
+        let tag = self.tick();
+        self.local(GraphOp::AddEdge {
+            id,
+            tag,
+            source,
+            target,
+            value,
+        });
+        Ok(id)
+    }
+
+    pub fn remove_edge(&mut self, id: EdgeId) -> GraphResult<()> {
+        if !self.contains_edge(id) {
+            return Err(GraphError::EdgeNotFound(id));
+        }
+        let observed = self.observed(self.edges.get(&id)).unwrap_or_default();
+        self.local(GraphOp::RemoveEdge { id, observed });
+        Ok(())
+    }
+
+    pub fn set_edge_value(&mut self, id: EdgeId, value: E) -> GraphResult<()> {
+        if !self.contains_edge(id) {
+            return Err(GraphError::EdgeNotFound(id));
+        }
+        let stamp = self.tick();
+        self.local(GraphOp::SetEdgeValue { id, stamp, value });
+        Ok(())
+    }
+
+    /// Set a component on a node or edge, replacing any of the same type
+    pub fn set_component<T: Component + 'static>(
+        &mut self,
+        target: ChangeTarget,
+        component: T,
+    ) -> GraphResult<()> {
+        self.check_target(target)?;
+        let (name, data) = self
+            .codecs
+            .encode_json(&TypeId::of::<T>(), &component)
+            .ok_or_else(|| {
+                GraphError::ComponentNotFound(format!("no codec for {}", component.type_name()))
+            })??;
+        let stamp = self.tick();
+        self.local(GraphOp::SetComponent {
+            target,
+            name: name.to_string(),
+            stamp,
+            data: Some(data),
+        });
+        Ok(())
+    }
+
+    pub fn remove_component<T: Component + 'static>(
+        &mut self,
+        target: ChangeTarget,
+    ) -> GraphResult<()> {
+        self.check_target(target)?;
+        let name = self
+            .codecs
+            .name_of(&TypeId::of::<T>())
+            .ok_or_else(|| GraphError::ComponentNotFound(std::any::type_name::<T>().to_string()))?;
+        let stamp = self.tick();
+        self.local(GraphOp::SetComponent {
+            target,
+            name: name.to_string(),
+            stamp,
+            data: None,
+        });
+        Ok(())
+    }
+
+    fn check_target(&self, target: ChangeTarget) -> GraphResult<()> {
+        match target {
+            ChangeTarget::Node(id) if !self.contains_node(id) => Err(GraphError::NodeNotFound(id)),
+            ChangeTarget::Edge(id) if !self.contains_edge(id) => Err(GraphError::EdgeNotFound(id)),
+            ChangeTarget::Metadata => Err(GraphError::InvalidOperation(
+                "graph metadata has no components".to_string(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    fn observed<V>(&self, element: Option<&Element<V>>) -> Option<Vec<Stamp>> {
+        let live: Vec<Stamp> = element?.presence.live().collect();
+        (!live.is_empty()).then_some(live)
+    }
+
+    /// Apply an op from any replica; applying an op twice has no effect
+    pub fn apply(&mut self, op: GraphOp<N, E>) {
+        match op {
+            GraphOp::AddNode { id, tag, value } => {
+                self.observe(tag);
+                let node = self.nodes.entry(id).or_default();
+                node.presence.tags.insert(tag);
+                write(&mut node.value, tag, value);
+            }
+            GraphOp::RemoveNode { id, observed } => {
+                let node = self.nodes.entry(id).or_default();
+                node.presence.removed.extend(observed);
+            }
+            GraphOp::SetNodeValue { id, stamp, value } => {
+                self.observe(stamp);
+                write(&mut self.nodes.entry(id).or_default().value, stamp, value);
+            }
+            GraphOp::AddEdge {
+                id,
+                tag,
+                source,
+                target,
+                value,
+            } => {
+                self.observe(tag);
+                let edge = self.edges.entry(id).or_default();
+                edge.presence.tags.insert(tag);
+                write(&mut edge.value, tag, value);
+                let mut endpoints = self.endpoints.remove(&id);
+                write(&mut endpoints, tag, (source, target));
+                self.endpoints.extend(endpoints.map(|lww| (id, lww)));
+            }
+            GraphOp::RemoveEdge { id, observed } => {
+                let edge = self.edges.entry(id).or_default();
+                edge.presence.removed.extend(observed);
+            }
+            GraphOp::SetEdgeValue { id, stamp, value } => {
+                self.observe(stamp);
+                write(&mut self.edges.entry(id).or_default().value, stamp, value);
+            }
+            GraphOp::SetComponent {
+                target,
+                name,
+                stamp,
+                data,
+            } => {
+                self.observe(stamp);
+                let components = match target {
+                    ChangeTarget::Node(id) => &mut self.nodes.entry(id).or_default().components,
+                    ChangeTarget::Edge(id) => &mut self.edges.entry(id).or_default().components,
+                    ChangeTarget::Metadata => return,
+                };
+                let mut register = components.remove(&name);
+                write(&mut register, stamp, data);
+                components.extend(register.map(|lww| (name, lww)));
+            }
+        }
+    }
+
+    pub fn apply_all(&mut self, ops: impl IntoIterator<Item = GraphOp<N, E>>) {
+        for op in ops {
+            self.apply(op);
+        }
+    }
+
+    /// Keep the Lamport clock ahead of every stamp seen
+    fn observe(&mut self, stamp: Stamp) {
+        self.clock = self.clock.max(stamp.counter);
+    }
+
+    pub fn contains_node(&self, id: NodeId) -> bool {
+        self.nodes
+            .get(&id)
+            .is_some_and(|node| node.presence.is_live() && node.value.is_some())
+    }
+
+    pub fn contains_edge(&self, id: EdgeId) -> bool {
+        let live = self
+            .edges
+            .get(&id)
+            .is_some_and(|edge| edge.presence.is_live() && edge.value.is_some());
+        live && self.endpoints.get(&id).is_some_and(|endpoints| {
+            let (source, target) = endpoints.value;
+            self.contains_node(source) && self.contains_node(target)
+        })
+    }
+
+    pub fn node_value(&self, id: NodeId) -> Option<&N> {
+        self.contains_node(id)
+            .then(|| self.nodes[&id].value.as_ref().map(|lww| &lww.value))
+            .flatten()
+    }
+
+    pub fn edge_value(&self, id: EdgeId) -> Option<&E> {
+        self.contains_edge(id)
+            .then(|| self.edges[&id].value.as_ref().map(|lww| &lww.value))
+            .flatten()
+    }
+
+    /// Visible nodes, ordered by id
+    pub fn node_ids(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes
+            .keys()
+            .copied()
+            .filter(|id| self.contains_node(*id))
+    }
+
+    /// Visible edges, ordered by id
+    pub fn edge_ids(&self) -> impl Iterator<Item = EdgeId> + '_ {
+        self.edges
+            .keys()
+            .copied()
+            .filter(|id| self.contains_edge(*id))
+    }
+
+    /// The visible graph, with nodes and edges ordered by id
+    ///
+    /// Replicas that have applied the same ops produce identical graphs.
+    pub fn to_context_graph(&self) -> GraphResult<ContextGraph<N, E>> {
+        let mut graph = ContextGraph::new("");
+        graph.id = self.id;
+        for id in self.node_ids() {
+            let node = &self.nodes[&id];
+            graph.insert_node_entry(NodeEntry {
+                id,
+                value: node
+                    .value
+                    .as_ref()
+                    .expect("visible nodes have values")
+                    .value
+                    .clone(),
+                components: self.components(&node.components)?,
+            });
+        }
+        for id in self.edge_ids() {
+            let edge = &self.edges[&id];
+            let (source, target) = self.endpoints[&id].value;
+            graph.insert_edge_entry(EdgeEntry {
+                id,
+                source,
+                target,
+                value: edge
+                    .value
+                    .as_ref()
+                    .expect("visible edges have values")
+                    .value
+                    .clone(),
+                components: self.components(&edge.components)?,
+            })?;
+        }
+        Ok(graph)
+    }
+
+    fn components(
+        &self,
+        registers: &BTreeMap<String, Lww<Option<Value>>>,
+    ) -> GraphResult<ComponentStorage> {
+        let mut storage = ComponentStorage::new();
+        for (name, register) in registers {
+            if let Some(data) = &register.value {
+                storage.add_boxed(self.codecs.decode_json(name, data)?)?;
+            }
+        }
+        Ok(storage)
+    }
+}
+
+impl<N, E> Debug for ReplicatedGraph<N, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ReplicatedGraph")
+            .field("id", &self.id)
+            .field("replica", &self.replica)
+            .field("clock", &self.clock)
+            .field("pending", &self.pending.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::diff;
+
+    type Replica = ReplicatedGraph<String, String>;
+
+    fn replicas(count: usize) -> Vec<Replica> {
+        let id = ContextGraphId::new();
+        (0..count)
+            .map(|_| Replica::new(id, ReplicaId::new()))
+            .collect()
+    }
+
+    /// Deliver every replica's pending ops to every replica, in the given order
+    fn sync(replicas: &mut [Replica], order: &[usize]) {
+        let ops: Vec<Vec<GraphOp<String, String>>> =
+            replicas.iter_mut().map(Replica::drain_ops).collect();
+        for replica in replicas.iter_mut() {
+            for &from in order {
+                replica.apply_all(ops[from].clone());
+            }
+        }
+    }
+
+    fn assert_converged(replicas: &[Replica]) {
+        let graphs: Vec<_> = replicas
+            .iter()
+            .map(|r| r.to_context_graph().unwrap())
+            .collect();
+        for graph in &graphs[1..] {
+            assert!(diff(&graphs[0], graph).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn test_concurrent_edits_converge() {
+        let mut sites = replicas(3);
+        let hub = sites[0].add_node("hub".to_string());
+        sync(&mut sites, &[0]);
+
+        let east = sites[1].add_node("east".to_string());
+        sites[1].add_edge(hub, east, "link".to_string()).unwrap();
+        sites[2].set_node_value(hub, "HUB".to_string()).unwrap();
+        sites[0].set_node_value(hub, "Hub".to_string()).unwrap();
+        sites[0]
+            .set_component(ChangeTarget::Node(hub), Label("central".to_string()))
+            .unwrap();
+
+        // Ops arrive in different orders, some of them twice
+        let ops: Vec<_> = sites.iter_mut().map(Replica::drain_ops).collect();
+        for (replica, order) in sites.iter_mut().zip([[2, 1, 0], [0, 2, 1], [1, 0, 2]]) {
+            for from in order {
+                replica.apply_all(ops[from].clone());
+                replica.apply_all(ops[from].clone());
+            }
+        }
+
+        assert_converged(&sites);
+        let graph = sites[0].to_context_graph().unwrap();
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.edge_count(), 1);
+        // The later Lamport stamp wins; equal counters fall back to replica id
+        let winner = if sites[0].replica_id() > sites[2].replica_id() {
+            "Hub"
+        } else {
+            "HUB"
+        };
+        assert_eq!(sites[1].node_value(hub).unwrap(), winner);
+        assert!(graph.get_node(hub).unwrap().has_component::<Label>());
+    }
+
+    #[test]
+    fn test_add_wins_over_concurrent_remove() {
+        let mut sites = replicas(2);
+        let a = sites[0].add_node("a".to_string());
+        let b = sites[0].add_node("b".to_string());
+        let edge = sites[0].add_edge(a, b, "ab".to_string()).unwrap();
+        sync(&mut sites, &[0]);
+
+        sites[0].remove_node(b).unwrap();
+        sites[1].add_node_with_id(b, "b again".to_string());
+        sync(&mut sites, &[1, 0]);
+
+        assert_converged(&sites);
+        assert_eq!(sites[0].node_value(b).map(String::as_str), Some("b again"));
+        assert!(sites[0].contains_edge(edge));
+
+        // A removal that has seen every addition sticks
+        sites[1].remove_node(b).unwrap();
+        sync(&mut sites, &[1]);
+        assert!(!sites[0].contains_node(b));
+        assert!(!sites[0].contains_edge(edge));
+        assert_converged(&sites);
+
+        let json = serde_json::to_string(&GraphOp::<String, String>::RemoveNode {
+            id: b,
+            observed: vec![],
+        })
+        .unwrap();
+        assert!(json.contains("\"op\":\"remove_node\""));
+    }
+}
@@ -0,0 +1,667 @@
+//! Memory-mapped, read-only graph snapshots
+//!
+//! A snapshot freezes a [`ContextGraph`] into a file laid out for direct
+//! access: fixed-width id columns, id lookup tables sorted by UUID, CSR
+//! adjacency in both directions and offset-indexed value blobs. Opening a
+//! snapshot maps the file and checks its structure; nothing is copied onto
+//! the heap, so many processes mapping the same file share one copy in the
+//! page cache. Values are decoded on access with [`BinaryValue`].
+//!
+//! Snapshots hold structure, values and graph metadata. Components are not
+//! included; use the [binary format](crate::binary) when they are needed.
+//!
+//! File layout (little-endian): the magic `CGSNAP01`, the graph id, node and
+//! edge counts, then a table of `(offset, length)` pairs for the sections
+//! below, each aligned to 8 bytes. Positions are `u32`, offsets `u64`.
+
+use crate::binary::BinaryValue;
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use crate::view::GraphView;
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::marker::PhantomData;
+use std::ops::Range;
+use std::path::Path;
+use uuid::Uuid;
+
+const MAGIC: &[u8; 8] = b"CGSNAP01";
+
+const NODE_IDS: usize = 0;
+const NODE_LOOKUP: usize = 1;
+const NODE_VALUE_OFFSETS: usize = 2;
+const NODE_VALUES: usize = 3;
+const OUT_OFFSETS: usize = 4;
+const OUT_EDGES: usize = 5;
+const IN_OFFSETS: usize = 6;
+const IN_EDGES: usize = 7;
+const EDGE_IDS: usize = 8;
+const EDGE_LOOKUP: usize = 9;
+const EDGE_ENDPOINTS: usize = 10;
+const EDGE_VALUE_OFFSETS: usize = 11;
+const EDGE_VALUES: usize = 12;
+const METADATA: usize = 13;
+const SECTIONS: usize = 14;
+
+const HEADER_LEN: usize = MAGIC.len() + 16 + 8 + 8 + SECTIONS * 16;
+
+/// A frozen graph backed by a memory-mapped snapshot file
+///
+/// Implements [`GraphView`], so code written against the view runs on
+/// snapshots and mutable graphs alike. Nodes and edges keep the storage
+/// order the graph had when the snapshot was written.
+pub struct MappedGraph<N, E> {
+    map: Mmap,
+    id: ContextGraphId,
+    metadata: Metadata,
+    node_count: usize,
+    edge_count: usize,
+    sections: [Range<usize>; SECTIONS],
+    values: PhantomData<fn() -> (N, E)>,
+}
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug + BinaryValue + 'static + Send + Sync,
+    E: Clone + Debug + BinaryValue + 'static + Send + Sync,
+{
+    /// Write a snapshot file for [`MappedGraph::open`]
+    pub fn write_snapshot(&self, path: impl AsRef<Path>) -> GraphResult<()> {
+        let file = File::create(path)?;
+        let mut writer = BufWriter::new(file);
+        self.write_snapshot_to(&mut writer)?;
+        writer.flush().map_err(GraphError::from)
+    }
+
+    /// Stream a snapshot to a writer
+    pub fn write_snapshot_to<W: Write>(&self, mut writer: W) -> GraphResult<()> {
+        let nodes: Vec<(NodeId, &NodeEntry<N>)> = self.get_all_nodes().collect();
+        let edges: Vec<(EdgeId, &EdgeEntry<E>)> = self.get_all_edges().collect();
+        if u32::try_from(nodes.len()).is_err() || u32::try_from(edges.len()).is_err() {
+            return Err(GraphError::InvalidOperation(
+                "snapshots hold at most u32::MAX nodes and edges".to_string(),
+            ));
+        }
+        let node_position: HashMap<NodeId, u32> = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| (*id, i as u32))
+            .collect();
+        let edge_position: HashMap<EdgeId, u32> = edges
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| (*id, i as u32))
+            .collect();
+
+        let mut sections: Vec<Vec<u8>> = vec![Vec::new(); SECTIONS];
+        for (node_id, _) in &nodes {
+            sections[NODE_IDS].extend_from_slice(node_id.as_uuid().as_bytes());
+        }
+        sections[NODE_LOOKUP] = lookup(nodes.iter().map(|(id, _)| id.as_uuid().as_bytes()));
+        (sections[NODE_VALUE_OFFSETS], sections[NODE_VALUES]) =
+            blob(nodes.iter().map(|(_, node)| &node.value))?;
+
+        let out = nodes.iter().map(|(node_id, _)| {
+            self.outgoing_edges(*node_id)
+                .map(|(edge_id, _)| edge_position[&edge_id])
+                .collect::<Vec<_>>()
+        });
+        (sections[OUT_OFFSETS], sections[OUT_EDGES]) = csr(out);
+        let incoming = nodes.iter().map(|(node_id, _)| {
+            self.incoming_edges(*node_id)
+                .map(|(edge_id, _)| edge_position[&edge_id])
+                .collect::<Vec<_>>()
+        });
+        (sections[IN_OFFSETS], sections[IN_EDGES]) = csr(incoming);
+
+        for (edge_id, edge) in &edges {
+            sections[EDGE_IDS].extend_from_slice(edge_id.as_uuid().as_bytes());
+            for endpoint in [edge.source, edge.target] {
+                sections[EDGE_ENDPOINTS].extend_from_slice(&node_position[&endpoint].to_le_bytes());
+            }
+        }
+        sections[EDGE_LOOKUP] = lookup(edges.iter().map(|(id, _)| id.as_uuid().as_bytes()));
+        (sections[EDGE_VALUE_OFFSETS], sections[EDGE_VALUES]) =
+            blob(edges.iter().map(|(_, edge)| &edge.value))?;
+        sections[METADATA] = serde_json::to_vec(&self.metadata)
+            .map_err(|err| GraphError::InvalidOperation(err.to_string()))?;
+
+        let mut header = Vec::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice(self.id.as_uuid().as_bytes());
+        header.extend_from_slice(&(nodes.len() as u64).to_le_bytes());
+        header.extend_from_slice(&(edges.len() as u64).to_le_bytes());
+        let mut offset = HEADER_LEN;
+        for section in &sections {
+            header.extend_from_slice(&(offset as u64).to_le_bytes());
+            header.extend_from_slice(&(section.len() as u64).to_le_bytes());
+            offset += padded(section.len());
+        }
+
+        writer.write_all(&header)?;
+        for section in &sections {
+            writer.write_all(section)?;
+            let padding = padded(section.len()) - section.len();
+            writer.write_all(&[0; 8][..padding])?;
+        }
+        Ok(())
+    }
+}
+
+/// Positions ordered by id, for binary search
+fn lookup<'a>(ids: impl Iterator<Item = &'a [u8; 16]>) -> Vec<u8> {
+    let mut ordered: Vec<(usize, &[u8; 16])> = ids.enumerate().collect();
+    ordered.sort_unstable_by_key(|(_, id)| *id);
+    ordered
+        .into_iter()
+        .flat_map(|(position, _)| (position as u32).to_le_bytes())
+        .collect()
+}
+
+/// Offsets and concatenated encodings of a sequence of values
+fn blob<'a, T: BinaryValue + 'a>(
+    values: impl Iterator<Item = &'a T>,
+) -> GraphResult<(Vec<u8>, Vec<u8>)> {
+    let mut offsets = 0u64.to_le_bytes().to_vec();
+    let mut data = Vec::new();
+    for value in values {
+        value.write_to(&mut data)?;
+        offsets.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    }
+    Ok((offsets, data))
+}
+
+/// Compressed sparse rows: per-node offsets into one list of edge positions
+fn csr(rows: impl Iterator<Item = Vec<u32>>) -> (Vec<u8>, Vec<u8>) {
+    let mut offsets = 0u64.to_le_bytes().to_vec();
+    let mut entries = Vec::new();
+    let mut count = 0u64;
+    for row in rows {
+        count += row.len() as u64;
+        entries.extend(row.into_iter().flat_map(u32::to_le_bytes));
+        offsets.extend_from_slice(&count.to_le_bytes());
+    }
+    (offsets, entries)
+}
+
+fn padded(len: usize) -> usize {
+    len.div_ceil(8) * 8
+}
+
+/// A malformed snapshot, with the byte offset of the bad field when known
+fn corrupt(offset: Option<usize>, message: impl Into<String>) -> GraphError {
+    GraphError::Decode {
+        format: "snapshot".to_string(),
+        offset: offset.map(|offset| offset as u64),
+        message: message.into(),
+    }
+}
+
+fn u64_at(bytes: &[u8], index: usize) -> u64 {
+    let start = index * 8;
+    u64::from_le_bytes(bytes[start..start + 8].try_into().expect("8-byte slice"))
+}
+
+fn u32_at(bytes: &[u8], index: usize) -> usize {
+    let start = index * 4;
+    u32::from_le_bytes(bytes[start..start + 4].try_into().expect("4-byte slice")) as usize
+}
+
+fn id_at(bytes: &[u8], index: usize) -> [u8; 16] {
+    let start = index * 16;
+    bytes[start..start + 16].try_into().expect("16-byte slice")
+}
+
+impl<N, E> MappedGraph<N, E>
+where
+    N: Clone + BinaryValue,
+    E: Clone + BinaryValue,
+{
+    /// Map a snapshot file written by [`ContextGraph::write_snapshot`]
+    ///
+    /// The file must not be modified while it is mapped.
+    pub fn open(path: impl AsRef<Path>) -> GraphResult<Self> {
+        let file = File::open(path)?;
+        // SAFETY: the map is read-only and snapshots are immutable once
+        // written; truncating the file while mapped is documented as misuse
+        let map = unsafe { Mmap::map(&file) }?;
+        Self::from_map(map)
+    }
+
+    fn from_map(map: Mmap) -> GraphResult<Self> {
+        if map.len() < HEADER_LEN || &map[..MAGIC.len()] != MAGIC {
+            return Err(corrupt(Some(0), "not a ContextGraph snapshot"));
+        }
+        let header = &map[MAGIC.len()..HEADER_LEN];
+        let id = ContextGraphId::from_uuid(Uuid::from_bytes(id_at(header, 0)));
+        let count = |index| {
+            usize::try_from(u64_at(&header[16..], index)).map_err(|_| {
+                let offset = MAGIC.len() + 16 + index * 8;
+                corrupt(Some(offset), "element count overflows usize")
+            })
+        };
+        let (node_count, edge_count) = (count(0)?, count(1)?);
+
+        let table = &header[32..];
+        let mut sections: [Range<usize>; SECTIONS] = Default::default();
+        for (index, section) in sections.iter_mut().enumerate() {
+            let start = usize::try_from(u64_at(table, index * 2)).ok();
+            let len = usize::try_from(u64_at(table, index * 2 + 1)).ok();
+            *section = match start.zip(len) {
+                Some((start, len))
+                    if start.checked_add(len).is_some_and(|end| end <= map.len()) =>
+                {
+                    start..start + len
+                }
+                _ => {
+                    let offset = MAGIC.len() + 32 + index * 16;
+                    let message = format!("section {index} lies outside the file");
+                    return Err(corrupt(Some(offset), message));
+                }
+            };
+        }
+
+        let expected = |per: usize, count: usize| per.checked_mul(count);
+        let sizes = [
+            (NODE_IDS, expected(16, node_count)),
+            (NODE_LOOKUP, expected(4, node_count)),
+            (
+                NODE_VALUE_OFFSETS,
+                expected(8, node_count.saturating_add(1)),
+            ),
+            (OUT_OFFSETS, expected(8, node_count.saturating_add(1))),
+            (OUT_EDGES, expected(4, edge_count)),
+            (IN_OFFSETS, expected(8, node_count.saturating_add(1))),
+            (IN_EDGES, expected(4, edge_count)),
+            (EDGE_IDS, expected(16, edge_count)),
+            (EDGE_LOOKUP, expected(4, edge_count)),
+            (EDGE_ENDPOINTS, expected(8, edge_count)),
+            (
+                EDGE_VALUE_OFFSETS,
+                expected(8, edge_count.saturating_add(1)),
+            ),
+        ];
+        for (section, size) in sizes {
+            if Some(sections[section].len()) != size {
+                let message = format!("section {section} has the wrong size");
+                return Err(corrupt(Some(sections[section].start), message));
+            }
+        }
+
+        let metadata = serde_json::from_slice(&map[sections[METADATA].clone()]).map_err(|err| {
+            let message = format!("invalid graph metadata: {err}");
+            corrupt(Some(sections[METADATA].start), message)
+        })?;
+        let graph = Self {
+            map,
+            id,
+            metadata,
+            node_count,
+            edge_count,
+            sections,
+            values: PhantomData,
+        };
+        graph.validate()?;
+        Ok(graph)
+    }
+
+    /// Check every index so accessors never read out of bounds
+    fn validate(&self) -> GraphResult<()> {
+        let offsets_ok = |section, count, end| {
+            let offsets = self.section(section);
+            u64_at(offsets, 0) == 0
+                && u64_at(offsets, count) == end as u64
+                && (0..count).all(|i| u64_at(offsets, i) <= u64_at(offsets, i + 1))
+        };
+        let positions_ok = |section, below| {
+            let positions = self.section(section);
+            (0..positions.len() / 4).all(|i| u32_at(positions, i) < below)
+        };
+        let lookup_ok = |lookup, ids, count| {
+            positions_ok(lookup, count)
+                && (1..count).all(|i| {
+                    let (ids, lookup) = (self.section(ids), self.section(lookup));
+                    id_at(ids, u32_at(lookup, i - 1)) < id_at(ids, u32_at(lookup, i))
+                })
+        };
+
+        let (n, m) = (self.node_count, self.edge_count);
+        let valid = offsets_ok(NODE_VALUE_OFFSETS, n, self.sections[NODE_VALUES].len())
+            && offsets_ok(EDGE_VALUE_OFFSETS, m, self.sections[EDGE_VALUES].len())
+            && offsets_ok(OUT_OFFSETS, n, m)
+            && offsets_ok(IN_OFFSETS, n, m)
+            && positions_ok(OUT_EDGES, m)
+            && positions_ok(IN_EDGES, m)
+            && positions_ok(EDGE_ENDPOINTS, n)
+            && lookup_ok(NODE_LOOKUP, NODE_IDS, n)
+            && lookup_ok(EDGE_LOOKUP, EDGE_IDS, m);
+        if valid {
+            Ok(())
+        } else {
+            Err(corrupt(None, "inconsistent index sections"))
+        }
+    }
+
+    pub fn id(&self) -> ContextGraphId {
+        self.id
+    }
+
+    pub fn metadata(&self) -> &Metadata {
+        &self.metadata
+    }
+
+    /// Encoded node value, borrowed straight from the mapping
+    pub fn node_value_bytes(&self, node_id: NodeId) -> Option<&[u8]> {
+        let position = self.node_position(node_id)?;
+        Some(self.value_bytes(NODE_VALUE_OFFSETS, NODE_VALUES, position))
+    }
+
+    /// Thaw into a mutable graph with the same ids, values and metadata
+    pub fn to_context_graph(&self) -> GraphResult<ContextGraph<N, E>>
+    where
+        N: Debug + 'static + Send + Sync,
+        E: Debug + 'static + Send + Sync,
+    {
+        let mut graph = ContextGraph::new("");
+        graph.id = self.id;
+        graph.metadata = self.metadata.clone();
+        for position in 0..self.node_count {
+            let range = self.value_range(NODE_VALUE_OFFSETS, NODE_VALUES, position);
+            let mut bytes = &self.map[range.clone()];
+            graph.insert_node_entry(NodeEntry {
+                id: self.node_id_at(position),
+                value: N::read_from(&mut bytes)
+                    .map_err(|err| corrupt(Some(range.start), err.to_string()))?,
+                components: ComponentStorage::new(),
+            });
+        }
+        for position in 0..self.edge_count {
+            let (source, target) = self.endpoint_positions(position);
+            let range = self.value_range(EDGE_VALUE_OFFSETS, EDGE_VALUES, position);
+            let mut bytes = &self.map[range.clone()];
+            graph.insert_edge_entry(EdgeEntry {
+                id: self.edge_id_at(position),
+                source: self.node_id_at(source),
+                target: self.node_id_at(target),
+                value: E::read_from(&mut bytes)
+                    .map_err(|err| corrupt(Some(range.start), err.to_string()))?,
+                components: ComponentStorage::new(),
+            })?;
+        }
+        Ok(graph)
+    }
+
+    fn section(&self, section: usize) -> &[u8] {
+        &self.map[self.sections[section].clone()]
+    }
+
+    fn node_id_at(&self, position: usize) -> NodeId {
+        NodeId::from_uuid(Uuid::from_bytes(id_at(self.section(NODE_IDS), position)))
+    }
+
+    fn edge_id_at(&self, position: usize) -> EdgeId {
+        EdgeId::from_uuid(Uuid::from_bytes(id_at(self.section(EDGE_IDS), position)))
+    }
+
+    fn node_position(&self, node_id: NodeId) -> Option<usize> {
+        self.search(
+            NODE_LOOKUP,
+            NODE_IDS,
+            self.node_count,
+            node_id.as_uuid().as_bytes(),
+        )
+    }
+
+    fn edge_position(&self, edge_id: EdgeId) -> Option<usize> {
+        self.search(
+            EDGE_LOOKUP,
+            EDGE_IDS,
+            self.edge_count,
+            edge_id.as_uuid().as_bytes(),
+        )
+    }
+
+    fn search(&self, lookup: usize, ids: usize, count: usize, key: &[u8; 16]) -> Option<usize> {
+        let (lookup, ids) = (self.section(lookup), self.section(ids));
+        let (mut low, mut high) = (0, count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let position = u32_at(lookup, mid);
+            match id_at(ids, position).cmp(key) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Some(position),
+            }
+        }
+        None
+    }
+
+    fn value_bytes(&self, offsets: usize, values: usize, position: usize) -> &[u8] {
+        &self.map[self.value_range(offsets, values, position)]
+    }
+
+    /// Where a value lies in the file
+    fn value_range(&self, offsets: usize, values: usize, position: usize) -> Range<usize> {
+        let offsets = self.section(offsets);
+        let base = self.sections[values].start;
+        let start = u64_at(offsets, position) as usize;
+        let end = u64_at(offsets, position + 1) as usize;
+        base + start..base + end
+    }
+
+    fn endpoint_positions(&self, position: usize) -> (usize, usize) {
+        let endpoints = self.section(EDGE_ENDPOINTS);
+        (
+            u32_at(endpoints, position * 2),
+            u32_at(endpoints, position * 2 + 1),
+        )
+    }
+
+    /// Edge positions in one CSR row
+    fn row(&self, offsets: usize, entries: usize, node: usize) -> impl Iterator<Item = usize> + '_ {
+        let offsets = self.section(offsets);
+        let start = u64_at(offsets, node) as usize;
+        let end = u64_at(offsets, node + 1) as usize;
+        let entries = self.section(entries);
+        (start..end).map(move |i| u32_at(entries, i))
+    }
+
+    fn edge_ids_in(&self, offsets: usize, entries: usize, node_id: NodeId) -> Vec<EdgeId> {
+        self.node_position(node_id)
+            .map(|node| {
+                self.row(offsets, entries, node)
+                    .map(|edge| self.edge_id_at(edge))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl<N, E> GraphView for MappedGraph<N, E>
+where
+    N: Clone + BinaryValue,
+    E: Clone + BinaryValue,
+{
+    type Node = N;
+    type Edge = E;
+
+    fn graph_id(&self) -> ContextGraphId {
+        self.id
+    }
+
+    fn node_count(&self) -> usize {
+        self.node_count
+    }
+
+    fn edge_count(&self) -> usize {
+        self.edge_count
+    }
+
+    fn node_ids(&self) -> Box<dyn Iterator<Item = NodeId> + '_> {
+        Box::new((0..self.node_count).map(|position| self.node_id_at(position)))
+    }
+
+    fn edge_ids(&self) -> Box<dyn Iterator<Item = EdgeId> + '_> {
+        Box::new((0..self.edge_count).map(|position| self.edge_id_at(position)))
+    }
+
+    fn contains_node(&self, node_id: NodeId) -> bool {
+        self.node_position(node_id).is_some()
+    }
+
+    /// Decodes the value; undecodable bytes read as missing
+    fn node_value(&self, node_id: NodeId) -> Option<Cow<'_, N>> {
+        let mut bytes = self.node_value_bytes(node_id)?;
+        N::read_from(&mut bytes).ok().map(Cow::Owned)
+    }
+
+    /// Decodes the value; undecodable bytes read as missing
+    fn edge_value(&self, edge_id: EdgeId) -> Option<Cow<'_, E>> {
+        let position = self.edge_position(edge_id)?;
+        let mut bytes = self.value_bytes(EDGE_VALUE_OFFSETS, EDGE_VALUES, position);
+        E::read_from(&mut bytes).ok().map(Cow::Owned)
+    }
+
+    fn edge_endpoints(&self, edge_id: EdgeId) -> Option<(NodeId, NodeId)> {
+        let (source, target) = self.endpoint_positions(self.edge_position(edge_id)?);
+        Some((self.node_id_at(source), self.node_id_at(target)))
+    }
+
+    fn outgoing_edge_ids(&self, node_id: NodeId) -> Vec<EdgeId> {
+        self.edge_ids_in(OUT_OFFSETS, OUT_EDGES, node_id)
+    }
+
+    fn incoming_edge_ids(&self, node_id: NodeId) -> Vec<EdgeId> {
+        self.edge_ids_in(IN_OFFSETS, IN_EDGES, node_id)
+    }
+
+    fn degree(&self, node_id: NodeId) -> usize {
+        self.node_position(node_id).map_or(0, |node| {
+            self.row(OUT_OFFSETS, OUT_EDGES, node).count()
+                + self.row(IN_OFFSETS, IN_EDGES, node).count()
+        })
+    }
+}
+
+impl<N, E> Debug for MappedGraph<N, E> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MappedGraph")
+            .field("id", &self.id)
+            .field("node_count", &self.node_count)
+            .field("edge_count", &self.edge_count)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traversal::TraversalDirection;
+
+    fn upstream<G: GraphView>(graph: &G, node_id: NodeId) -> Vec<NodeId> {
+        graph
+            .traverse(node_id)
+            .reversed()
+            .bfs()
+            .map(|visit| visit.node_id)
+            .collect()
+    }
+
+    fn snapshot_path(graph_id: ContextGraphId) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("contextgraph-{graph_id}.snapshot"))
+    }
+
+    #[test]
+    fn test_snapshot_matches_graph() {
+        let mut graph = ContextGraph::<String, u32>::new("Frozen");
+        let a = graph.add_node("a".to_string());
+        let b = graph.add_node("b".to_string());
+        let c = graph.add_node("c".to_string());
+        let ab = graph.add_edge(a, b, 1).unwrap();
+        graph.add_edge(a, c, 2).unwrap();
+        graph.add_edge(b, c, 3).unwrap();
+        graph.add_edge(a, b, 4).unwrap();
+
+        let path = snapshot_path(graph.id);
+        graph.write_snapshot(&path).unwrap();
+        let mapped = MappedGraph::<String, u32>::open(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(mapped.id(), graph.id);
+        assert_eq!(mapped.metadata(), &graph.metadata);
+        assert_eq!(mapped.node_ids().collect::<Vec<_>>(), vec![a, b, c]);
+        for node_id in [a, b, c] {
+            assert_eq!(mapped.successors(node_id), graph.successors(node_id));
+            assert_eq!(mapped.neighbors(node_id), graph.neighbors(node_id));
+            assert_eq!(
+                mapped.outgoing_edge_ids(node_id),
+                GraphView::outgoing_edge_ids(&graph, node_id)
+            );
+            assert_eq!(mapped.degree(node_id), graph.degree(node_id));
+        }
+        assert_eq!(mapped.node_value(b).unwrap().as_str(), "b");
+        assert_eq!(*mapped.edge_value(ab).unwrap(), 1);
+        assert_eq!(mapped.edge_endpoints(ab), Some((a, b)));
+        assert_eq!(upstream(&mapped, c), upstream(&graph, c));
+        let order: Vec<_> = mapped
+            .traverse(a)
+            .direction(TraversalDirection::Outgoing)
+            .topological()
+            .unwrap()
+            .map(|visit| (visit.node_id, visit.depth))
+            .collect();
+        assert_eq!(order, vec![(a, 0), (b, 1), (c, 2)]);
+        assert_eq!(
+            mapped
+                .query_edges()
+                .where_value(|value| *value > 1)
+                .from(a)
+                .count(),
+            2
+        );
+        assert!(!mapped.contains_node(NodeId::new()));
+
+        let thawed = mapped.to_context_graph().unwrap();
+        assert_eq!(thawed.get_all_edges().count(), 4);
+        assert_eq!(thawed.get_edge(ab).unwrap().value, 1);
+    }
+
+    #[test]
+    fn test_corrupt_snapshot_rejected() {
+        let mut graph = ContextGraph::<u8, ()>::new("Corrupt");
+        let a = graph.add_node(1);
+        let b = graph.add_node(2);
+        graph.add_edge(a, b, ()).unwrap();
+
+        let mut bytes = Vec::new();
+        graph.write_snapshot_to(&mut bytes).unwrap();
+        // Point the single edge's target past the node column
+        let table = MAGIC.len() + 32;
+        let offset = u64_at(&bytes[table..], EDGE_ENDPOINTS * 2) as usize;
+        bytes[offset + 4] = 9;
+
+        let path = snapshot_path(graph.id);
+        let open = |bytes: &[u8]| {
+            std::fs::write(&path, bytes).unwrap();
+            let err = MappedGraph::<u8, ()>::open(&path).unwrap_err();
+            std::fs::remove_file(&path).unwrap();
+            err
+        };
+        let err = open(&bytes);
+        assert!(
+            matches!(err, GraphError::Decode { offset: None, .. }),
+            "{err}"
+        );
+
+        bytes[..MAGIC.len()].copy_from_slice(b"CGSNAP00");
+        assert_eq!(
+            open(&bytes).to_string(),
+            "snapshot decode error at byte 0: not a ContextGraph snapshot"
+        );
+    }
+}
@@ -0,0 +1,447 @@
+//! Time-bounded nodes and edges
+//!
+//! Attach a [`ValidityInterval`] to a node or edge to say when it holds.
+//! Elements without one are valid at every instant, and an edge only counts
+//! as valid while both of its endpoints are too.
+//!
+//! [`ContextGraph::snapshot_at`] gives a [`GraphView`] of the graph at one
+//! instant, and [`ContextGraph::temporal_path`] finds time-respecting paths,
+//! where each edge is crossed no earlier than the one before it.
+
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use crate::view::GraphView;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fmt::Debug;
+use std::time::SystemTime;
+
+/// Half-open interval `[start, end)` during which an element is valid
+///
+/// A missing bound is unbounded on that side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub struct ValidityInterval {
+    pub start: Option<SystemTime>,
+    pub end: Option<SystemTime>,
+}
+
+impl ValidityInterval {
+    pub fn new(start: SystemTime, end: SystemTime) -> Self {
+        Self {
+            start: Some(start),
+            end: Some(end),
+        }
+    }
+
+    /// Valid from `start` onwards
+    pub fn starting(start: SystemTime) -> Self {
+        Self {
+            start: Some(start),
+            end: None,
+        }
+    }
+
+    /// Valid until, but not including, `end`
+    pub fn until(end: SystemTime) -> Self {
+        Self {
+            start: None,
+            end: Some(end),
+        }
+    }
+
+    /// Valid at every instant
+    pub fn always() -> Self {
+        Self::default()
+    }
+
+    pub fn contains(&self, instant: SystemTime) -> bool {
+        self.start.is_none_or(|start| start <= instant) && self.end.is_none_or(|end| instant < end)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        matches!((self.start, self.end), (Some(start), Some(end)) if start >= end)
+    }
+
+    /// The instants valid in both intervals
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self {
+            start: self.start.max(other.start),
+            end: match (self.end, other.end) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            },
+        }
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        !self.intersection(other).is_empty()
+    }
+
+    /// The earliest instant at or after `instant` inside the interval
+    pub fn earliest_from(&self, instant: SystemTime) -> Option<SystemTime> {
+        let at = self.start.map_or(instant, |start| start.max(instant));
+        self.contains(at).then_some(at)
+    }
+}
+
+impl Component for ValidityInterval {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(*self)
+    }
+    fn type_name(&self) -> &'static str {
+        "ValidityInterval"
+    }
+}
+
+/// One edge crossing on a time-respecting path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TemporalStep {
+    pub edge: EdgeId,
+    pub target: NodeId,
+    /// When the edge is crossed
+    pub at: SystemTime,
+}
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug,
+    E: Clone + Debug,
+{
+    /// When a node is valid; unbounded if it has no interval
+    pub fn node_validity(&self, node_id: NodeId) -> Option<ValidityInterval> {
+        self.get_node(node_id).map(|node| {
+            node.get_component::<ValidityInterval>()
+                .copied()
+                .unwrap_or_default()
+        })
+    }
+
+    /// When an edge is valid, narrowed to when both endpoints are
+    pub fn edge_validity(&self, edge_id: EdgeId) -> Option<ValidityInterval> {
+        let edge = self.get_edge(edge_id)?;
+        let own = edge
+            .get_component::<ValidityInterval>()
+            .copied()
+            .unwrap_or_default();
+        Some(
+            own.intersection(&self.node_validity(edge.source)?)
+                .intersection(&self.node_validity(edge.target)?),
+        )
+    }
+
+    pub fn is_node_valid_at(&self, node_id: NodeId, instant: SystemTime) -> bool {
+        self.node_validity(node_id)
+            .is_some_and(|interval| interval.contains(instant))
+    }
+
+    pub fn is_edge_valid_at(&self, edge_id: EdgeId, instant: SystemTime) -> bool {
+        self.edge_validity(edge_id)
+            .is_some_and(|interval| interval.contains(instant))
+    }
+
+    /// A view of the graph at `instant`, hiding everything not valid then
+    pub fn snapshot_at(&self, instant: SystemTime) -> TemporalSnapshot<'_, N, E> {
+        TemporalSnapshot {
+            graph: self,
+            instant,
+        }
+    }
+
+    /// Earliest arrival time at every node reachable from `start`, leaving at
+    /// `departure` and crossing each edge while it is valid
+    pub fn earliest_arrivals(
+        &self,
+        start: NodeId,
+        departure: SystemTime,
+    ) -> HashMap<NodeId, SystemTime> {
+        self.earliest_arrival_search(start, departure)
+            .into_iter()
+            .map(|(node_id, (arrival, _))| (node_id, arrival))
+            .collect()
+    }
+
+    /// The time-respecting path from `start` to `end` that arrives earliest
+    ///
+    /// Returns the edges crossed in order, empty when `start == end`, or
+    /// `None` if `end` cannot be reached.
+    pub fn temporal_path(
+        &self,
+        start: NodeId,
+        end: NodeId,
+        departure: SystemTime,
+    ) -> Option<Vec<TemporalStep>> {
+        let search = self.earliest_arrival_search(start, departure);
+        search.get(&end)?;
+
+        let mut steps = Vec::new();
+        let mut current = end;
+        while let Some(&(at, Some(edge))) = search.get(&current) {
+            steps.push(TemporalStep {
+                edge,
+                target: current,
+                at,
+            });
+            current = self.get_edge(edge)?.source;
+        }
+        steps.reverse();
+        Some(steps)
+    }
+
+    /// Dijkstra over arrival times; each node maps to its arrival and the
+    /// edge it was reached by
+    fn earliest_arrival_search(
+        &self,
+        start: NodeId,
+        departure: SystemTime,
+    ) -> HashMap<NodeId, (SystemTime, Option<EdgeId>)> {
+        let mut best = HashMap::new();
+        if !self.is_node_valid_at(start, departure) {
+            return best;
+        }
+
+        let mut settled = HashSet::new();
+        let mut queue = BinaryHeap::new();
+        best.insert(start, (departure, None));
+        queue.push(Reverse((departure, start)));
+
+        while let Some(Reverse((time, node_id))) = queue.pop() {
+            if !settled.insert(node_id) {
+                continue;
+            }
+            for (edge_id, edge) in self.outgoing_edges(node_id) {
+                let Some(at) = self
+                    .edge_validity(edge_id)
+                    .and_then(|interval| interval.earliest_from(time))
+                else {
+                    continue;
+                };
+                let improves = best
+                    .get(&edge.target)
+                    .is_none_or(|&(arrival, _)| at < arrival);
+                if improves && !settled.contains(&edge.target) {
+                    best.insert(edge.target, (at, Some(edge_id)));
+                    queue.push(Reverse((at, edge.target)));
+                }
+            }
+        }
+        best
+    }
+}
+
+/// A graph as it stood at one instant
+pub struct TemporalSnapshot<'a, N, E> {
+    graph: &'a ContextGraph<N, E>,
+    instant: SystemTime,
+}
+
+impl<N, E> TemporalSnapshot<'_, N, E>
+where
+    N: Clone + Debug,
+    E: Clone + Debug,
+{
+    pub fn instant(&self) -> SystemTime {
+        self.instant
+    }
+
+    /// Copy the visible part into a standalone graph with the same id
+    pub fn to_context_graph(&self) -> ContextGraph<N, E> {
+        let mut graph = self.graph.clone();
+        let edges: HashSet<EdgeId> = graph
+            .get_all_edges()
+            .map(|(edge_id, _)| edge_id)
+            .filter(|&edge_id| !self.graph.is_edge_valid_at(edge_id, self.instant))
+            .collect();
+        let nodes: HashSet<NodeId> = graph
+            .get_all_nodes()
+            .map(|(node_id, _)| node_id)
+            .filter(|&node_id| !self.graph.is_node_valid_at(node_id, self.instant))
+            .collect();
+        graph.remove_edges(&edges);
+        graph.remove_nodes(&nodes);
+        graph
+    }
+}
+
+impl<N, E> GraphView for TemporalSnapshot<'_, N, E>
+where
+    N: Clone + Debug,
+    E: Clone + Debug,
+{
+    type Node = N;
+    type Edge = E;
+
+    fn graph_id(&self) -> ContextGraphId {
+        self.graph.id
+    }
+
+    fn node_count(&self) -> usize {
+        self.node_ids().count()
+    }
+
+    fn edge_count(&self) -> usize {
+        self.edge_ids().count()
+    }
+
+    fn node_ids(&self) -> Box<dyn Iterator<Item = NodeId> + '_> {
+        Box::new(
+            self.graph
+                .get_all_nodes()
+                .map(|(node_id, _)| node_id)
+                .filter(|&node_id| self.contains_node(node_id)),
+        )
+    }
+
+    fn edge_ids(&self) -> Box<dyn Iterator<Item = EdgeId> + '_> {
+        Box::new(
+            self.graph
+                .get_all_edges()
+                .map(|(edge_id, _)| edge_id)
+                .filter(|&edge_id| self.graph.is_edge_valid_at(edge_id, self.instant)),
+        )
+    }
+
+    fn contains_node(&self, node_id: NodeId) -> bool {
+        self.graph.is_node_valid_at(node_id, self.instant)
+    }
+
+    fn node_value(&self, node_id: NodeId) -> Option<Cow<'_, N>> {
+        self.contains_node(node_id)
+            .then(|| self.graph.get_node_value(node_id).map(Cow::Borrowed))
+            .flatten()
+    }
+
+    fn edge_value(&self, edge_id: EdgeId) -> Option<Cow<'_, E>> {
+        self.graph
+            .is_edge_valid_at(edge_id, self.instant)
+            .then(|| self.graph.get_edge_value(edge_id).map(Cow::Borrowed))
+            .flatten()
+    }
+
+    fn node_entry(&self, node_id: NodeId) -> Option<Cow<'_, NodeEntry<N>>> {
+        self.contains_node(node_id)
+            .then(|| self.graph.get_node(node_id).map(Cow::Borrowed))
+            .flatten()
+    }
+
+    fn edge_entry(&self, edge_id: EdgeId) -> Option<Cow<'_, EdgeEntry<E>>> {
+        self.graph
+            .is_edge_valid_at(edge_id, self.instant)
+            .then(|| self.graph.get_edge(edge_id).map(Cow::Borrowed))
+            .flatten()
+    }
+
+    fn edge_endpoints(&self, edge_id: EdgeId) -> Option<(NodeId, NodeId)> {
+        self.graph
+            .is_edge_valid_at(edge_id, self.instant)
+            .then(|| self.graph.get_edge(edge_id).map(|e| (e.source, e.target)))
+            .flatten()
+    }
+
+    fn outgoing_edge_ids(&self, node_id: NodeId) -> Vec<EdgeId> {
+        self.graph
+            .outgoing_edges(node_id)
+            .map(|(edge_id, _)| edge_id)
+            .filter(|&edge_id| self.graph.is_edge_valid_at(edge_id, self.instant))
+            .collect()
+    }
+
+    fn incoming_edge_ids(&self, node_id: NodeId) -> Vec<EdgeId> {
+        self.graph
+            .incoming_edges(node_id)
+            .map(|(edge_id, _)| edge_id)
+            .filter(|&edge_id| self.graph.is_edge_valid_at(edge_id, self.instant))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_snapshot_hides_invalid_elements() {
+        let mut graph = ContextGraph::<&str, &str>::new("Org");
+        let acme = graph.add_node("acme");
+        let alice = graph.add_node("alice");
+        let bob = graph.add_node("bob");
+        graph
+            .get_node_mut(bob)
+            .unwrap()
+            .add_component(ValidityInterval::starting(at(50)))
+            .unwrap();
+        let job = graph.add_edge(alice, acme, "employed").unwrap();
+        graph
+            .get_edge_mut(job)
+            .unwrap()
+            .add_component(ValidityInterval::new(at(10), at(100)))
+            .unwrap();
+        graph.add_edge(bob, acme, "employed").unwrap();
+
+        let early = graph.snapshot_at(at(20));
+        assert_eq!(early.node_count(), 2);
+        assert!(!early.contains_node(bob));
+        assert_eq!(early.edge_ids().collect::<Vec<_>>(), vec![job]);
+
+        let late = graph.snapshot_at(at(100));
+        assert_eq!(late.node_count(), 3);
+        assert_eq!(late.edge_count(), 1);
+        assert_eq!(late.predecessors(acme), vec![bob]);
+
+        let copy = late.to_context_graph();
+        assert_eq!(copy.id, graph.id);
+        assert_eq!(copy.node_count(), 3);
+        assert!(copy.get_edge(job).is_none());
+    }
+
+    #[test]
+    fn test_temporal_path_respects_time_order() {
+        let mut graph = ContextGraph::<&str, ()>::new("Deployments");
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        let windows = [(a, b, 10, 20), (b, c, 5, 8), (b, c, 30, 40)];
+        let mut edges = Vec::new();
+        for (source, target, start, end) in windows {
+            let edge = graph.add_edge(source, target, ()).unwrap();
+            graph
+                .get_edge_mut(edge)
+                .unwrap()
+                .add_component(ValidityInterval::new(at(start), at(end)))
+                .unwrap();
+            edges.push(edge);
+        }
+
+        let path = graph.temporal_path(a, c, at(0)).unwrap();
+        assert_eq!(
+            path,
+            vec![
+                TemporalStep {
+                    edge: edges[0],
+                    target: b,
+                    at: at(10)
+                },
+                TemporalStep {
+                    edge: edges[2],
+                    target: c,
+                    at: at(30)
+                },
+            ]
+        );
+        assert!(graph.temporal_path(a, c, at(25)).is_none());
+        assert_eq!(graph.temporal_path(a, a, at(25)), Some(Vec::new()));
+
+        let arrivals = graph.earliest_arrivals(b, at(6));
+        assert_eq!(arrivals[&c], at(6));
+    }
+}
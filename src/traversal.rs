@@ -0,0 +1,488 @@
+//! Typed traversal iterators over any [`GraphView`]
+//!
+//! Traversals are configured with [`ContextGraph::traverse`] (or
+//! [`GraphView::traverse`] on any other view, such as a
+//! [`MappedGraph`](crate::MappedGraph)) and then run as breadth-first,
+//! depth-first, post-order or topological iterators. Every iterator yields
+//! [`Visit`]s carrying our stable [`NodeId`], the node entry and the depth at
+//! which the node was discovered - storage indices never leak out. Dropping
+//! the iterator ends the traversal early.
+//!
+//! ```rust,ignore
+//! let reachable: Vec<NodeId> = graph
+//!     .traverse(root)
+//!     .direction(TraversalDirection::Outgoing)
+//!     .filter_edges(|edge| edge.has_component::<Label>())
+//!     .max_depth(3)
+//!     .bfs()
+//!     .map(|visit| visit.node_id)
+//!     .collect();
+//! ```
+
+use crate::context_graph::ContextGraph;
+use crate::types::*;
+use crate::view::GraphView;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Debug;
+
+/// Which edges a traversal follows from each node
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TraversalDirection {
+    /// Follow edges from source to target
+    #[default]
+    Outgoing,
+    /// Follow edges backwards, from target to source
+    Incoming,
+    /// Follow edges in both directions
+    Both,
+}
+
+/// A node reached by a traversal
+///
+/// The entry is borrowed from views that hold entries, such as
+/// [`ContextGraph`], and decoded for views that do not.
+#[derive(Debug, Clone)]
+pub struct Visit<'a, N: Clone> {
+    pub node_id: NodeId,
+    pub node: Cow<'a, NodeEntry<N>>,
+    /// Hops from the start node (or topological layer for topological order)
+    pub depth: usize,
+}
+
+type EdgeFilter<'a, E> = Box<dyn Fn(&EdgeEntry<E>) -> bool + 'a>;
+type NodePrune<'a, N> = Box<dyn Fn(&NodeEntry<N>) -> bool + 'a>;
+
+/// Traversal configuration shared by all traversal iterators
+pub struct Traversal<'a, G: GraphView> {
+    graph: &'a G,
+    start: Option<NodeId>,
+    direction: TraversalDirection,
+    edge_filter: Option<EdgeFilter<'a, G::Edge>>,
+    prune: Option<NodePrune<'a, G::Node>>,
+    max_depth: Option<usize>,
+}
+
+impl<'a, G: GraphView> Traversal<'a, G> {
+    /// Start a traversal at `start`; an unknown start yields nothing
+    pub fn new(graph: &'a G, start: NodeId) -> Self {
+        Self {
+            graph,
+            start: graph.contains_node(start).then_some(start),
+            direction: TraversalDirection::default(),
+            edge_filter: None,
+            prune: None,
+            max_depth: None,
+        }
+    }
+
+    /// Set which edges are followed (default: outgoing)
+    pub fn direction(mut self, direction: TraversalDirection) -> Self {
+        self.direction = direction;
+        self
+    }
+
+    /// Follow incoming edges instead of outgoing ones
+    pub fn reversed(self) -> Self {
+        self.direction(TraversalDirection::Incoming)
+    }
+
+    /// Only follow edges accepted by the predicate
+    pub fn filter_edges(mut self, filter: impl Fn(&EdgeEntry<G::Edge>) -> bool + 'a) -> Self {
+        self.edge_filter = Some(Box::new(filter));
+        self
+    }
+
+    /// Visit matching nodes but do not expand past them
+    pub fn prune(mut self, predicate: impl Fn(&NodeEntry<G::Node>) -> bool + 'a) -> Self {
+        self.prune = Some(Box::new(predicate));
+        self
+    }
+
+    /// Do not expand nodes at or beyond this depth
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = Some(depth);
+        self
+    }
+
+    /// Breadth-first traversal
+    pub fn bfs(self) -> Bfs<'a, G> {
+        let mut queue = VecDeque::new();
+        let mut discovered = HashSet::new();
+        if let Some(start) = self.start {
+            queue.push_back((start, 0));
+            discovered.insert(start);
+        }
+        Bfs {
+            spec: self,
+            queue,
+            discovered,
+        }
+    }
+
+    /// Depth-first traversal, yielding nodes in pre-order
+    pub fn dfs(self) -> Dfs<'a, G> {
+        let stack = self.start.map(|start| (start, 0)).into_iter().collect();
+        Dfs {
+            spec: self,
+            stack,
+            visited: HashSet::new(),
+        }
+    }
+
+    /// Depth-first traversal, yielding each node after all of its descendants
+    pub fn post_order(self) -> DfsPostOrder<'a, G> {
+        let mut stack = Vec::new();
+        let mut visited = HashSet::new();
+        if let Some(start) = self.start {
+            visited.insert(start);
+            let mut children = self.children(start, 0);
+            children.reverse();
+            stack.push((start, 0, children));
+        }
+        DfsPostOrder {
+            spec: self,
+            stack,
+            visited,
+        }
+    }
+
+    /// Topological order of the nodes reachable from the start
+    ///
+    /// A node is yielded only after every reachable predecessor along the
+    /// followed edges. The visit depth is the node's layer: the length of the
+    /// longest followed path from the start. Fails if a cycle is reachable.
+    pub fn topological(self) -> GraphResult<Topological<'a, G::Node>> {
+        let Some(start) = self.start else {
+            return Ok(Topological {
+                order: Vec::new().into_iter(),
+            });
+        };
+
+        // Collect the reachable set honouring depth and prune limits
+        let mut reachable = vec![start];
+        let mut discovered = HashSet::from([start]);
+        let mut queue = VecDeque::from([(start, 0)]);
+        while let Some((node_id, depth)) = queue.pop_front() {
+            for child in self.children(node_id, depth) {
+                if discovered.insert(child) {
+                    reachable.push(child);
+                    queue.push_back((child, depth + 1));
+                }
+            }
+        }
+
+        // Order constraints are every followed edge inside the reachable set
+        let mut in_degree: HashMap<NodeId, usize> =
+            reachable.iter().map(|node_id| (*node_id, 0)).collect();
+        let mut successors: HashMap<NodeId, Vec<NodeId>> = HashMap::new();
+        for node_id in &reachable {
+            let next: Vec<NodeId> = self
+                .followed(*node_id)
+                .into_iter()
+                .filter(|n| discovered.contains(n))
+                .collect();
+            for n in &next {
+                *in_degree.entry(*n).or_default() += 1;
+            }
+            successors.insert(*node_id, next);
+        }
+
+        let mut layer: HashMap<NodeId, usize> = HashMap::new();
+        let mut ready: VecDeque<NodeId> = reachable
+            .iter()
+            .filter(|node_id| in_degree[*node_id] == 0)
+            .copied()
+            .collect();
+        let mut order = Vec::with_capacity(reachable.len());
+        let mut sorted = 0;
+
+        while let Some(node_id) = ready.pop_front() {
+            sorted += 1;
+            let depth = layer.get(&node_id).copied().unwrap_or(0);
+            order.extend(self.visit(node_id, depth));
+            for n in &successors[&node_id] {
+                let entry = layer.entry(*n).or_default();
+                *entry = (*entry).max(depth + 1);
+                if let Some(degree) = in_degree.get_mut(n) {
+                    *degree -= 1;
+                    if *degree == 0 {
+                        ready.push_back(*n);
+                    }
+                }
+            }
+        }
+
+        if sorted < reachable.len() {
+            return Err(GraphError::CycleDetected);
+        }
+
+        Ok(Topological {
+            order: order.into_iter(),
+        })
+    }
+
+    /// Neighbors to expand from a node discovered at `depth`
+    fn children(&self, node_id: NodeId, depth: usize) -> Vec<NodeId> {
+        if self.max_depth.is_some_and(|max| depth >= max) {
+            return Vec::new();
+        }
+        if let Some(prune) = &self.prune {
+            if self
+                .graph
+                .node_entry(node_id)
+                .is_some_and(|node| prune(&node))
+            {
+                return Vec::new();
+            }
+        }
+        self.followed(node_id)
+    }
+
+    /// Neighbors reachable over followed edges, in edge insertion order
+    fn followed(&self, node_id: NodeId) -> Vec<NodeId> {
+        let mut neighbors = Vec::new();
+        if self.direction != TraversalDirection::Incoming {
+            neighbors.extend(
+                self.graph
+                    .outgoing_edge_ids(node_id)
+                    .into_iter()
+                    .filter(|edge_id| self.follows(*edge_id))
+                    .filter_map(|edge_id| self.graph.edge_endpoints(edge_id))
+                    .map(|(_, target)| target),
+            );
+        }
+        if self.direction != TraversalDirection::Outgoing {
+            neighbors.extend(
+                self.graph
+                    .incoming_edge_ids(node_id)
+                    .into_iter()
+                    .filter(|edge_id| self.follows(*edge_id))
+                    .filter_map(|edge_id| self.graph.edge_endpoints(edge_id))
+                    .map(|(source, _)| source),
+            );
+        }
+        neighbors
+    }
+
+    fn follows(&self, edge_id: EdgeId) -> bool {
+        match &self.edge_filter {
+            Some(filter) => self
+                .graph
+                .edge_entry(edge_id)
+                .is_some_and(|edge| filter(&edge)),
+            None => true,
+        }
+    }
+
+    /// The visit for a node, or `None` if the view cannot produce its entry
+    fn visit(&self, node_id: NodeId, depth: usize) -> Option<Visit<'a, G::Node>> {
+        let node = self.graph.node_entry(node_id)?;
+        Some(Visit {
+            node_id,
+            node,
+            depth,
+        })
+    }
+}
+
+/// Breadth-first traversal iterator
+pub struct Bfs<'a, G: GraphView> {
+    spec: Traversal<'a, G>,
+    queue: VecDeque<(NodeId, usize)>,
+    discovered: HashSet<NodeId>,
+}
+
+impl<'a, G: GraphView> Iterator for Bfs<'a, G> {
+    type Item = Visit<'a, G::Node>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node_id, depth)) = self.queue.pop_front() {
+            for child in self.spec.children(node_id, depth) {
+                if self.discovered.insert(child) {
+                    self.queue.push_back((child, depth + 1));
+                }
+            }
+            if let Some(visit) = self.spec.visit(node_id, depth) {
+                return Some(visit);
+            }
+        }
+        None
+    }
+}
+
+/// Depth-first (pre-order) traversal iterator
+pub struct Dfs<'a, G: GraphView> {
+    spec: Traversal<'a, G>,
+    stack: Vec<(NodeId, usize)>,
+    visited: HashSet<NodeId>,
+}
+
+impl<'a, G: GraphView> Iterator for Dfs<'a, G> {
+    type Item = Visit<'a, G::Node>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((node_id, depth)) = self.stack.pop() {
+            if !self.visited.insert(node_id) {
+                continue;
+            }
+            let children = self.spec.children(node_id, depth);
+            self.stack.extend(
+                children
+                    .into_iter()
+                    .rev()
+                    .filter(|child| !self.visited.contains(child))
+                    .map(|child| (child, depth + 1)),
+            );
+            if let Some(visit) = self.spec.visit(node_id, depth) {
+                return Some(visit);
+            }
+        }
+        None
+    }
+}
+
+/// Depth-first post-order traversal iterator
+pub struct DfsPostOrder<'a, G: GraphView> {
+    spec: Traversal<'a, G>,
+    stack: Vec<(NodeId, usize, Vec<NodeId>)>,
+    visited: HashSet<NodeId>,
+}
+
+impl<'a, G: GraphView> Iterator for DfsPostOrder<'a, G> {
+    type Item = Visit<'a, G::Node>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node_id, depth, children) = self.stack.last_mut()?;
+            let (node_id, depth) = (*node_id, *depth);
+
+            // Children are stored reversed so the next one is at the end
+            match children.pop() {
+                Some(child) => {
+                    if self.visited.insert(child) {
+                        let mut grandchildren = self.spec.children(child, depth + 1);
+                        grandchildren.reverse();
+                        self.stack.push((child, depth + 1, grandchildren));
+                    }
+                }
+                None => {
+                    self.stack.pop();
+                    if let Some(visit) = self.spec.visit(node_id, depth) {
+                        return Some(visit);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Topological order iterator
+pub struct Topological<'a, N: Clone> {
+    order: std::vec::IntoIter<Visit<'a, N>>,
+}
+
+impl<'a, N: Clone> Iterator for Topological<'a, N> {
+    type Item = Visit<'a, N>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.order.next()
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.order.size_hint()
+    }
+}
+
+impl<N, E> ContextGraph<N, E>
+where
+    N: Clone + Debug,
+    E: Clone + Debug,
+{
+    /// Configure a traversal starting at `start`
+    pub fn traverse(&self, start: NodeId) -> Traversal<'_, Self> {
+        Traversal::new(self, start)
+    }
+
+    /// Breadth-first traversal over outgoing edges
+    pub fn bfs(&self, start: NodeId) -> Bfs<'_, Self> {
+        self.traverse(start).bfs()
+    }
+
+    /// Depth-first traversal over outgoing edges
+    pub fn dfs(&self, start: NodeId) -> Dfs<'_, Self> {
+        self.traverse(start).dfs()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids<'a, N: Clone + 'a>(visits: impl Iterator<Item = Visit<'a, N>>) -> Vec<NodeId> {
+        visits.map(|v| v.node_id).collect()
+    }
+
+    #[test]
+    fn test_traversal_orders() {
+        let mut graph = ContextGraph::<&str, &str>::new("Traversal");
+
+        let a = graph.add_node("A");
+        let b = graph.add_node("B");
+        let c = graph.add_node("C");
+        let d = graph.add_node("D");
+
+        graph.add_edge(a, b, "next").unwrap();
+        graph.add_edge(a, c, "next").unwrap();
+        graph.add_edge(b, d, "next").unwrap();
+        graph.add_edge(c, d, "skip").unwrap();
+
+        assert_eq!(ids(graph.bfs(a)), vec![a, b, c, d]);
+        assert_eq!(ids(graph.dfs(a)), vec![a, b, d, c]);
+        assert_eq!(ids(graph.traverse(a).post_order()), vec![d, b, c, a]);
+        assert_eq!(ids(graph.traverse(d).reversed().bfs()), vec![d, b, c, a]);
+
+        let depths: Vec<usize> = graph.bfs(a).map(|v| v.depth).collect();
+        assert_eq!(depths, vec![0, 1, 1, 2]);
+
+        let filtered = graph
+            .traverse(a)
+            .filter_edges(|edge| edge.value == "next")
+            .max_depth(1)
+            .bfs();
+        assert_eq!(ids(filtered), vec![a, b, c]);
+
+        let pruned = graph.traverse(a).prune(|node| node.value == "B").dfs();
+        assert_eq!(ids(pruned), vec![a, b, c, d]);
+
+        // Early termination just stops pulling from the iterator
+        assert_eq!(graph.bfs(a).take_while(|v| v.node_id != c).count(), 2);
+    }
+
+    #[test]
+    fn test_topological_layers() {
+        let mut graph = ContextGraph::<&str, ()>::new("Topo");
+
+        let a = graph.add_node("A");
+        let b = graph.add_node("B");
+        let c = graph.add_node("C");
+
+        graph.add_edge(a, b, ()).unwrap();
+        graph.add_edge(b, c, ()).unwrap();
+        graph.add_edge(a, c, ()).unwrap();
+
+        let order: Vec<(NodeId, usize)> = graph
+            .traverse(a)
+            .topological()
+            .unwrap()
+            .map(|v| (v.node_id, v.depth))
+            .collect();
+        assert_eq!(order, vec![(a, 0), (b, 1), (c, 2)]);
+
+        graph.add_edge(c, a, ()).unwrap();
+        assert!(matches!(
+            graph.traverse(a).topological(),
+            Err(GraphError::CycleDetected)
+        ));
+    }
+}
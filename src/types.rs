@@ -4,10 +4,60 @@ use serde::{Deserialize, Serialize};
 use std::any::{Any, TypeId};
 use std::collections::HashMap;
 use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
 use uuid::Uuid;
 
+/// Public UUID conversions shared by the graph, node and edge identifiers
+macro_rules! uuid_identifier {
+    ($name:ident) => {
+        impl $name {
+            /// Wrap an existing UUID, e.g. one derived from an external key
+            pub const fn from_uuid(uuid: Uuid) -> Self {
+                Self(uuid)
+            }
+
+            pub const fn as_uuid(&self) -> &Uuid {
+                &self.0
+            }
+
+            /// Deterministic name-based (v5) ID; the same inputs always give
+            /// the same ID
+            pub fn new_v5(namespace: &Uuid, name: impl AsRef<[u8]>) -> Self {
+                Self(Uuid::new_v5(namespace, name.as_ref()))
+            }
+
+            /// Time-ordered (v7) ID; later IDs sort after earlier ones
+            pub fn new_v7() -> Self {
+                Self(Uuid::now_v7())
+            }
+        }
+
+        impl From<Uuid> for $name {
+            fn from(uuid: Uuid) -> Self {
+                Self(uuid)
+            }
+        }
+
+        impl From<$name> for Uuid {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = uuid::Error;
+
+            /// Parse any UUID form, including the one produced by `Display`
+            fn from_str(text: &str) -> Result<Self, Self::Err> {
+                Uuid::parse_str(text).map(Self)
+            }
+        }
+    };
+}
+
 /// Unique identifier for a ContextGraph
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct ContextGraphId(Uuid);
 
 impl ContextGraphId {
@@ -16,6 +66,8 @@ impl ContextGraphId {
     }
 }
 
+uuid_identifier!(ContextGraphId);
+
 impl Default for ContextGraphId {
     fn default() -> Self {
         Self::new()
@@ -29,7 +81,7 @@ impl fmt::Display for ContextGraphId {
 }
 
 /// Unique identifier for a ConceptGraph
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct ConceptGraphId(Uuid);
 
 impl ConceptGraphId {
@@ -51,7 +103,7 @@ impl fmt::Display for ConceptGraphId {
 }
 
 /// Node identifier within a graph
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct NodeId(Uuid);
 
 impl NodeId {
@@ -60,6 +112,8 @@ impl NodeId {
     }
 }
 
+uuid_identifier!(NodeId);
+
 impl Default for NodeId {
     fn default() -> Self {
         Self::new()
@@ -73,7 +127,7 @@ impl fmt::Display for NodeId {
 }
 
 /// Edge identifier within a graph
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct EdgeId(Uuid);
 
 impl EdgeId {
@@ -82,6 +136,8 @@ impl EdgeId {
     }
 }
 
+uuid_identifier!(EdgeId);
+
 impl Default for EdgeId {
     fn default() -> Self {
         Self::new()
@@ -108,17 +164,16 @@ pub trait Component: Any + Send + Sync {
 }
 
 /// Storage for components attached to a graph element
-/// Components are immutable once added
-#[derive(Default)]
+/// Components are immutable once added, so clones share them and the
+/// storage itself is only copied when a clone is modified
+#[derive(Default, Clone)]
 pub struct ComponentStorage {
-    components: HashMap<TypeId, Box<dyn Component>>,
+    components: Arc<HashMap<TypeId, Arc<Box<dyn Component>>>>,
 }
 
 impl ComponentStorage {
     pub fn new() -> Self {
-        Self {
-            components: HashMap::new(),
-        }
+        Self::default()
     }
 
     /// Add a component (can only be done once per type)
@@ -129,7 +184,19 @@ impl ComponentStorage {
                 component.type_name().to_string(),
             ));
         }
-        self.components.insert(type_id, Box::new(component));
+        Arc::make_mut(&mut self.components).insert(type_id, Arc::new(Box::new(component)));
+        Ok(())
+    }
+
+    /// Add a type-erased component (can only be done once per type)
+    pub fn add_boxed(&mut self, component: Box<dyn Component>) -> Result<(), GraphError> {
+        let type_id = Any::type_id(component.as_any());
+        if self.components.contains_key(&type_id) {
+            return Err(GraphError::ComponentAlreadyExists(
+                component.type_name().to_string(),
+            ));
+        }
+        Arc::make_mut(&mut self.components).insert(type_id, Arc::new(component));
         Ok(())
     }
 
@@ -142,7 +209,24 @@ impl ComponentStorage {
 
     /// Remove a component by type (returns the component)
     pub fn remove<T: Component + 'static>(&mut self) -> Option<Box<dyn Component>> {
-        self.components.remove(&TypeId::of::<T>())
+        self.remove_type(&TypeId::of::<T>())
+    }
+
+    /// Remove a component by its `TypeId`
+    ///
+    /// The component is cloned only if another storage still shares it.
+    pub(crate) fn remove_type(&mut self, type_id: &TypeId) -> Option<Box<dyn Component>> {
+        if !self.components.contains_key(type_id) {
+            return None;
+        }
+        let component = Arc::make_mut(&mut self.components).remove(type_id)?;
+        Some(Arc::try_unwrap(component).unwrap_or_else(|shared| shared.clone_box()))
+    }
+
+    /// Remove a component by type, returning it as its concrete type
+    pub fn take<T: Component + 'static>(&mut self) -> Option<T> {
+        let any: Box<dyn Any> = self.remove::<T>()?;
+        any.downcast::<T>().ok().map(|c| *c)
     }
 
     /// Check if a component type exists
@@ -151,8 +235,10 @@ impl ComponentStorage {
     }
 
     /// Iterate over all components
-    pub fn iter(&self) -> impl Iterator<Item = (&TypeId, &Box<dyn Component>)> {
-        self.components.iter()
+    pub fn iter(&self) -> impl Iterator<Item = (&TypeId, &dyn Component)> {
+        self.components
+            .iter()
+            .map(|(type_id, component)| (type_id, component.as_ref().as_ref()))
     }
 
     /// Get the number of components
@@ -166,16 +252,6 @@ impl ComponentStorage {
     }
 }
 
-impl Clone for ComponentStorage {
-    fn clone(&self) -> Self {
-        let mut storage = ComponentStorage::new();
-        for (type_id, component) in &self.components {
-            storage.components.insert(*type_id, component.clone_box());
-        }
-        storage
-    }
-}
-
 impl fmt::Debug for ComponentStorage {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let component_names: Vec<&str> = self.components.values().map(|c| c.type_name()).collect();
@@ -342,6 +418,27 @@ impl Component for GraphReference {
     }
 }
 
+/// Provenance component (for elements inlined from a nested graph)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Provenance {
+    /// The graph the element was originally defined in
+    pub graph_id: ContextGraphId,
+    /// The node that held that graph as a Subgraph
+    pub container: NodeId,
+}
+
+impl Component for Provenance {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn clone_box(&self) -> Box<dyn Component> {
+        Box::new(self.clone())
+    }
+    fn type_name(&self) -> &'static str {
+        "Provenance"
+    }
+}
+
 /// Subgraph component (for nodes that contain entire graphs)
 #[derive(Debug)]
 pub struct Subgraph<N, E>
@@ -414,6 +511,45 @@ pub enum GraphError {
 
     #[error("Cycle detected in graph")]
     CycleDetected,
+
+    #[error("Node already exists: {0}")]
+    DuplicateNode(NodeId),
+
+    #[error("Edge already exists: {0}")]
+    DuplicateEdge(EdgeId),
+
+    #[error("Conflict: {0}")]
+    Conflict(String),
+
+    #[error("I/O error: {0}")]
+    Io(String),
+
+    #[error("{format} parse error at line {line}, column {column}: {message}")]
+    Parse {
+        format: String,
+        /// 1-based line, or 0 when the location is unknown
+        line: usize,
+        /// 1-based column, or 0 when the location is unknown
+        column: usize,
+        message: String,
+    },
+
+    #[error(
+        "{format} decode error{}: {message}",
+        .offset.map(|offset| format!(" at byte {offset}")).unwrap_or_default()
+    )]
+    Decode {
+        format: String,
+        /// Byte offset into the input, when known
+        offset: Option<u64>,
+        message: String,
+    },
+}
+
+impl From<std::io::Error> for GraphError {
+    fn from(err: std::io::Error) -> Self {
+        GraphError::Io(err.to_string())
+    }
 }
 
 /// Result type for graph operations
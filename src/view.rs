@@ -0,0 +1,269 @@
+//! Read-only graph access shared by every graph representation
+//!
+//! [`GraphView`] is the common surface of the mutable [`ContextGraph`] and
+//! frozen representations such as [`MappedGraph`](crate::MappedGraph), so
+//! analysis code can be written once against either. Implementors provide
+//! id lookup, values and per-node edge lists; adjacency and value queries are
+//! derived from those, and [`Traversal`], [`NodeQuery`] and [`EdgeQuery`] run
+//! on any view.
+
+use crate::context_graph::ContextGraph;
+use crate::query::{EdgeQuery, NodeQuery};
+use crate::traversal::Traversal;
+use crate::types::*;
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fmt::Debug;
+
+/// Read-only access to a graph keyed by stable ids
+///
+/// `node_ids`/`edge_ids` iterate in storage order: insertion order until
+/// something is removed, when the last node (or edge) moves into the freed
+/// slot. The order is stable between mutations, so derived results are
+/// deterministic for a given graph state. Per-node edge lists stay in
+/// insertion order.
+pub trait GraphView {
+    type Node: Clone;
+    type Edge: Clone;
+
+    fn graph_id(&self) -> ContextGraphId;
+    fn node_count(&self) -> usize;
+    fn edge_count(&self) -> usize;
+    fn node_ids(&self) -> Box<dyn Iterator<Item = NodeId> + '_>;
+    fn edge_ids(&self) -> Box<dyn Iterator<Item = EdgeId> + '_>;
+    fn contains_node(&self, node_id: NodeId) -> bool;
+    fn node_value(&self, node_id: NodeId) -> Option<Cow<'_, Self::Node>>;
+    fn edge_value(&self, edge_id: EdgeId) -> Option<Cow<'_, Self::Edge>>;
+
+    /// Source and target of an edge
+    fn edge_endpoints(&self, edge_id: EdgeId) -> Option<(NodeId, NodeId)>;
+
+    /// Edges starting at a node
+    fn outgoing_edge_ids(&self, node_id: NodeId) -> Vec<EdgeId>;
+
+    /// Edges ending at a node
+    fn incoming_edge_ids(&self, node_id: NodeId) -> Vec<EdgeId>;
+
+    /// A node with its components
+    ///
+    /// Views that do not hold components give entries without any.
+    fn node_entry(&self, node_id: NodeId) -> Option<Cow<'_, NodeEntry<Self::Node>>> {
+        let value = self.node_value(node_id)?.into_owned();
+        Some(Cow::Owned(NodeEntry::with_id(node_id, value)))
+    }
+
+    /// An edge with its components
+    ///
+    /// Views that do not hold components give entries without any.
+    fn edge_entry(&self, edge_id: EdgeId) -> Option<Cow<'_, EdgeEntry<Self::Edge>>> {
+        let (source, target) = self.edge_endpoints(edge_id)?;
+        let value = self.edge_value(edge_id)?.into_owned();
+        Some(Cow::Owned(EdgeEntry {
+            id: edge_id,
+            source,
+            target,
+            value,
+            components: ComponentStorage::new(),
+        }))
+    }
+
+    /// Get the nodes reachable over one outgoing edge
+    fn successors(&self, node_id: NodeId) -> Vec<NodeId> {
+        let targets = self
+            .outgoing_edge_ids(node_id)
+            .into_iter()
+            .filter_map(|edge_id| self.edge_endpoints(edge_id).map(|(_, target)| target));
+        dedup(targets)
+    }
+
+    /// Get the nodes with an edge pointing at this node
+    fn predecessors(&self, node_id: NodeId) -> Vec<NodeId> {
+        let sources = self
+            .incoming_edge_ids(node_id)
+            .into_iter()
+            .filter_map(|edge_id| self.edge_endpoints(edge_id).map(|(source, _)| source));
+        dedup(sources)
+    }
+
+    /// Get the nodes connected to this node in either direction
+    fn neighbors(&self, node_id: NodeId) -> Vec<NodeId> {
+        dedup(
+            self.predecessors(node_id)
+                .into_iter()
+                .chain(self.successors(node_id)),
+        )
+    }
+
+    /// Get the degree of a node (in + out edges)
+    fn degree(&self, node_id: NodeId) -> usize {
+        self.incoming_edge_ids(node_id).len() + self.outgoing_edge_ids(node_id).len()
+    }
+
+    /// Nodes whose value satisfies the predicate, in `node_ids` order
+    fn find_nodes(&self, predicate: impl Fn(&Self::Node) -> bool) -> Vec<NodeId>
+    where
+        Self: Sized,
+    {
+        self.node_ids()
+            .filter(|node_id| {
+                self.node_value(*node_id)
+                    .is_some_and(|value| predicate(&value))
+            })
+            .collect()
+    }
+
+    /// Configure a traversal starting at `start`
+    fn traverse(&self, start: NodeId) -> Traversal<'_, Self>
+    where
+        Self: Sized,
+    {
+        Traversal::new(self, start)
+    }
+
+    /// Start a fluent query over nodes
+    fn query_nodes(&self) -> NodeQuery<'_, Self>
+    where
+        Self: Sized,
+    {
+        NodeQuery::new(self)
+    }
+
+    /// Start a fluent query over edges
+    fn query_edges(&self) -> EdgeQuery<'_, Self>
+    where
+        Self: Sized,
+    {
+        EdgeQuery::new(self)
+    }
+}
+
+fn dedup(ids: impl Iterator<Item = NodeId>) -> Vec<NodeId> {
+    let mut seen = HashSet::new();
+    ids.filter(|id| seen.insert(*id)).collect()
+}
+
+impl<N, E> GraphView for ContextGraph<N, E>
+where
+    N: Clone + Debug,
+    E: Clone + Debug,
+{
+    type Node = N;
+    type Edge = E;
+
+    fn graph_id(&self) -> ContextGraphId {
+        self.id
+    }
+
+    fn node_count(&self) -> usize {
+        ContextGraph::node_count(self)
+    }
+
+    fn edge_count(&self) -> usize {
+        ContextGraph::edge_count(self)
+    }
+
+    fn node_ids(&self) -> Box<dyn Iterator<Item = NodeId> + '_> {
+        Box::new(self.get_all_nodes().map(|(node_id, _)| node_id))
+    }
+
+    fn edge_ids(&self) -> Box<dyn Iterator<Item = EdgeId> + '_> {
+        Box::new(self.get_all_edges().map(|(edge_id, _)| edge_id))
+    }
+
+    fn contains_node(&self, node_id: NodeId) -> bool {
+        self.get_node(node_id).is_some()
+    }
+
+    fn node_value(&self, node_id: NodeId) -> Option<Cow<'_, N>> {
+        self.get_node_value(node_id).map(Cow::Borrowed)
+    }
+
+    fn edge_value(&self, edge_id: EdgeId) -> Option<Cow<'_, E>> {
+        self.get_edge_value(edge_id).map(Cow::Borrowed)
+    }
+
+    fn edge_endpoints(&self, edge_id: EdgeId) -> Option<(NodeId, NodeId)> {
+        self.get_edge(edge_id)
+            .map(|edge| (edge.source, edge.target))
+    }
+
+    fn node_entry(&self, node_id: NodeId) -> Option<Cow<'_, NodeEntry<N>>> {
+        self.get_node(node_id).map(Cow::Borrowed)
+    }
+
+    fn edge_entry(&self, edge_id: EdgeId) -> Option<Cow<'_, EdgeEntry<E>>> {
+        self.get_edge(edge_id).map(Cow::Borrowed)
+    }
+
+    fn outgoing_edge_ids(&self, node_id: NodeId) -> Vec<EdgeId> {
+        self.outgoing_edges(node_id).map(|(id, _)| id).collect()
+    }
+
+    fn incoming_edge_ids(&self, node_id: NodeId) -> Vec<EdgeId> {
+        self.incoming_edges(node_id).map(|(id, _)| id).collect()
+    }
+
+    fn successors(&self, node_id: NodeId) -> Vec<NodeId> {
+        ContextGraph::successors(self, node_id)
+    }
+
+    fn predecessors(&self, node_id: NodeId) -> Vec<NodeId> {
+        ContextGraph::predecessors(self, node_id)
+    }
+
+    fn neighbors(&self, node_id: NodeId) -> Vec<NodeId> {
+        ContextGraph::neighbors(self, node_id)
+    }
+
+    fn degree(&self, node_id: NodeId) -> usize {
+        ContextGraph::degree(self, node_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Written once against the view, so it also runs on snapshots
+    fn labeled_reachable<G: GraphView>(graph: &G, start: NodeId) -> Vec<NodeId> {
+        graph
+            .traverse(start)
+            .prune(|node| node.has_component::<Label>())
+            .bfs()
+            .map(|visit| visit.node_id)
+            .collect()
+    }
+
+    #[test]
+    fn test_context_graph_view() {
+        let mut graph = ContextGraph::<&str, ()>::new("View");
+        let a = graph.add_node("A");
+        let b = graph.add_node("B");
+        let c = graph.add_node("C");
+        graph.add_edge(a, b, ()).unwrap();
+        graph.add_edge(a, c, ()).unwrap();
+        let bc = graph.add_edge(b, c, ()).unwrap();
+        graph
+            .get_node_mut(b)
+            .unwrap()
+            .add_component(Label("Stop".to_string()))
+            .unwrap();
+
+        assert_eq!(labeled_reachable(&graph, a), vec![a, b, c]);
+        assert_eq!(labeled_reachable(&graph, b), vec![b]);
+        assert_eq!(graph.find_nodes(|value| *value != "B"), vec![a, c]);
+        assert_eq!(GraphView::neighbors(&graph, b), vec![a, c]);
+        assert!(matches!(graph.node_entry(b), Some(Cow::Borrowed(node)) if node.id == b));
+        assert_eq!(graph.edge_entry(bc).unwrap().source, b);
+
+        let view: &dyn GraphView<Node = &str, Edge = ()> = &graph;
+        assert_eq!(view.successors(a), vec![b, c]);
+
+        // Removal moves the last node into the freed slot; edge lists keep order
+        let d = graph.add_node("D");
+        graph.add_edge(a, d, ()).unwrap();
+        graph.remove_node(b);
+        assert_eq!(graph.node_ids().collect::<Vec<_>>(), vec![a, d, c]);
+        assert_eq!(GraphView::successors(&graph, a), vec![c, d]);
+    }
+}